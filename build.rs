@@ -0,0 +1,27 @@
+use std::process::Command;
+
+/// Exposes the commit the backend is built from as `KNOWMARK_GIT_HASH`.
+///
+/// The hash can be provided through `KNOWMARK_GIT_HASH` environment variable when building
+/// outside of a git checkout, e.g. in a container. Otherwise it's "unknown".
+fn main() {
+    println!("cargo:rerun-if-env-changed=KNOWMARK_GIT_HASH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+
+    let hash = std::env::var("KNOWMARK_GIT_HASH")
+        .ok()
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "--short", "HEAD"])
+                .output()
+                .ok()
+                .filter(|it| it.status.success())
+                .and_then(|it| String::from_utf8(it.stdout).ok())
+        })
+        .map(|it| it.trim().to_string())
+        .filter(|it| !it.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=KNOWMARK_GIT_HASH={}", hash);
+}
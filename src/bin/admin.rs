@@ -0,0 +1,522 @@
+//! Command line tool for operations tasks.
+//!
+//! Results are printed to stdout (IDs one per line, quizzes as JSON), while logs and errors go to
+//! stderr. Exit code is 0 on success, 1 if the command failed and 2 if it was used incorrectly.
+
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::process::exit;
+use std::str::FromStr;
+
+use bson::doc;
+use knowmark_backend::data::quiz::db::QuizRepository;
+use knowmark_backend::data::quiz::{Quiz, QUIZ_COLLECTION_NAME};
+use knowmark_backend::data::user::audit::{AuditAction, AuditEntry, UserAuditDbExt};
+use knowmark_backend::data::user::db::{check_email, UserRepository, UserSignupData};
+use knowmark_backend::data::user::policy::normalize_password;
+use knowmark_backend::data::user::{PasswordHash, User};
+use knowmark_backend::data::{id, migration};
+use knowmark_backend::error::BackendError;
+use knowmark_backend::resp::l10n::Language;
+use knowmark_backend::resp::problem::Problem;
+use knowmark_backend::resp::validation::{Validate, Validator};
+use knowmark_backend::role::Role;
+#[cfg(feature = "generate-security")]
+use knowmark_backend::security::KeySet;
+use knowmark_backend::security::Security;
+use knowmark_backend::settings::Settings;
+use mongodb::Database;
+use rocket::futures::TryStreamExt;
+use tracing::Level;
+use tracing_subscriber::FmtSubscriber;
+use uuid::Uuid;
+
+const USAGE: &str = "Usage: knowmark-admin <command> [arguments]
+
+Commands:
+  user create <username> <email> [role]  Create a user, password is read from stdin
+  user password <user>                   Set password of a user, password is read from stdin
+  user role <user> <role> [reason]       Change role of a user
+  keys generate                          Generate missing password salt and JWT keys
+  keys rotate                            Replace JWT keys, signing out all users
+  migrate [--dry-run]                    Apply pending database migrations, or only list them
+  quiz export [author]                   Print quizzes as a JSON array
+  quiz import [file]                     Store quizzes from a JSON array in file or stdin
+
+<user> is a user ID, username or email. <role> is one of none, normal, author or admin.
+User commands print the user ID and quiz import prints IDs of stored quizzes. Migrations are
+printed as their version and name, followed by indented steps with --dry-run.";
+
+/// Reason recorded for changes made with this tool.
+const DEFAULT_REASON: &str = "Changed with knowmark-admin.";
+
+#[derive(Debug)]
+enum Failure {
+    Usage,
+    Error(String),
+}
+
+impl From<Problem> for Failure {
+    /// Problems are described like API clients see them, with their detail and field errors.
+    fn from(problem: Problem) -> Self {
+        let mut message = problem.title.clone();
+        if let Some(detail) = &problem.detail {
+            let detail = Language::English.format(detail, &problem.detail_args);
+            message = format!("{}: {}", message, detail);
+        }
+        if let Some(errors) = problem.body.get("errors") {
+            message = format!("{} {}", message, errors);
+        }
+        Failure::Error(message)
+    }
+}
+
+impl From<BackendError> for Failure {
+    fn from(e: BackendError) -> Self {
+        Failure::Error(e.to_string())
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        Failure::Error(e.to_string())
+    }
+}
+
+impl From<mongodb::error::Error> for Failure {
+    fn from(e: mongodb::error::Error) -> Self {
+        Failure::Error(e.to_string())
+    }
+}
+
+impl From<serde_json::Error> for Failure {
+    fn from(e: serde_json::Error) -> Self {
+        Failure::Error(e.to_string())
+    }
+}
+
+/// Command given on the command line.
+#[derive(Debug, PartialEq)]
+enum Command {
+    UserCreate {
+        username: String,
+        email: String,
+        role: Role,
+    },
+    UserPassword {
+        user: String,
+    },
+    UserRole {
+        user: String,
+        role: Role,
+        reason: String,
+    },
+    KeysGenerate,
+    KeysRotate,
+    Migrate {
+        dry_run: bool,
+    },
+    QuizExport {
+        author: Option<String>,
+    },
+    QuizImport {
+        file: Option<String>,
+    },
+}
+
+impl Command {
+    fn parse(args: &[String]) -> Result<Command, Failure> {
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let command = match args.as_slice() {
+            ["user", "create", username, email] => Command::UserCreate {
+                username: username.to_string(),
+                email: email.to_string(),
+                role: Role::Normal,
+            },
+            ["user", "create", username, email, role] => Command::UserCreate {
+                username: username.to_string(),
+                email: email.to_string(),
+                role: Role::from_str(role)?,
+            },
+            ["user", "password", user] => Command::UserPassword {
+                user: user.to_string(),
+            },
+            ["user", "role", user, role] => Command::UserRole {
+                user: user.to_string(),
+                role: Role::from_str(role)?,
+                reason: DEFAULT_REASON.to_string(),
+            },
+            ["user", "role", user, role, reason] => Command::UserRole {
+                user: user.to_string(),
+                role: Role::from_str(role)?,
+                reason: reason.to_string(),
+            },
+            ["keys", "generate"] => Command::KeysGenerate,
+            ["keys", "rotate"] => Command::KeysRotate,
+            ["migrate"] => Command::Migrate { dry_run: false },
+            ["migrate", "--dry-run"] => Command::Migrate { dry_run: true },
+            ["quiz", "export"] => Command::QuizExport { author: None },
+            ["quiz", "export", author] => Command::QuizExport {
+                author: Some(author.to_string()),
+            },
+            ["quiz", "import"] => Command::QuizImport { file: None },
+            ["quiz", "import", file] => Command::QuizImport {
+                file: Some(file.to_string()),
+            },
+            _ => return Err(Failure::Usage),
+        };
+        Ok(command)
+    }
+}
+
+type CommandResult = Result<(), Failure>;
+
+/// Reads the first line of stdin, so passwords can be piped or typed.
+fn read_password() -> Result<String, Failure> {
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    let password = line.trim_end_matches(&['\r', '\n'][..]).to_string();
+    if password.is_empty() {
+        return Err(Failure::Error(
+            "password must be provided on stdin".to_string(),
+        ));
+    }
+    Ok(password)
+}
+
+fn check(validator: Validator<'_>) -> CommandResult {
+    if validator.is_valid() {
+        return Ok(());
+    }
+
+    let errors: Vec<String> = validator
+        .errors()
+        .iter()
+        .map(|it| serde_json::to_string(it).unwrap_or_default())
+        .collect();
+    Err(Failure::Error(format!(
+        "invalid fields: {}",
+        errors.join(", ")
+    )))
+}
+
+async fn find_user(db: &Database, user: &str) -> Result<User, Failure> {
+    let found = if let Ok(id) = Uuid::from_str(user) {
+        db.get_user(id).await?
+    } else if user.contains('@') {
+        db.find_user_by_email(user).await?
+    } else {
+        db.find_user_by_username(user).await?
+    };
+
+    found.ok_or_else(|| Failure::Error(format!("user {} doesn't exist", user)))
+}
+
+async fn user_create(
+    db: &Database,
+    settings: &Settings,
+    security: &Security,
+    username: &str,
+    email: &str,
+    role: Role,
+) -> CommandResult {
+    let password = read_password()?;
+
+    let policy = &settings.auth.credentials;
+    let mut validator = Validator::new();
+    check_email(&mut validator, "email", email);
+    policy.check_username(&mut validator, "username", username);
+    policy.check_password(&mut validator, "password", &password, &[username]);
+    check(validator)?;
+
+    let signup = UserSignupData {
+        email: Cow::Borrowed(email),
+        username: Cow::Borrowed(username),
+        password: Cow::Owned(password),
+    };
+    let no_admins: &[String] = &[];
+    let (_, user) = db.create_user(signup, security.salt, no_admins).await?;
+
+    if role != user.user_role {
+        db.update_role(user.id, role).await?;
+        let action = AuditAction::RoleChanged {
+            from: user.user_role,
+            to: role,
+        };
+        db.record_audit(&AuditEntry::new(
+            user.id,
+            Uuid::nil(),
+            action,
+            DEFAULT_REASON,
+        ))
+        .await?;
+    }
+
+    println!("{}", user.id);
+    Ok(())
+}
+
+async fn user_password(
+    db: &Database,
+    settings: &Settings,
+    security: &Security,
+    user: &str,
+) -> CommandResult {
+    let user = find_user(db, user).await?;
+    let password = read_password()?;
+
+    let mut validator = Validator::new();
+    settings.auth.credentials.check_password(
+        &mut validator,
+        "password",
+        &password,
+        &[user.username.as_str(), user.email.as_str()],
+    );
+    check(validator)?;
+
+    let pw_hash = PasswordHash::new(normalize_password(&password), security.salt);
+    db.update_password(user.id, pw_hash).await?;
+
+    println!("{}", user.id);
+    Ok(())
+}
+
+async fn user_role(db: &Database, user: &str, role: Role, reason: &str) -> CommandResult {
+    let user = find_user(db, user).await?;
+
+    if role != user.user_role {
+        db.update_role(user.id, role).await?;
+        let action = AuditAction::RoleChanged {
+            from: user.user_role,
+            to: role,
+        };
+        db.record_audit(&AuditEntry::new(user.id, Uuid::nil(), action, reason))
+            .await?;
+    }
+
+    println!("{}", user.id);
+    Ok(())
+}
+
+fn keys(settings: &Settings, rotate: bool) -> CommandResult {
+    let dir = &settings.security.dir;
+    match rotate {
+        false => {
+            Security::load_from(dir);
+        }
+        #[cfg(feature = "generate-security")]
+        true => {
+            std::fs::create_dir_all(dir)?;
+            KeySet::generate(dir);
+        }
+        #[cfg(not(feature = "generate-security"))]
+        true => {
+            return Err(Failure::Error(
+                "keys can only be generated with generate-security feature".to_string(),
+            ));
+        }
+    }
+
+    for file in Security::FILES {
+        println!("{}", dir.join(file).display());
+    }
+    Ok(())
+}
+
+async fn migrate(db: &Database, dry_run: bool) -> CommandResult {
+    for it in migration::migrate(db, dry_run).await? {
+        println!("{}\t{}", it.version, it.name);
+        if dry_run {
+            for step in &it.steps {
+                println!("  {}", step.describe());
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn quiz_export(db: &Database, author: Option<&str>) -> CommandResult {
+    let filter = match author {
+        None => doc! {},
+        Some(author) => id::eq("author", find_user(db, author).await?.id),
+    };
+
+    let quizzes: Vec<Quiz> = db
+        .collection::<Quiz>(QUIZ_COLLECTION_NAME)
+        .find(filter, None)
+        .await?
+        .try_collect()
+        .await?;
+
+    println!("{}", serde_json::to_string_pretty(&quizzes)?);
+    Ok(())
+}
+
+async fn quiz_import(db: &Database, file: Option<&str>) -> CommandResult {
+    let content = match file {
+        None => {
+            let mut content = String::new();
+            io::stdin().read_to_string(&mut content)?;
+            content
+        }
+        Some(file) => std::fs::read_to_string(file)?,
+    };
+
+    let mut quizzes: Vec<Quiz> = serde_json::from_str(&content)?;
+    for quiz in &mut quizzes {
+        quiz.validate()
+            .map_err(|e| Failure::Error(format!("quiz {}: {}", quiz.id, e)))?;
+        quiz.normalize_tags();
+    }
+
+    for quiz in &quizzes {
+        db.create_quiz(quiz).await?;
+        println!("{}", quiz.id);
+    }
+    Ok(())
+}
+
+async fn run(args: &[String]) -> CommandResult {
+    let command = Command::parse(args)?;
+    let settings = knowmark_backend::load_settings()?;
+
+    match command {
+        Command::KeysGenerate => return keys(&settings, false),
+        Command::KeysRotate => return keys(&settings, true),
+        _ => {}
+    }
+
+    let db = knowmark_backend::connect(&settings.database, None).await?;
+    match command {
+        Command::UserCreate {
+            username,
+            email,
+            role,
+        } => {
+            let security = Security::load_from(&settings.security.dir);
+            user_create(&db, &settings, &security, &username, &email, role).await
+        }
+        Command::UserPassword { user } => {
+            let security = Security::load_from(&settings.security.dir);
+            user_password(&db, &settings, &security, &user).await
+        }
+        Command::UserRole { user, role, reason } => user_role(&db, &user, role, &reason).await,
+        Command::Migrate { dry_run } => migrate(&db, dry_run).await,
+        Command::QuizExport { author } => quiz_export(&db, author.as_deref()).await,
+        Command::QuizImport { file } => quiz_import(&db, file.as_deref()).await,
+        Command::KeysGenerate | Command::KeysRotate => Ok(()),
+    }
+}
+
+#[rocket::main]
+async fn main() {
+    let subscriber = FmtSubscriber::builder()
+        .with_max_level(Level::WARN)
+        .with_writer(io::stderr)
+        .finish();
+    if let Err(err) = tracing::subscriber::set_global_default(subscriber) {
+        eprintln!("Unable to set global logger: {}", err);
+    }
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.is_empty() || args[0] == "--help" || args[0] == "-h" {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args).await {
+        Ok(()) => {}
+        Err(Failure::Usage) => {
+            eprintln!("{}", USAGE);
+            exit(2);
+        }
+        Err(Failure::Error(err)) => {
+            eprintln!("error: {}", err);
+            exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::http::Status;
+
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, Failure> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        Command::parse(&args)
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        assert_eq!(
+            parse("user create jane jane@example.com Author").unwrap(),
+            Command::UserCreate {
+                username: "jane".to_string(),
+                email: "jane@example.com".to_string(),
+                role: Role::Author,
+            }
+        );
+        assert_eq!(
+            parse("user role jane admin").unwrap(),
+            Command::UserRole {
+                user: "jane".to_string(),
+                role: Role::Admin,
+                reason: DEFAULT_REASON.to_string(),
+            }
+        );
+        assert_eq!(
+            parse("migrate --dry-run").unwrap(),
+            Command::Migrate { dry_run: true }
+        );
+        assert_eq!(
+            parse("quiz export").unwrap(),
+            Command::QuizExport { author: None }
+        );
+        assert_eq!(parse("keys rotate").unwrap(), Command::KeysRotate);
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        for args in [
+            "",
+            "user",
+            "user create jane",
+            "keys",
+            "migrate --force",
+            "quiz import a b",
+        ] {
+            assert!(
+                matches!(parse(args), Err(Failure::Usage)),
+                "{:?} was accepted",
+                args
+            );
+        }
+
+        match parse("user role jane owner") {
+            Err(Failure::Error(message)) => assert!(message.contains("owner"), "{}", message),
+            other => panic!("unknown role was accepted: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn problems_include_detail_and_errors() {
+        let mut problem = Problem::new_untyped(Status::BadRequest, "Invalid fields.");
+        problem
+            .detail("User {user} can't be changed.")
+            .arg("user", "jane")
+            .insert_json_value(
+                "errors",
+                serde_json::json!([{ "field": "email", "code": "bad_format" }]),
+            );
+
+        match Failure::from(problem) {
+            Failure::Error(message) => assert_eq!(
+                message,
+                r#"Invalid fields.: User jane can't be changed. [{"code":"bad_format","field":"email"}]"#
+            ),
+            Failure::Usage => panic!("problem reported as usage failure"),
+        }
+    }
+}
@@ -1,25 +1,20 @@
-use tracing::Level;
-
 #[rocket::main]
 async fn main() {
-    #[cfg(debug_assertions)]
-    let level = Some(Level::DEBUG);
-    #[cfg(not(debug_assertions))]
-    let level = Some(Level::INFO);
+    if std::env::args().any(|it| it == "--check-config") {
+        std::process::exit(knowmark_backend::check_config());
+    }
 
-    let r = match knowmark_backend::create(level).await {
+    // Logger might not be installed yet if settings couldn't be loaded, so errors go to stderr.
+    let r = match knowmark_backend::create().await {
         Ok(it) => it,
         Err(err) => {
-            tracing::error!("Unable to initialize backend: {}", err);
-            std::panic::panic_any(err);
+            eprintln!("Unable to initialize backend: {}", err);
+            std::process::exit(1);
         }
     };
 
-    match r.launch().await {
-        Ok(_) => {}
-        Err(err) => {
-            tracing::error!("Error launching server: {}", err);
-            std::panic::panic_any(err);
-        }
-    };
+    if let Err(err) = r.launch().await {
+        tracing::error!("Error launching server: {}", err);
+        std::process::exit(1);
+    }
 }
@@ -1,6 +1,4 @@
 use std::ffi::OsString;
-use tracing::Level;
-use tracing_subscriber::FmtSubscriber;
 #[cfg(target_os = "windows")]
 use windows_service::{define_windows_service, service_dispatcher};
 
@@ -10,15 +8,13 @@ define_windows_service!(ffi_service_main, knowmark_service_main);
 fn knowmark_service_main(arguments: Vec<OsString>) {
     let rt = tokio::runtime::Runtime::new().unwrap();
     rt.block_on(async {
-        let subscriber = FmtSubscriber::builder()
-            .with_max_level(Level::TRACE)
-            .finish();
-
-        if let Err(err) = tracing::subscriber::set_global_default(subscriber) {
-            eprintln!("{}", err);
+        let r = match knowmark_backend::create().await {
+            Ok(it) => it,
+            Err(err) => {
+                eprintln!("Unable to initialize backend: {}", err);
+                return;
+            }
         };
-
-        let r = knowmark_backend::create(Some(Level::INFO)).await;
         let r = match r.launch().await {
             Ok(r) => r,
             Err(e) => {
@@ -1,5 +1,11 @@
-use super::ClassRole;
+use super::{Class, ClassRole, CLASS_COLLECTION_NAME};
+use crate::data::id;
+use crate::data::user::profile::{check_tags, Tag};
+use crate::error::BackendError;
+use crate::resp::problem::Problem;
+use crate::resp::validation::{Validate, Validator};
 
+use mongodb::Database;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
@@ -8,6 +14,23 @@ use uuid::Uuid;
 pub struct ClassCreateData {
     pub name: String,
     pub owner: Option<Uuid>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+}
+
+const MAX_CLASS_NAME_LENGTH: usize = 64;
+const MAX_CLASS_TAGS: usize = 16;
+
+impl Validate for ClassCreateData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        validator.length(
+            "name",
+            self.name.trim().chars().count(),
+            1,
+            MAX_CLASS_NAME_LENGTH,
+        );
+        check_tags(validator, "tags", &self.tags, MAX_CLASS_TAGS);
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, ToSchema)]
@@ -16,3 +39,38 @@ pub struct AddUserData {
     pub user: Uuid,
     pub role: ClassRole,
 }
+
+/// Stored classes.
+pub trait ClassRepository {
+    async fn create_class(&self, class: &Class) -> Result<(), Problem>;
+
+    async fn get_class(&self, id: Uuid) -> Result<Option<Class>, Problem>;
+
+    /// Removes a class and returns whether it existed.
+    async fn delete_class(&self, id: Uuid) -> Result<bool, Problem>;
+}
+
+impl ClassRepository for Database {
+    async fn create_class(&self, class: &Class) -> Result<(), Problem> {
+        self.collection(CLASS_COLLECTION_NAME)
+            .insert_one(bson::to_document(class).map_err(BackendError::from)?, None)
+            .await
+            .map_err(Problem::from)?;
+        Ok(())
+    }
+
+    async fn get_class(&self, id: Uuid) -> Result<Option<Class>, Problem> {
+        self.collection::<Class>(CLASS_COLLECTION_NAME)
+            .find_one(id::by_id(id), None)
+            .await
+            .map_err(Problem::from)
+    }
+
+    async fn delete_class(&self, id: Uuid) -> Result<bool, Problem> {
+        self.collection::<Class>(CLASS_COLLECTION_NAME)
+            .delete_one(id::by_id(id), None)
+            .await
+            .map(|it| it.deleted_count > 0)
+            .map_err(Problem::from)
+    }
+}
@@ -2,9 +2,11 @@ use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::data::user::profile::Tag;
+
 pub mod db;
 
-const CLASS_COLLECTION_NAME: &str = "classes";
+pub static CLASS_COLLECTION_NAME: &str = "classes";
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum ClassRole {
@@ -15,6 +17,7 @@ pub enum ClassRole {
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ClassParticipant {
+    #[serde(with = "crate::data::id::binary")]
     pub user_id: Uuid,
     pub class_role: ClassRole,
     pub visible: bool,
@@ -25,11 +28,29 @@ pub struct Class {
     #[serde(
         default = "Uuid::new_v4",
         rename = "_id",
-        with = "bson::serde_helpers::uuid_1_as_binary"
+        with = "crate::data::id::binary"
     )]
-    id: Uuid,
-    name: String,
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
 
     #[serde(default)]
-    participants: Vec<ClassParticipant>,
+    pub participants: Vec<ClassParticipant>,
+}
+
+impl Class {
+    /// Creates a class with `owner` as its only teacher.
+    pub fn new(name: impl ToString, tags: Vec<Tag>, owner: Uuid) -> Class {
+        Class {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            tags,
+            participants: vec![ClassParticipant {
+                user_id: owner,
+                class_role: ClassRole::Teacher,
+                visible: true,
+            }],
+        }
+    }
 }
@@ -0,0 +1,166 @@
+//! Storage format of quiz dates.
+//!
+//! Quiz dates are stored as RFC 3339 strings, the same form they have in JSON. Stored strings are
+//! compared by characters, so every date is written in UTC with millisecond precision, e.g.
+//! `2023-01-31T12:00:00.000Z`, which keeps their order the same as the order of dates. Model
+//! fields are serialized with [`rfc3339`] or [`rfc3339_option`] and filters build values with
+//! [`to_bson`], so queries compare dates in the same form.
+
+use bson::{Bson, Document};
+use chrono::{DateTime, SecondsFormat, Utc};
+
+/// Date in its stored form.
+pub fn to_string(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Date value in its stored form, for filters.
+pub fn to_bson(date: &DateTime<Utc>) -> Bson {
+    Bson::String(to_string(date))
+}
+
+/// Reads a date in any RFC 3339 form.
+pub fn parse(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|it| it.with_timezone(&Utc))
+}
+
+/// Serde helper for date fields, `#[serde(with = "crate::data::date::rfc3339")]`.
+pub mod rfc3339 {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        date: &DateTime<Utc>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&super::to_string(date))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DateTime<Utc>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        super::parse(&value)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid RFC 3339 date: {}", value)))
+    }
+}
+
+/// Serde helper for optional date fields, `#[serde(with = "crate::data::date::rfc3339_option")]`.
+pub mod rfc3339_option {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_some(&super::to_string(date)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => super::parse(&value).map(Some).ok_or_else(|| {
+                serde::de::Error::custom(format!("invalid RFC 3339 date: {}", value))
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Rewrites dates at `path` of `document` into their stored form. Paths pass through arrays, e.g.
+/// `["participants", "started_on"]` converts `started_on` of every participant.
+///
+/// Returns whether anything changed.
+pub fn normalize_field(document: &mut Document, path: &[&str]) -> bool {
+    let (key, rest) = match path.split_first() {
+        Some(it) => it,
+        None => return false,
+    };
+    match document.get_mut(*key) {
+        Some(value) => normalize_value(value, rest),
+        None => false,
+    }
+}
+
+fn normalize_value(value: &mut Bson, path: &[&str]) -> bool {
+    match value {
+        Bson::Array(items) => items
+            .iter_mut()
+            .fold(false, |changed, it| normalize_value(it, path) || changed),
+        Bson::Document(document) if !path.is_empty() => normalize_field(document, path),
+        Bson::String(text) if path.is_empty() => match parse(text).map(|it| to_string(&it)) {
+            Some(normalized) if normalized != *text => {
+                *text = normalized;
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+
+    use super::*;
+
+    #[test]
+    fn stored_dates_sort_like_dates() {
+        // In order of time, but not as strings
+        let dates = [
+            "2023-01-31T12:00:00Z",
+            "2023-01-31T13:00:00.010+01:00",
+            "2023-01-31T12:00:00.5Z",
+            "2023-01-31T12:00:01Z",
+        ];
+        let mut sorted = dates.to_vec();
+        sorted.sort_unstable();
+        assert_ne!(sorted, dates);
+
+        let stored: Vec<String> = dates
+            .iter()
+            .map(|it| to_string(&parse(it).unwrap()))
+            .collect();
+        assert_eq!(
+            stored,
+            [
+                "2023-01-31T12:00:00.000Z",
+                "2023-01-31T12:00:00.010Z",
+                "2023-01-31T12:00:00.500Z",
+                "2023-01-31T12:00:01.000Z",
+            ]
+        );
+    }
+
+    #[test]
+    fn older_dates_are_normalized() {
+        let mut document = doc! {
+            "created": "2023-01-31T12:00:00Z",
+            "close_on": null,
+            "participants": [{ "started_on": "2023-01-31T12:00:00.123456Z" }],
+        };
+
+        assert!(normalize_field(&mut document, &["created"]));
+        assert!(!normalize_field(&mut document, &["close_on"]));
+        assert!(normalize_field(
+            &mut document,
+            &["participants", "started_on"]
+        ));
+        assert_eq!(
+            document,
+            doc! {
+                "created": "2023-01-31T12:00:00.000Z",
+                "close_on": null,
+                "participants": [{ "started_on": "2023-01-31T12:00:00.123Z" }],
+            }
+        );
+    }
+}
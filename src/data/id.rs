@@ -0,0 +1,156 @@
+//! Storage format of UUIDs.
+//!
+//! Every stored UUID is a BSON binary with UUID subtype (4). Model fields are serialized with
+//! [`binary`] and filters build values with [`to_bson`], so queries match stored fields. JSON
+//! representation stays a hyphenated string.
+
+use bson::spec::BinarySubtype;
+use bson::{doc, Bson, Document};
+use uuid::Uuid;
+
+/// Serde helper for UUID fields of stored models, `#[serde(with = "crate::data::id::binary")]`.
+pub use bson::serde_helpers::uuid_1_as_binary as binary;
+
+/// UUID value in its stored form.
+pub fn to_bson(id: Uuid) -> Bson {
+    Bson::from(bson::Uuid::from(id))
+}
+
+/// Reads a stored UUID.
+pub fn from_bson(value: &Bson) -> Option<Uuid> {
+    match value {
+        Bson::Binary(bin) if bin.subtype == BinarySubtype::Uuid => {
+            Uuid::from_slice(&bin.bytes).ok()
+        }
+        _ => None,
+    }
+}
+
+/// Reads a UUID in any format older versions stored it in: generic or legacy UUID binary, or a
+/// string.
+pub fn from_legacy_bson(value: &Bson) -> Option<Uuid> {
+    match value {
+        Bson::Binary(bin)
+            if matches!(
+                bin.subtype,
+                BinarySubtype::Uuid | BinarySubtype::UuidOld | BinarySubtype::Generic
+            ) =>
+        {
+            Uuid::from_slice(&bin.bytes).ok()
+        }
+        Bson::String(it) => Uuid::parse_str(it).ok(),
+        _ => None,
+    }
+}
+
+/// Filter matching `field` equal to `id`.
+pub fn eq(field: &str, id: Uuid) -> Document {
+    doc! { field: to_bson(id) }
+}
+
+/// Filter matching document with `id`.
+pub fn by_id(id: Uuid) -> Document {
+    eq("_id", id)
+}
+
+/// Rewrites UUIDs at `path` of `document` into their stored form. Paths pass through arrays, e.g.
+/// `["participants", "user_id"]` converts `user_id` of every participant.
+///
+/// Returns whether anything changed.
+pub fn normalize_field(document: &mut Document, path: &[&str]) -> bool {
+    let (key, rest) = match path.split_first() {
+        Some(it) => it,
+        None => return false,
+    };
+    match document.get_mut(*key) {
+        Some(value) => normalize_value(value, rest),
+        None => false,
+    }
+}
+
+fn normalize_value(value: &mut Bson, path: &[&str]) -> bool {
+    match value {
+        Bson::Array(items) => items
+            .iter_mut()
+            .fold(false, |changed, it| normalize_value(it, path) || changed),
+        Bson::Document(document) if !path.is_empty() => normalize_field(document, path),
+        _ if path.is_empty() => match from_legacy_bson(value) {
+            Some(id) if from_bson(value).is_none() => {
+                *value = to_bson(id);
+                true
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::Binary;
+
+    use super::*;
+    use crate::data::quiz::{Quiz, QuizParticipant};
+
+    fn example_quiz(author: Uuid, participant: Uuid) -> Quiz {
+        serde_json::from_value(serde_json::json!({
+            "name": "Example",
+            "author": author.to_string(),
+            "parts": [{ "Content": { "title": "Intro", "text": "Hello" } }],
+            "participants": [{
+                "user_id": participant.to_string(),
+                "started_on": "2023-01-31T12:00:00Z",
+            }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn stored_ids_match_filters() {
+        let (author, participant) = (Uuid::new_v4(), Uuid::new_v4());
+        let quiz = example_quiz(author, participant);
+
+        let document = bson::to_document(&quiz).unwrap();
+        assert_eq!(document.get("_id"), Some(&to_bson(quiz.id)));
+        assert_eq!(document.get("author"), Some(&to_bson(author)));
+        let participants = document.get_array("participants").unwrap();
+        let first = participants[0].as_document().unwrap();
+        assert_eq!(first.get("user_id"), Some(&to_bson(participant)));
+
+        let stored: Quiz = bson::from_document(document).unwrap();
+        assert_eq!(stored.id, quiz.id);
+        assert_eq!(stored.author, author);
+        let QuizParticipant { user_id, .. } = &stored.participants[0];
+        assert_eq!(*user_id, participant);
+
+        let json = serde_json::to_value(&stored).unwrap();
+        assert_eq!(json["author"], serde_json::json!(author.to_string()));
+    }
+
+    #[test]
+    fn legacy_ids_are_normalized() {
+        let id = Uuid::new_v4();
+        let generic = Bson::Binary(Binary {
+            subtype: BinarySubtype::Generic,
+            bytes: id.as_bytes().to_vec(),
+        });
+        let mut document = doc! {
+            "author": id.to_string(),
+            "participants": [{ "user_id": generic }, { "user_id": to_bson(id) }],
+        };
+
+        assert!(normalize_field(&mut document, &["author"]));
+        assert!(normalize_field(&mut document, &["participants", "user_id"]));
+        assert!(!normalize_field(
+            &mut document,
+            &["participants", "user_id"]
+        ));
+        assert_eq!(
+            document,
+            doc! {
+                "author": to_bson(id),
+                "participants": [{ "user_id": to_bson(id) }, { "user_id": to_bson(id) }],
+            }
+        );
+    }
+}
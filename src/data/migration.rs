@@ -0,0 +1,451 @@
+//! Versioned changes to the database.
+//!
+//! Migrations are applied in order of their versions and every applied one is recorded in
+//! [`MIGRATION_COLLECTION_NAME`] collection, so each runs only once. Released migrations must not
+//! be changed, changes to stored data or indexes are added as a new migration instead.
+
+use std::collections::HashSet;
+
+use bson::{doc, Document};
+use chrono::{DateTime, Utc};
+use mongodb::error::{ErrorKind, WriteFailure};
+use mongodb::options::{Collation, CollationStrength, IndexOptions};
+use mongodb::{Database, IndexModel};
+use rocket::futures::future::BoxFuture;
+use rocket::futures::TryStreamExt;
+
+use crate::data::class::CLASS_COLLECTION_NAME;
+use crate::data::quiz::attempt::ATTEMPT_COLLECTION_NAME;
+use crate::data::quiz::{PARTICIPANT_COLLECTION_NAME, PART_COLLECTION_NAME, QUIZ_COLLECTION_NAME};
+use crate::data::user::audit::USER_AUDIT_COLLECTION_NAME;
+use crate::data::user::db::{USER_COLLECTION_NAME, USER_SETTINGS_COLLECTION_NAME};
+use crate::data::user::token::USER_TOKEN_COLLECTION_NAME;
+use crate::data::{date, id};
+
+pub static MIGRATION_COLLECTION_NAME: &str = "_migrations";
+
+/// Changes a migration makes, applied in order.
+pub enum Step {
+    /// Creates a collection unless it already exists.
+    CreateCollection(&'static str),
+    /// Creates indexes, existing indexes with the same definition are left as they are.
+    CreateIndexes {
+        collection: &'static str,
+        indexes: Vec<IndexModel>,
+    },
+    /// Changes stored data.
+    Run {
+        description: &'static str,
+        run: fn(&Database) -> BoxFuture<'_, Result<(), mongodb::error::Error>>,
+    },
+}
+
+impl Step {
+    pub fn describe(&self) -> String {
+        match self {
+            Step::CreateCollection(name) => format!("create collection {}", name),
+            Step::CreateIndexes {
+                collection,
+                indexes,
+            } => {
+                let names: Vec<String> = indexes
+                    .iter()
+                    .map(|it| {
+                        it.options
+                            .as_ref()
+                            .and_then(|it| it.name.clone())
+                            .unwrap_or_else(|| it.keys.to_string())
+                    })
+                    .collect();
+                format!("create indexes {} on {}", names.join(", "), collection)
+            }
+            Step::Run { description, .. } => description.to_string(),
+        }
+    }
+
+    async fn apply(&self, db: &Database) -> Result<(), mongodb::error::Error> {
+        match self {
+            Step::CreateCollection(name) => {
+                let existing = db.list_collection_names(doc! { "name": *name }).await?;
+                if existing.is_empty() {
+                    db.create_collection(*name, None).await?;
+                }
+            }
+            Step::CreateIndexes {
+                collection,
+                indexes,
+            } => {
+                db.collection::<bson::Document>(collection)
+                    .create_indexes(indexes.clone(), None)
+                    .await?;
+            }
+            Step::Run { run, .. } => run(db).await?,
+        }
+        Ok(())
+    }
+}
+
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    pub steps: Vec<Step>,
+}
+
+impl Migration {
+    pub fn new(version: u32, name: &'static str) -> Migration {
+        Migration {
+            version,
+            name,
+            steps: vec![],
+        }
+    }
+
+    pub fn step(mut self, step: Step) -> Migration {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn create_collection(self, name: &'static str) -> Migration {
+        self.step(Step::CreateCollection(name))
+    }
+
+    pub fn create_indexes(self, collection: &'static str, indexes: Vec<IndexModel>) -> Migration {
+        self.step(Step::CreateIndexes {
+            collection,
+            indexes,
+        })
+    }
+}
+
+/// All migrations, ordered by version.
+pub fn migrations() -> Vec<Migration> {
+    let mut initial = Migration::new(1, "Create collections and indexes");
+    for name in [
+        USER_COLLECTION_NAME,
+        USER_SETTINGS_COLLECTION_NAME,
+        USER_TOKEN_COLLECTION_NAME,
+        USER_AUDIT_COLLECTION_NAME,
+        QUIZ_COLLECTION_NAME,
+        PART_COLLECTION_NAME,
+        PARTICIPANT_COLLECTION_NAME,
+        CLASS_COLLECTION_NAME,
+    ] {
+        initial = initial.create_collection(name);
+    }
+    for (collection, indexes) in initial_indexes() {
+        initial = initial.create_indexes(collection, indexes);
+    }
+
+    let uuids = Migration::new(2, "Store UUIDs as binary subtype 4").step(Step::Run {
+        description: "convert UUID fields stored as generic binary or strings",
+        run: |db| Box::pin(normalize_uuids(db)),
+    });
+
+    let attempts = Migration::new(3, "Create quiz attempts collection")
+        .create_collection(ATTEMPT_COLLECTION_NAME)
+        .create_indexes(
+            ATTEMPT_COLLECTION_NAME,
+            vec![IndexModel::builder()
+                .keys(doc! { "quiz": 1, "user": 1 })
+                .build()],
+        );
+
+    let dates = Migration::new(4, "Store quiz dates with millisecond precision").step(Step::Run {
+        description: "rewrite quiz dates as UTC with millisecond precision",
+        run: |db| Box::pin(normalize_dates(db)),
+    });
+
+    vec![initial, uuids, attempts, dates]
+}
+
+/// Indexes created by the initial migration.
+///
+/// Index definitions of migrations are written out in them, so an applied migration can't be
+/// changed by later changes to the code that uses the indexes.
+fn initial_indexes() -> Vec<(&'static str, Vec<IndexModel>)> {
+    let unique = |name: &str| {
+        IndexOptions::builder()
+            .name(name.to_string())
+            .unique(true)
+            .collation(
+                Collation::builder()
+                    .locale("en")
+                    .strength(CollationStrength::Secondary)
+                    .build(),
+            )
+            .build()
+    };
+    let tags = || {
+        IndexModel::builder()
+            .keys(doc! { "tags": 1 })
+            .options(IndexOptions::builder().name("tags".to_string()).build())
+            .build()
+    };
+    // Each collection can only have a single text index, so fields searched together are
+    // weighted to rank matches in names above matches in content
+    let text = |keys: Document, weights: Document| {
+        IndexModel::builder()
+            .keys(keys)
+            .options(
+                IndexOptions::builder()
+                    .name("search_text".to_string())
+                    .weights(weights)
+                    .build(),
+            )
+            .build()
+    };
+
+    vec![
+        (
+            USER_COLLECTION_NAME,
+            vec![
+                IndexModel::builder()
+                    .keys(doc! { "email": 1 })
+                    .options(unique("email_unique"))
+                    .build(),
+                IndexModel::builder()
+                    .keys(doc! { "username": 1 })
+                    .options(unique("username_unique"))
+                    .build(),
+                // Older users don't have a skeleton and aren't checked for lookalikes
+                IndexModel::builder()
+                    .keys(doc! { "username_skeleton": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .name("username_skeleton_unique".to_string())
+                            .unique(true)
+                            .partial_filter_expression(doc! {
+                                "username_skeleton": { "$exists": true }
+                            })
+                            .build(),
+                    )
+                    .build(),
+                text(doc! { "username": "text" }, doc! { "username": 1 }),
+            ],
+        ),
+        (
+            USER_TOKEN_COLLECTION_NAME,
+            vec![IndexModel::builder()
+                .keys(doc! { "expires": 1 })
+                .options(
+                    IndexOptions::builder()
+                        .name("expires_ttl".to_string())
+                        .expire_after(std::time::Duration::ZERO)
+                        .build(),
+                )
+                .build()],
+        ),
+        (
+            QUIZ_COLLECTION_NAME,
+            vec![
+                tags(),
+                text(
+                    doc! {
+                        "name": "text",
+                        "desc": "text",
+                        "parts.Content.title": "text",
+                        "parts.Content.text": "text",
+                    },
+                    doc! {
+                        "name": 10,
+                        "desc": 5,
+                        "parts.Content.title": 3,
+                        "parts.Content.text": 1,
+                    },
+                ),
+            ],
+        ),
+        (
+            CLASS_COLLECTION_NAME,
+            vec![tags(), text(doc! { "name": "text" }, doc! { "name": 1 })],
+        ),
+    ]
+}
+
+/// Fields holding UUIDs which older versions stored as generic binary.
+const UUID_FIELDS: &[(&str, &[&[&str]])] = &[
+    (
+        QUIZ_COLLECTION_NAME,
+        &[
+            &["author"],
+            &["participants", "user_id"],
+            &["parts", "Content", "id"],
+            &["parts", "Question", "id"],
+        ],
+    ),
+    (CLASS_COLLECTION_NAME, &[&["participants", "user_id"]]),
+    (USER_TOKEN_COLLECTION_NAME, &[&["user"]]),
+    (USER_AUDIT_COLLECTION_NAME, &[&["user"], &["actor"]]),
+];
+
+async fn normalize_uuids(db: &Database) -> Result<(), mongodb::error::Error> {
+    for (name, paths) in UUID_FIELDS {
+        let collection = db.collection::<Document>(name);
+        let mut cursor = collection.find(None, None).await?;
+        while let Some(mut document) = cursor.try_next().await? {
+            let changed = paths.iter().fold(false, |changed, it| {
+                id::normalize_field(&mut document, it) || changed
+            });
+            if !changed {
+                continue;
+            }
+            if let Some(key) = document.get("_id").cloned() {
+                collection
+                    .replace_one(doc! { "_id": key }, &document, None)
+                    .await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Date fields of quizzes, which older versions stored with varying precision and offsets.
+const QUIZ_DATE_FIELDS: &[&[&str]] = &[
+    &["created"],
+    &["open_on"],
+    &["close_on"],
+    &["participants", "started_on"],
+];
+
+async fn normalize_dates(db: &Database) -> Result<(), mongodb::error::Error> {
+    let collection = db.collection::<Document>(QUIZ_COLLECTION_NAME);
+    let mut cursor = collection.find(None, None).await?;
+    while let Some(mut document) = cursor.try_next().await? {
+        let changed = QUIZ_DATE_FIELDS.iter().fold(false, |changed, it| {
+            date::normalize_field(&mut document, it) || changed
+        });
+        if !changed {
+            continue;
+        }
+        if let Some(key) = document.get("_id").cloned() {
+            collection
+                .replace_one(doc! { "_id": key }, &document, None)
+                .await?;
+        }
+    }
+    Ok(())
+}
+
+/// Record of an applied migration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MigrationRecord {
+    #[serde(rename = "_id")]
+    pub version: u32,
+    pub name: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub applied: DateTime<Utc>,
+}
+
+pub async fn applied_migrations(
+    db: &Database,
+) -> Result<Vec<MigrationRecord>, mongodb::error::Error> {
+    db.collection::<MigrationRecord>(MIGRATION_COLLECTION_NAME)
+        .find(None, None)
+        .await?
+        .try_collect()
+        .await
+}
+
+/// Migrations that weren't applied yet, in the order they have to be applied.
+pub async fn pending_migrations(db: &Database) -> Result<Vec<Migration>, mongodb::error::Error> {
+    let applied: HashSet<u32> = applied_migrations(db)
+        .await?
+        .into_iter()
+        .map(|it| it.version)
+        .collect();
+
+    if let Some(latest) = applied.iter().max() {
+        if migrations().iter().all(|it| it.version < *latest) {
+            tracing::warn!(
+                "Database was migrated to version {} by a newer server version",
+                latest
+            );
+        }
+    }
+
+    Ok(migrations()
+        .into_iter()
+        .filter(|it| !applied.contains(&it.version))
+        .collect())
+}
+
+/// Applies pending migrations and returns them. With `dry_run` they're only returned.
+pub async fn migrate(
+    db: &Database,
+    dry_run: bool,
+) -> Result<Vec<Migration>, mongodb::error::Error> {
+    let pending = pending_migrations(db).await?;
+    if dry_run {
+        return Ok(pending);
+    }
+
+    let records = db.collection::<MigrationRecord>(MIGRATION_COLLECTION_NAME);
+    for migration in &pending {
+        tracing::info!(
+            "Applying migration {}: {}",
+            migration.version,
+            migration.name
+        );
+        for step in &migration.steps {
+            tracing::debug!("Migration {}: {}", migration.version, step.describe());
+            step.apply(db).await?;
+        }
+
+        let record = MigrationRecord {
+            version: migration.version,
+            name: migration.name.to_string(),
+            applied: Utc::now(),
+        };
+        match records.insert_one(&record, None).await {
+            Ok(_) => {}
+            // Another server instance applied the same migration at the same time
+            Err(e) if is_duplicate_key(&e) => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(pending)
+}
+
+fn is_duplicate_key(e: &mongodb::error::Error) -> bool {
+    matches!(
+        e.kind.as_ref(),
+        ErrorKind::Write(WriteFailure::WriteError(err)) if err.code == 11000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migration_versions_increase() {
+        let versions: Vec<u32> = migrations().iter().map(|it| it.version).collect();
+        assert!(
+            versions.windows(2).all(|it| it[0] < it[1]),
+            "{:?}",
+            versions
+        );
+        assert_eq!(versions.first(), Some(&1));
+    }
+
+    #[test]
+    fn initial_indexes_are_kept() {
+        let steps: Vec<String> = migrations()[0]
+            .steps
+            .iter()
+            .map(Step::describe)
+            .filter(|it| it.starts_with("create indexes"))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                "create indexes email_unique, username_unique, username_skeleton_unique, \
+                 search_text on user",
+                "create indexes expires_ttl on user.tokens",
+                "create indexes tags, search_text on quiz",
+                "create indexes tags, search_text on classes",
+            ]
+        );
+    }
+}
@@ -1,4 +1,10 @@
 pub mod class;
 pub mod content;
+pub mod date;
+pub mod id;
+pub mod migration;
 pub mod quiz;
+pub mod search;
+pub mod store;
+pub mod tag;
 pub mod user;
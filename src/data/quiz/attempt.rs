@@ -0,0 +1,73 @@
+use bson::doc;
+use chrono::{DateTime, Utc};
+use mongodb::options::FindOptions;
+use mongodb::Database;
+use rocket::futures::TryStreamExt;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::ValidationResult;
+use crate::data::id;
+use crate::resp::problem::Problem;
+
+pub static ATTEMPT_COLLECTION_NAME: &str = "quiz.attempts";
+
+/// Graded answers a user submitted for a quiz.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct QuizAttempt {
+    #[serde(rename = "_id", with = "crate::data::id::binary")]
+    pub id: Uuid,
+    #[serde(with = "crate::data::id::binary")]
+    pub quiz: Uuid,
+    #[serde(with = "crate::data::id::binary")]
+    pub user: Uuid,
+    pub result: ValidationResult,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub submitted: DateTime<Utc>,
+}
+
+impl QuizAttempt {
+    pub fn new(quiz: Uuid, user: Uuid, result: ValidationResult) -> QuizAttempt {
+        QuizAttempt {
+            id: Uuid::new_v4(),
+            quiz,
+            user,
+            result,
+            submitted: Utc::now(),
+        }
+    }
+}
+
+/// Stored quiz attempts.
+pub trait AttemptRepository {
+    async fn record_attempt(&self, attempt: &QuizAttempt) -> Result<(), Problem>;
+
+    /// Lists attempts of `user` at `quiz`, oldest first.
+    async fn quiz_attempts(&self, quiz: Uuid, user: Uuid) -> Result<Vec<QuizAttempt>, Problem>;
+}
+
+impl AttemptRepository for Database {
+    async fn record_attempt(&self, attempt: &QuizAttempt) -> Result<(), Problem> {
+        self.collection::<QuizAttempt>(ATTEMPT_COLLECTION_NAME)
+            .insert_one(attempt, None)
+            .await
+            .map_err(Problem::from)?;
+        Ok(())
+    }
+
+    async fn quiz_attempts(&self, quiz: Uuid, user: Uuid) -> Result<Vec<QuizAttempt>, Problem> {
+        let mut filter = id::eq("quiz", quiz);
+        filter.extend(id::eq("user", user));
+
+        self.collection::<QuizAttempt>(ATTEMPT_COLLECTION_NAME)
+            .find(
+                filter,
+                FindOptions::builder().sort(doc! { "submitted": 1 }).build(),
+            )
+            .await
+            .map_err(Problem::from)?
+            .try_collect()
+            .await
+            .map_err(Problem::from)
+    }
+}
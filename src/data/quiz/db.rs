@@ -0,0 +1,42 @@
+use mongodb::Database;
+use uuid::Uuid;
+
+use super::{Quiz, QUIZ_COLLECTION_NAME};
+use crate::data::id;
+use crate::error::BackendError;
+use crate::resp::problem::Problem;
+
+/// Stored quizzes.
+pub trait QuizRepository {
+    async fn create_quiz(&self, quiz: &Quiz) -> Result<(), Problem>;
+
+    async fn get_quiz(&self, id: Uuid) -> Result<Option<Quiz>, Problem>;
+
+    /// Removes a quiz and returns whether it existed.
+    async fn delete_quiz(&self, id: Uuid) -> Result<bool, Problem>;
+}
+
+impl QuizRepository for Database {
+    async fn create_quiz(&self, quiz: &Quiz) -> Result<(), Problem> {
+        self.collection(QUIZ_COLLECTION_NAME)
+            .insert_one(bson::to_document(quiz).map_err(BackendError::from)?, None)
+            .await
+            .map_err(Problem::from)?;
+        Ok(())
+    }
+
+    async fn get_quiz(&self, id: Uuid) -> Result<Option<Quiz>, Problem> {
+        self.collection::<Quiz>(QUIZ_COLLECTION_NAME)
+            .find_one(id::by_id(id), None)
+            .await
+            .map_err(Problem::from)
+    }
+
+    async fn delete_quiz(&self, id: Uuid) -> Result<bool, Problem> {
+        self.collection::<Quiz>(QUIZ_COLLECTION_NAME)
+            .delete_one(id::by_id(id), None)
+            .await
+            .map(|it| it.deleted_count > 0)
+            .map_err(Problem::from)
+    }
+}
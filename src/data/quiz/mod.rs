@@ -1,8 +1,18 @@
+use bson::{doc, Document};
 use chrono::{DateTime, Utc};
+use std::borrow::Cow;
 use std::{collections::HashMap, time::Duration};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::data::id;
+use crate::data::user::profile::{check_tags, normalize_tags, Tag};
+use crate::resp::validation::{FieldErrorCode, Validate, Validator};
+use crate::settings::Settings;
+
+pub mod attempt;
+pub mod db;
+
 pub static PART_COLLECTION_NAME: &str = "quiz.parts";
 pub static PARTICIPANT_COLLECTION_NAME: &str = "participant";
 pub static QUIZ_COLLECTION_NAME: &str = "quiz";
@@ -11,6 +21,50 @@ fn true_bool() -> bool {
     true
 }
 
+fn default_max_name_length() -> usize {
+    128
+}
+
+fn default_max_description_length() -> usize {
+    4096
+}
+
+fn default_max_tags() -> usize {
+    16
+}
+
+/// Limits quizzes have to fit in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct QuizLimits {
+    #[serde(default = "default_max_name_length")]
+    pub max_name_length: usize,
+    #[serde(default = "default_max_description_length")]
+    pub max_description_length: usize,
+    #[serde(default = "default_max_tags")]
+    pub max_tags: usize,
+}
+
+impl Default for QuizLimits {
+    fn default() -> Self {
+        QuizLimits {
+            max_name_length: default_max_name_length(),
+            max_description_length: default_max_description_length(),
+            max_tags: default_max_tags(),
+        }
+    }
+}
+
+impl QuizLimits {
+    /// Limits of the validated request, or the default ones outside of requests.
+    pub fn current<'a>(validator: &Validator<'a>) -> Cow<'a, QuizLimits> {
+        validator
+            .state::<Settings>()
+            .map(|it| Cow::Borrowed(&it.quiz))
+            .unwrap_or_default()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[non_exhaustive]
 pub enum QuestionKind {
@@ -69,13 +123,13 @@ pub enum AnswerValidation {
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum QuizPart {
     Content {
-        #[serde(default = "Uuid::new_v4")]
+        #[serde(default = "Uuid::new_v4", with = "crate::data::id::binary")]
         id: Uuid,
         title: String,
         text: String,
     },
     Question {
-        #[serde(default = "Uuid::new_v4")]
+        #[serde(default = "Uuid::new_v4", with = "crate::data::id::binary")]
         id: Uuid,
         text: String,
         kind: QuestionKind,
@@ -102,7 +156,9 @@ pub enum AnswerChoice {
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct QuizParticipant {
+    #[serde(with = "crate::data::id::binary")]
     pub user_id: Uuid,
+    #[serde(with = "crate::data::date::rfc3339")]
     pub started_on: DateTime<Utc>,
     #[serde(default)]
     pub choices: HashMap<Uuid, AnswerChoice>,
@@ -113,20 +169,20 @@ pub struct Quiz {
     #[serde(
         default = "Uuid::new_v4",
         rename = "_id",
-        with = "bson::serde_helpers::uuid_1_as_binary"
+        with = "crate::data::id::binary"
     )]
     pub id: Uuid,
     pub name: String,
     #[serde(default)]
     pub desc: String,
-    #[serde(
-        default = "Uuid::new_v4",
-        with = "bson::serde_helpers::uuid_1_as_binary"
-    )] // TODO: Remove default
+    #[serde(default = "Uuid::new_v4", with = "crate::data::id::binary")] // TODO: Remove default
     pub author: Uuid,
-    #[serde(default = "Utc::now")]
+    #[serde(default = "Utc::now", with = "crate::data::date::rfc3339")]
     pub created: DateTime<Utc>,
     pub parts: Vec<QuizPart>,
+    /// Topics of the quiz, stored in lower case
+    #[serde(default)]
+    pub tags: Vec<Tag>,
 
     #[serde(default)]
     pub time_limit: Option<Duration>,
@@ -139,9 +195,9 @@ pub struct Quiz {
 
     #[serde(default = "true_bool")]
     pub public: bool,
-    #[serde(default)]
+    #[serde(default, with = "crate::data::date::rfc3339_option")]
     pub open_on: Option<DateTime<Utc>>,
-    #[serde(default)]
+    #[serde(default, with = "crate::data::date::rfc3339_option")]
     pub close_on: Option<DateTime<Utc>>,
     #[serde(default)]
     pub begin_buffer: Option<Duration>,
@@ -149,6 +205,47 @@ pub struct Quiz {
     pub participants: Vec<QuizParticipant>,
 }
 
+impl Quiz {
+    /// Stores tags in lower case and without duplicates.
+    pub fn normalize_tags(&mut self) {
+        self.tags = normalize_tags(&self.tags);
+    }
+
+    /// Filter matching quizzes `viewer` can see: public ones and ones they authored.
+    pub fn visible_to(viewer: Option<Uuid>) -> Document {
+        // Quizzes without `public` field are public
+        let mut visible = vec![doc! { "public": { "$ne": false } }];
+        if let Some(viewer) = viewer {
+            visible.push(id::eq("author", viewer));
+        }
+        doc! { "$or": visible }
+    }
+}
+
+impl Validate for Quiz {
+    fn check(&self, validator: &mut Validator<'_>) {
+        let limits = QuizLimits::current(validator);
+        validator
+            .length(
+                "name",
+                self.name.trim().chars().count(),
+                1,
+                limits.max_name_length,
+            )
+            .length(
+                "desc",
+                self.desc.chars().count(),
+                0,
+                limits.max_description_length,
+            );
+        check_tags(validator, "tags", &self.tags, limits.max_tags);
+
+        if let (Some(open_on), Some(close_on)) = (self.open_on, self.close_on) {
+            validator.check(close_on > open_on, "close_on", FieldErrorCode::OutOfRange);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum PartAnswer {
     Bool { answer: bool },
@@ -162,7 +259,7 @@ pub struct QuizAnswers {
     pub answers: HashMap<Uuid, PartAnswer>,
 }
 
-#[derive(Debug, Default, Serialize, ToSchema)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
 pub struct ValidationResult {
     pub total_questions: usize,
     pub correct_answers: usize,
@@ -0,0 +1,323 @@
+use bson::{doc, Bson, Document};
+use mongodb::options::FindOptions;
+use mongodb::Database;
+use rocket::futures::TryStreamExt;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::data::class::CLASS_COLLECTION_NAME;
+use crate::data::id;
+use crate::data::quiz::{Quiz, QUIZ_COLLECTION_NAME};
+use crate::data::user::db::USER_COLLECTION_NAME;
+use crate::resp::problem::Problem;
+
+/// Number of characters shown around the first match in a snippet.
+const SNIPPET_CONTEXT: usize = 48;
+
+/// Snippet candidates of quizzes, listed as (display name, path into the document).
+pub const QUIZ_FIELDS: &[(&str, &[&str])] = &[
+    ("name", &["name"]),
+    ("desc", &["desc"]),
+    ("content_title", &["parts", "Content", "title"]),
+    ("content", &["parts", "Content", "text"]),
+];
+pub const CLASS_FIELDS: &[(&str, &[&str])] = &[("name", &["name"])];
+pub const USER_FIELDS: &[(&str, &[&str])] = &[("username", &["username"])];
+
+/// Part of a matched field around the first match.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Snippet {
+    /// Name of the matched field
+    pub field: String,
+    pub text: String,
+    /// `[start, end)` character ranges of matched terms in `text`
+    pub highlights: Vec<[usize; 2]>,
+}
+
+impl Snippet {
+    /// Creates a snippet of `text` around the first occurrence of any of the `terms`.
+    ///
+    /// Terms are matched without regard to letter case. Returns `None` if no terms occur in
+    /// `text`, which is possible with stemmed matches.
+    pub fn new(field: impl ToString, text: &str, terms: &[String]) -> Option<Snippet> {
+        let chars: Vec<char> = text.chars().collect();
+        // Mapped char by char so positions stay the same as in `chars`
+        let lower: Vec<char> = chars
+            .iter()
+            .map(|c| c.to_lowercase().next().unwrap_or(*c))
+            .collect();
+
+        let mut matches: Vec<[usize; 2]> = vec![];
+        for term in terms {
+            let term: Vec<char> = term.chars().collect();
+            if term.is_empty() || term.len() > lower.len() {
+                continue;
+            }
+            for start in 0..=(lower.len() - term.len()) {
+                if lower[start..start + term.len()] == term[..] {
+                    matches.push([start, start + term.len()]);
+                }
+            }
+        }
+        matches.sort_unstable();
+
+        let first = matches.first()?[0];
+        let start = first.saturating_sub(SNIPPET_CONTEXT);
+        let end = (first + SNIPPET_CONTEXT * 2).min(chars.len());
+
+        // Overlapping matches are merged so highlights never nest
+        let mut highlights: Vec<[usize; 2]> = vec![];
+        for [from, to] in matches {
+            if from < start || to > end {
+                continue;
+            }
+            let (from, to) = (from - start, to - start);
+            match highlights.last_mut() {
+                Some(last) if from <= last[1] => last[1] = last[1].max(to),
+                _ => highlights.push([from, to]),
+            }
+        }
+
+        Some(Snippet {
+            field: field.to_string(),
+            text: chars[start..end].iter().collect(),
+            highlights,
+        })
+    }
+}
+
+/// Splits a search query into lower case terms used for highlighting.
+pub fn query_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .map(|it| {
+            it.trim_matches(|c: char| c == '"' || c == '-')
+                .to_lowercase()
+        })
+        .filter(|it| !it.is_empty())
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SearchHit {
+    pub id: Uuid,
+    /// Quiz or class name, or username
+    pub title: String,
+    /// Relevance of the hit, higher is better
+    pub score: f64,
+    pub snippets: Vec<Snippet>,
+}
+
+impl SearchHit {
+    /// Builds a hit from a document returned by a text search.
+    ///
+    /// `fields` are snippet candidates, listed as (display name, path into the document). Paths
+    /// can point into arrays of documents.
+    fn from_document(
+        document: &Document,
+        title_field: &str,
+        fields: &[(&str, &[&str])],
+        terms: &[String],
+    ) -> Option<SearchHit> {
+        let id = id::from_bson(document.get("_id")?)?;
+
+        let mut snippets = vec![];
+        for (name, path) in fields {
+            for text in field_texts(&Bson::Document(document.clone()), path) {
+                if let Some(snippet) = Snippet::new(name, &text, terms) {
+                    snippets.push(snippet);
+                }
+            }
+        }
+
+        Some(SearchHit {
+            id,
+            title: document
+                .get_str(title_field)
+                .unwrap_or_default()
+                .to_string(),
+            score: document.get_f64("score").unwrap_or_default(),
+            snippets,
+        })
+    }
+}
+
+/// Finds `documents` containing some of the query terms in `fields`, best hits first.
+///
+/// Stand-in for MongoDB text search in stores without text indexes. Terms are matched as plain
+/// text, without stemming, and hits are scored by the number of matched fields.
+pub fn scan_documents(
+    documents: &[Document],
+    query: &str,
+    title_field: &str,
+    fields: &[(&str, &[&str])],
+    limit: i64,
+) -> Vec<SearchHit> {
+    let terms = query_terms(query);
+    let mut hits: Vec<SearchHit> = documents
+        .iter()
+        .filter_map(|it| SearchHit::from_document(it, title_field, fields, &terms))
+        .filter(|it| !it.snippets.is_empty())
+        .map(|mut it| {
+            it.score = it.snippets.len() as f64;
+            it
+        })
+        .collect();
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit.max(0) as usize);
+    hits
+}
+
+/// Collects all strings found at `path`, descending into arrays.
+fn field_texts(value: &Bson, path: &[&str]) -> Vec<String> {
+    match (value, path.split_first()) {
+        (Bson::String(text), None) => vec![text.clone()],
+        (Bson::Array(items), _) => items.iter().flat_map(|it| field_texts(it, path)).collect(),
+        (Bson::Document(doc), Some((key, rest))) => doc
+            .get(*key)
+            .map(|it| field_texts(it, rest))
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct SearchResults {
+    pub quizzes: Vec<SearchHit>,
+    pub classes: Vec<SearchHit>,
+    pub users: Vec<SearchHit>,
+}
+
+pub trait SearchDbExt {
+    /// Searches quizzes that are public or authored by `viewer`.
+    async fn search_quizzes(
+        &self,
+        query: &str,
+        viewer: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem>;
+
+    /// Searches classes `viewer` participates in.
+    async fn search_classes(
+        &self,
+        query: &str,
+        viewer: Uuid,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem>;
+
+    async fn search_users(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, Problem>;
+}
+
+/// Finds documents matching `query` with the text index of `collection`, which migrations create.
+async fn text_search(
+    db: &Database,
+    collection: &str,
+    query: &str,
+    visibility: Option<Document>,
+    projection: Document,
+    limit: i64,
+) -> Result<Vec<Document>, Problem> {
+    let text = doc! { "$text": { "$search": query } };
+    let filter = match visibility {
+        Some(visibility) => doc! { "$and": [text, visibility] },
+        None => text,
+    };
+
+    let mut projection = projection;
+    projection.insert("score", doc! { "$meta": "textScore" });
+
+    let options = FindOptions::builder()
+        .projection(projection)
+        .sort(doc! { "score": { "$meta": "textScore" } })
+        .limit(limit)
+        .build();
+
+    db.collection::<Document>(collection)
+        .find(filter, options)
+        .await
+        .map_err(Problem::from)?
+        .try_collect()
+        .await
+        .map_err(Problem::from)
+}
+
+impl SearchDbExt for Database {
+    async fn search_quizzes(
+        &self,
+        query: &str,
+        viewer: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem> {
+        let documents = text_search(
+            self,
+            QUIZ_COLLECTION_NAME,
+            query,
+            Some(Quiz::visible_to(viewer)),
+            doc! { "name": 1, "desc": 1, "parts.Content": 1 },
+            limit,
+        )
+        .await?;
+
+        let terms = query_terms(query);
+        Ok(documents
+            .iter()
+            .filter_map(|it| SearchHit::from_document(it, "name", QUIZ_FIELDS, &terms))
+            .collect())
+    }
+
+    async fn search_classes(
+        &self,
+        query: &str,
+        viewer: Uuid,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem> {
+        let documents = text_search(
+            self,
+            CLASS_COLLECTION_NAME,
+            query,
+            Some(id::eq("participants.user_id", viewer)),
+            doc! { "name": 1 },
+            limit,
+        )
+        .await?;
+
+        let terms = query_terms(query);
+        Ok(documents
+            .iter()
+            .filter_map(|it| SearchHit::from_document(it, "name", CLASS_FIELDS, &terms))
+            .collect())
+    }
+
+    async fn search_users(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, Problem> {
+        let documents = text_search(
+            self,
+            USER_COLLECTION_NAME,
+            query,
+            None,
+            doc! { "username": 1 },
+            limit,
+        )
+        .await?;
+
+        let terms = query_terms(query);
+        Ok(documents
+            .iter()
+            .filter_map(|it| SearchHit::from_document(it, "username", USER_FIELDS, &terms))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippet_highlights_terms_around_first_match() {
+        let text = "Photosynthesis converts light into chemical energy. Light drives it.";
+        let snippet = Snippet::new("desc", text, &query_terms("LIGHT energy")).unwrap();
+
+        assert_eq!(snippet.text, text);
+        assert_eq!(snippet.highlights, [[24, 29], [44, 50], [52, 57]]);
+        assert!(Snippet::new("desc", text, &query_terms("oxygen")).is_none());
+    }
+}
@@ -0,0 +1,608 @@
+//! Store keeping all data in memory, so routes can be used without running MongoDB.
+//!
+//! Data is lost when the store is dropped. Emails and usernames are unique the same way MongoDB
+//! indexes enforce it, ignoring letter case and lookalike usernames.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use bson::{from_document, Document};
+use chrono::{Duration, Utc};
+use mongodb::options::FindOptions;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::query;
+use crate::data::class::db::ClassRepository;
+use crate::data::class::{Class, CLASS_COLLECTION_NAME};
+use crate::data::quiz::attempt::{AttemptRepository, QuizAttempt};
+use crate::data::quiz::db::QuizRepository;
+use crate::data::quiz::{Quiz, QUIZ_COLLECTION_NAME};
+use crate::data::search::{self, SearchDbExt, SearchHit};
+use crate::data::tag::{combine_usage, TagDbExt, TagUsage};
+use crate::data::user::audit::{AuditEntry, UserAuditDbExt};
+use crate::data::user::db::{problem, UserRepository, UserSignupData, USER_COLLECTION_NAME};
+use crate::data::user::policy::{normalize_username, username_skeleton};
+use crate::data::user::profile::{Profile, Tag};
+use crate::data::user::settings::{UserSettings, UserSettingsDbExt};
+use crate::data::user::token::{generate_token, hash_token};
+use crate::data::user::token::{TokenPurpose, UserToken, UserTokenDbExt};
+use crate::data::user::{AccountStatus, PasswordHash, User};
+use crate::resp::catalog::ProblemType;
+use crate::resp::jwt::UserRoleToken;
+use crate::resp::problem::Problem;
+use crate::role::Role;
+
+#[derive(Default)]
+pub struct MemoryStore {
+    users: Mutex<HashMap<Uuid, User>>,
+    user_tokens: Mutex<HashMap<String, UserToken>>,
+    user_audit: Mutex<Vec<AuditEntry>>,
+    user_settings: Mutex<HashMap<Uuid, UserSettings>>,
+    quizzes: Mutex<HashMap<Uuid, Quiz>>,
+    classes: Mutex<HashMap<Uuid, Class>>,
+    attempts: Mutex<Vec<QuizAttempt>>,
+}
+
+impl std::fmt::Debug for MemoryStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MemoryStore")
+    }
+}
+
+/// Locks a collection. Panics while holding a lock can't leave collections half updated, so
+/// poisoning is ignored.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|it| it.into_inner())
+}
+
+fn same_text(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// Checks `email` and `username` aren't used by users other than `except`.
+fn check_unique(
+    users: &HashMap<Uuid, User>,
+    except: Option<Uuid>,
+    email: Option<&str>,
+    username: Option<&str>,
+) -> Result<(), Problem> {
+    let others = || users.values().filter(move |it| Some(it.id) != except);
+
+    if let Some(email) = email {
+        if others().any(|it| same_text(&it.email, email)) {
+            return Err(problem::bad_email(email, "Email already registered."));
+        }
+    }
+
+    if let Some(username) = username {
+        let skeleton = username_skeleton(username);
+        if others().any(|it| it.username_skeleton.as_deref() == Some(skeleton.as_str())) {
+            return Err(problem::bad_username(
+                username,
+                "Username looks too similar to an already used one.",
+            ));
+        }
+        if others().any(|it| same_text(&it.username, username)) {
+            return Err(problem::bad_username(username, "Username already used."));
+        }
+    }
+
+    Ok(())
+}
+
+fn to_documents<'a, T: Serialize + 'a>(
+    items: impl IntoIterator<Item = &'a T>,
+) -> Result<Vec<Document>, Problem> {
+    items
+        .into_iter()
+        .map(|it| bson::to_document(it).map_err(Problem::from))
+        .collect()
+}
+
+fn count_tags<'a>(tags: impl IntoIterator<Item = &'a Tag>) -> Vec<(String, u64)> {
+    let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+    for tag in tags {
+        *counts.entry(tag.as_ref().to_string()).or_default() += 1;
+    }
+    counts.into_iter().collect()
+}
+
+impl MemoryStore {
+    /// Applies `change` to user with `id` and returns whether the user exists.
+    fn update_user(&self, id: Uuid, change: impl FnOnce(&mut User)) -> bool {
+        match lock(&self.users).get_mut(&id) {
+            Some(user) => {
+                change(user);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Documents of `collection`, as MongoDB would store them. Only users, quizzes and classes
+    /// can be queried as documents.
+    fn documents(&self, collection: &str) -> Result<Vec<Document>, Problem> {
+        if collection == USER_COLLECTION_NAME {
+            to_documents(lock(&self.users).values())
+        } else if collection == QUIZ_COLLECTION_NAME {
+            to_documents(lock(&self.quizzes).values())
+        } else if collection == CLASS_COLLECTION_NAME {
+            to_documents(lock(&self.classes).values())
+        } else {
+            Err(Problem::typed(ProblemType::ServerUnsupported))
+        }
+    }
+
+    /// Documents of `collection` matching `filter`, see [`query`] for supported filters. Sort,
+    /// limit and projection of `options` are applied.
+    pub fn find_documents(
+        &self,
+        collection: &str,
+        filter: &Document,
+        options: &FindOptions,
+    ) -> Result<Vec<Document>, Problem> {
+        let mut documents: Vec<Document> = self
+            .documents(collection)?
+            .into_iter()
+            .filter(|it| query::matches(it, filter))
+            .collect();
+
+        if let Some(sort) = &options.sort {
+            query::sort(&mut documents, sort);
+        }
+        if let Some(limit) = options.limit {
+            documents.truncate(limit.unsigned_abs() as usize);
+        }
+        if let Some(projection) = &options.projection {
+            documents = documents
+                .into_iter()
+                .map(|it| query::project(it, projection))
+                .collect();
+        }
+        Ok(documents)
+    }
+
+    /// Number of documents of `collection` matching `filter`.
+    pub fn count_documents(&self, collection: &str, filter: &Document) -> Result<u64, Problem> {
+        Ok(self
+            .documents(collection)?
+            .iter()
+            .filter(|it| query::matches(it, filter))
+            .count() as u64)
+    }
+}
+
+impl UserRepository for MemoryStore {
+    async fn create_user<'a>(
+        &self,
+        create_user: UserSignupData<'_>,
+        salt: impl AsRef<[u8]>,
+        admin_names: impl AsRef<[String]>,
+    ) -> Result<(UserRoleToken, User), Problem> {
+        let mut user = create_user.to_user(salt);
+
+        if admin_names.as_ref().contains(&user.username) {
+            user.user_role = Role::Admin;
+        }
+
+        let urt = UserRoleToken::new(&user);
+
+        let mut users = lock(&self.users);
+        check_unique(&users, None, Some(&user.email), Some(&user.username))?;
+        users.insert(user.id, user.clone());
+
+        Ok((urt, user))
+    }
+
+    async fn get_user(&self, id: Uuid) -> Result<Option<User>, Problem> {
+        Ok(lock(&self.users).get(&id).cloned())
+    }
+
+    async fn find_user_by_email(&self, email: impl AsRef<str>) -> Result<Option<User>, Problem> {
+        Ok(lock(&self.users)
+            .values()
+            .find(|it| same_text(&it.email, email.as_ref()))
+            .cloned())
+    }
+
+    async fn find_user_by_username(
+        &self,
+        username: impl AsRef<str>,
+    ) -> Result<Option<User>, Problem> {
+        let username = normalize_username(username);
+        Ok(lock(&self.users)
+            .values()
+            .find(|it| same_text(&it.username, &username))
+            .cloned())
+    }
+
+    async fn update_password(&self, id: Uuid, pw_hash: PasswordHash) -> Result<bool, Problem> {
+        Ok(self.update_user(id, |it| it.pw_hash = pw_hash))
+    }
+
+    async fn set_email_verified(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem> {
+        match lock(&self.users).get_mut(&id) {
+            Some(user) if user.email == email.as_ref() => {
+                user.email_verified = true;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn update_profile(&self, id: Uuid, profile: &Profile) -> Result<bool, Problem> {
+        Ok(self.update_user(id, |it| it.profile = profile.clone()))
+    }
+
+    async fn update_role(&self, id: Uuid, role: Role) -> Result<bool, Problem> {
+        Ok(self.update_user(id, |it| it.user_role = role))
+    }
+
+    async fn update_status(&self, id: Uuid, status: &AccountStatus) -> Result<bool, Problem> {
+        Ok(self.update_user(id, |it| it.status = status.clone()))
+    }
+
+    async fn update_username(&self, id: Uuid, username: impl AsRef<str>) -> Result<bool, Problem> {
+        let username = normalize_username(username);
+
+        let mut users = lock(&self.users);
+        check_unique(&users, Some(id), None, Some(&username))?;
+        match users.get_mut(&id) {
+            Some(user) => {
+                user.username_skeleton = Some(username_skeleton(&username));
+                user.username = username;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn update_email(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem> {
+        let mut users = lock(&self.users);
+        check_unique(&users, Some(id), Some(email.as_ref()), None)?;
+        match users.get_mut(&id) {
+            Some(user) => {
+                user.email = email.as_ref().to_string();
+                user.email_verified = false;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete_user(&self, id: Uuid) -> Result<Option<User>, Problem> {
+        Ok(lock(&self.users).remove(&id))
+    }
+}
+
+impl UserTokenDbExt for MemoryStore {
+    async fn create_user_token(
+        &self,
+        user: Uuid,
+        purpose: TokenPurpose,
+        email: Option<String>,
+        ttl: Duration,
+    ) -> Result<String, Problem> {
+        let token = generate_token();
+
+        let entry = UserToken {
+            hash: hash_token(&token),
+            user,
+            purpose,
+            email,
+            expires: Utc::now() + ttl,
+        };
+        lock(&self.user_tokens).insert(entry.hash.clone(), entry);
+
+        Ok(token)
+    }
+
+    async fn consume_user_token(
+        &self,
+        token: impl AsRef<str>,
+        purpose: TokenPurpose,
+    ) -> Result<Option<UserToken>, Problem> {
+        let mut tokens = lock(&self.user_tokens);
+        let now = Utc::now();
+        tokens.retain(|_, it| it.expires > now);
+
+        let hash = hash_token(token);
+        match tokens.get(&hash) {
+            Some(it) if it.purpose == purpose => Ok(tokens.remove(&hash)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn delete_user_tokens(&self, user: Uuid, purpose: TokenPurpose) -> Result<u64, Problem> {
+        let mut tokens = lock(&self.user_tokens);
+        let before = tokens.len();
+        tokens.retain(|_, it| it.user != user || it.purpose != purpose);
+        Ok((before - tokens.len()) as u64)
+    }
+}
+
+impl UserAuditDbExt for MemoryStore {
+    async fn record_audit(&self, entry: &AuditEntry) -> Result<(), Problem> {
+        lock(&self.user_audit).push(entry.clone());
+        Ok(())
+    }
+
+    async fn user_audit(&self, user: Uuid) -> Result<Vec<AuditEntry>, Problem> {
+        let mut entries: Vec<AuditEntry> = lock(&self.user_audit)
+            .iter()
+            .filter(|it| it.user == user)
+            .cloned()
+            .collect();
+        entries.sort_by(|a, b| b.time.cmp(&a.time));
+        Ok(entries)
+    }
+}
+
+impl UserSettingsDbExt for MemoryStore {
+    async fn get_user_settings(&self, user: Uuid) -> Result<UserSettings, Problem> {
+        Ok(lock(&self.user_settings)
+            .get(&user)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn save_user_settings(&self, user: Uuid, settings: &UserSettings) -> Result<(), Problem> {
+        lock(&self.user_settings).insert(user, settings.clone());
+        Ok(())
+    }
+}
+
+impl QuizRepository for MemoryStore {
+    async fn create_quiz(&self, quiz: &Quiz) -> Result<(), Problem> {
+        lock(&self.quizzes).insert(quiz.id, quiz.clone());
+        Ok(())
+    }
+
+    async fn get_quiz(&self, id: Uuid) -> Result<Option<Quiz>, Problem> {
+        Ok(lock(&self.quizzes).get(&id).cloned())
+    }
+
+    async fn delete_quiz(&self, id: Uuid) -> Result<bool, Problem> {
+        Ok(lock(&self.quizzes).remove(&id).is_some())
+    }
+}
+
+impl ClassRepository for MemoryStore {
+    async fn create_class(&self, class: &Class) -> Result<(), Problem> {
+        lock(&self.classes).insert(class.id, class.clone());
+        Ok(())
+    }
+
+    async fn get_class(&self, id: Uuid) -> Result<Option<Class>, Problem> {
+        Ok(lock(&self.classes).get(&id).cloned())
+    }
+
+    async fn delete_class(&self, id: Uuid) -> Result<bool, Problem> {
+        Ok(lock(&self.classes).remove(&id).is_some())
+    }
+}
+
+impl AttemptRepository for MemoryStore {
+    async fn record_attempt(&self, attempt: &QuizAttempt) -> Result<(), Problem> {
+        lock(&self.attempts).push(attempt.clone());
+        Ok(())
+    }
+
+    async fn quiz_attempts(&self, quiz: Uuid, user: Uuid) -> Result<Vec<QuizAttempt>, Problem> {
+        Ok(lock(&self.attempts)
+            .iter()
+            .filter(|it| it.quiz == quiz && it.user == user)
+            .cloned()
+            .collect())
+    }
+}
+
+impl SearchDbExt for MemoryStore {
+    async fn search_quizzes(
+        &self,
+        query: &str,
+        viewer: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem> {
+        let documents = to_documents(
+            lock(&self.quizzes)
+                .values()
+                .filter(|it| it.public || Some(it.author) == viewer),
+        )?;
+        Ok(search::scan_documents(
+            &documents,
+            query,
+            "name",
+            search::QUIZ_FIELDS,
+            limit,
+        ))
+    }
+
+    async fn search_classes(
+        &self,
+        query: &str,
+        viewer: Uuid,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem> {
+        let documents = to_documents(
+            lock(&self.classes)
+                .values()
+                .filter(|it| it.participants.iter().any(|p| p.user_id == viewer)),
+        )?;
+        Ok(search::scan_documents(
+            &documents,
+            query,
+            "name",
+            search::CLASS_FIELDS,
+            limit,
+        ))
+    }
+
+    async fn search_users(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, Problem> {
+        let documents = to_documents(lock(&self.users).values())?;
+        Ok(search::scan_documents(
+            &documents,
+            query,
+            "username",
+            search::USER_FIELDS,
+            limit,
+        ))
+    }
+}
+
+impl TagDbExt for MemoryStore {
+    async fn tag_usage(&self, viewer: Option<Uuid>) -> Result<Vec<TagUsage>, Problem> {
+        let quiz_tags = count_tags(
+            lock(&self.quizzes)
+                .values()
+                .filter(|it| it.public)
+                .flat_map(|it| &it.tags),
+        );
+        let class_tags = count_tags(
+            lock(&self.classes)
+                .values()
+                .filter(|it| it.participants.iter().any(|p| Some(p.user_id) == viewer))
+                .flat_map(|it| &it.tags),
+        );
+
+        Ok(combine_usage(quiz_tags, class_tags))
+    }
+
+    async fn recommended_quizzes<R: DeserializeOwned>(
+        &self,
+        viewer: Uuid,
+        interests: &[Tag],
+        projection: Document,
+        limit: i64,
+    ) -> Result<Vec<R>, Problem> {
+        let matched = |quiz: &Quiz| {
+            quiz.tags
+                .iter()
+                .filter(|tag| interests.iter().any(|it| it.as_ref() == tag.as_ref()))
+                .count()
+        };
+
+        let mut quizzes: Vec<(usize, Quiz)> = lock(&self.quizzes)
+            .values()
+            .filter(|it| it.public && it.author != viewer)
+            .map(|it| (matched(it), it.clone()))
+            .filter(|(count, _)| *count > 0)
+            .collect();
+        quizzes.sort_by(|(a_count, a), (b_count, b)| {
+            b_count.cmp(a_count).then(b.created.cmp(&a.created))
+        });
+        quizzes.truncate(limit.max(0) as usize);
+
+        quizzes
+            .into_iter()
+            .map(|(_, quiz)| {
+                let document = query::project(bson::to_document(&quiz)?, &projection);
+                from_document(document).map_err(Problem::from)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::data::quiz::ValidationResult;
+
+    fn signup<'a>(email: &'a str, username: &'a str) -> UserSignupData<'a> {
+        UserSignupData {
+            email: Cow::Borrowed(email),
+            username: Cow::Borrowed(username),
+            password: Cow::Borrowed("correct horse battery staple"),
+        }
+    }
+
+    #[rocket::async_test]
+    async fn users_are_unique_and_found_ignoring_case() {
+        let store = MemoryStore::default();
+        let no_admins: &[String] = &[];
+
+        let (_, user) = store
+            .create_user(signup("memory@example.com", "memory"), "salt", no_admins)
+            .await
+            .unwrap();
+        let duplicate = store
+            .create_user(signup("other@example.com", "Memory"), "salt", no_admins)
+            .await
+            .unwrap_err();
+        assert!(duplicate.is(ProblemType::UserBadUsername));
+
+        let found = store
+            .find_user_by_email("MEMORY@example.com")
+            .await
+            .unwrap();
+        assert_eq!(found.map(|it| it.id), Some(user.id));
+        assert!(store
+            .update_email(user.id, "new@example.com")
+            .await
+            .unwrap());
+        let found = store
+            .find_user_by_username("MEMORY")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.email, "new@example.com");
+        assert!(!found.email_verified);
+
+        assert!(store.delete_user(user.id).await.unwrap().is_some());
+        assert!(store.get_user(user.id).await.unwrap().is_none());
+    }
+
+    #[rocket::async_test]
+    async fn tokens_are_single_use() {
+        let store = MemoryStore::default();
+        let user = Uuid::new_v4();
+
+        let token = store
+            .create_user_token(user, TokenPurpose::PasswordReset, None, Duration::hours(1))
+            .await
+            .unwrap();
+        let expired = store
+            .create_user_token(user, TokenPurpose::PasswordReset, None, Duration::hours(-1))
+            .await
+            .unwrap();
+
+        let wrong_purpose = store
+            .consume_user_token(&token, TokenPurpose::EmailVerification)
+            .await
+            .unwrap();
+        assert!(wrong_purpose.is_none());
+        let consumed = store
+            .consume_user_token(&token, TokenPurpose::PasswordReset)
+            .await
+            .unwrap();
+        assert_eq!(consumed.map(|it| it.user), Some(user));
+        for it in [&token, &expired] {
+            let again = store
+                .consume_user_token(it, TokenPurpose::PasswordReset)
+                .await
+                .unwrap();
+            assert!(again.is_none());
+        }
+    }
+
+    #[rocket::async_test]
+    async fn attempts_are_listed_per_user() {
+        let store = MemoryStore::default();
+        let (quiz, user) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for it in [user, user, Uuid::new_v4()] {
+            store
+                .record_attempt(&QuizAttempt::new(quiz, it, ValidationResult::default()))
+                .await
+                .unwrap();
+        }
+
+        let attempts = store.quiz_attempts(quiz, user).await.unwrap();
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts.iter().all(|it| it.user == user));
+    }
+}
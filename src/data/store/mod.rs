@@ -0,0 +1,408 @@
+//! Data store used by routes.
+//!
+//! Routes access stored data through repository traits ([`UserRepository`], [`QuizRepository`],
+//! [`ClassRepository`], [`AttemptRepository`] and user token, audit and settings traits) which are
+//! implemented for MongoDB [`Database`] and [`MemoryStore`]. [`Store`] is managed by Rocket and
+//! forwards calls to the backend it was created with.
+//!
+//! Paged listings pass MongoDB filters to [`Store::find_documents`]. [`MemoryStore`] evaluates the
+//! filters routes use, and scans text for search instead of using text indexes.
+
+use bson::{doc, Document};
+use chrono::Duration;
+use mongodb::options::FindOptions;
+use mongodb::Database;
+use rocket::futures::TryStreamExt;
+use serde::de::DeserializeOwned;
+use uuid::Uuid;
+
+use crate::data::class::db::ClassRepository;
+use crate::data::class::Class;
+use crate::data::migration;
+use crate::data::quiz::attempt::{AttemptRepository, QuizAttempt};
+use crate::data::quiz::db::QuizRepository;
+use crate::data::quiz::Quiz;
+use crate::data::search::{SearchDbExt, SearchHit};
+use crate::data::tag::{TagDbExt, TagUsage};
+use crate::data::user::audit::{AuditEntry, UserAuditDbExt};
+use crate::data::user::db::{UserRepository, UserSignupData};
+use crate::data::user::profile::{Profile, Tag};
+use crate::data::user::settings::{UserSettings, UserSettingsDbExt};
+use crate::data::user::token::{TokenPurpose, UserToken, UserTokenDbExt};
+use crate::data::user::{AccountStatus, PasswordHash, User};
+use crate::resp::jwt::UserRoleToken;
+use crate::resp::problem::Problem;
+use crate::role::Role;
+
+pub mod memory;
+mod query;
+
+pub use memory::MemoryStore;
+
+#[derive(Debug)]
+pub enum Store {
+    Mongo(Database),
+    Memory(MemoryStore),
+}
+
+impl Store {
+    /// Creates an empty in-memory store.
+    pub fn memory() -> Store {
+        Store::Memory(MemoryStore::default())
+    }
+
+    /// Checks the store can be reached.
+    pub async fn ping(&self) -> Result<(), Problem> {
+        match self {
+            Store::Mongo(db) => db
+                .run_command(doc! { "ping": 1 }, None)
+                .await
+                .map(|_| ())
+                .map_err(Problem::from),
+            Store::Memory(_) => Ok(()),
+        }
+    }
+
+    /// Versions of migrations which weren't applied to the store.
+    pub async fn pending_migrations(&self) -> Result<Vec<u32>, Problem> {
+        match self {
+            Store::Mongo(db) => Ok(migration::pending_migrations(db)
+                .await?
+                .iter()
+                .map(|it| it.version)
+                .collect()),
+            Store::Memory(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// Documents of `collection` matching `filter`, for listings repository traits don't cover.
+    pub async fn find_documents(
+        &self,
+        collection: &str,
+        filter: Document,
+        options: FindOptions,
+    ) -> Result<Vec<Document>, Problem> {
+        match self {
+            Store::Mongo(db) => db
+                .collection::<Document>(collection)
+                .find(filter, options)
+                .await
+                .map_err(Problem::from)?
+                .try_collect()
+                .await
+                .map_err(Problem::from),
+            Store::Memory(store) => store.find_documents(collection, &filter, &options),
+        }
+    }
+
+    /// Number of documents of `collection` matching `filter`.
+    pub async fn count_documents(
+        &self,
+        collection: &str,
+        filter: Document,
+    ) -> Result<u64, Problem> {
+        match self {
+            Store::Mongo(db) => db
+                .collection::<Document>(collection)
+                .count_documents(filter, None)
+                .await
+                .map_err(Problem::from),
+            Store::Memory(store) => store.count_documents(collection, &filter),
+        }
+    }
+}
+
+impl From<Database> for Store {
+    fn from(db: Database) -> Self {
+        Store::Mongo(db)
+    }
+}
+
+impl From<MemoryStore> for Store {
+    fn from(store: MemoryStore) -> Self {
+        Store::Memory(store)
+    }
+}
+
+impl UserRepository for Store {
+    async fn create_user<'a>(
+        &self,
+        create_user: UserSignupData<'_>,
+        salt: impl AsRef<[u8]>,
+        admin_names: impl AsRef<[String]>,
+    ) -> Result<(UserRoleToken, User), Problem> {
+        match self {
+            Store::Mongo(db) => db.create_user(create_user, salt, admin_names).await,
+            Store::Memory(store) => store.create_user(create_user, salt, admin_names).await,
+        }
+    }
+
+    async fn get_user(&self, id: Uuid) -> Result<Option<User>, Problem> {
+        match self {
+            Store::Mongo(db) => db.get_user(id).await,
+            Store::Memory(store) => store.get_user(id).await,
+        }
+    }
+
+    async fn find_user_by_email(&self, email: impl AsRef<str>) -> Result<Option<User>, Problem> {
+        match self {
+            Store::Mongo(db) => db.find_user_by_email(email).await,
+            Store::Memory(store) => store.find_user_by_email(email).await,
+        }
+    }
+
+    async fn find_user_by_username(
+        &self,
+        username: impl AsRef<str>,
+    ) -> Result<Option<User>, Problem> {
+        match self {
+            Store::Mongo(db) => db.find_user_by_username(username).await,
+            Store::Memory(store) => store.find_user_by_username(username).await,
+        }
+    }
+
+    async fn update_password(&self, id: Uuid, pw_hash: PasswordHash) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.update_password(id, pw_hash).await,
+            Store::Memory(store) => store.update_password(id, pw_hash).await,
+        }
+    }
+
+    async fn set_email_verified(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.set_email_verified(id, email).await,
+            Store::Memory(store) => store.set_email_verified(id, email).await,
+        }
+    }
+
+    async fn update_profile(&self, id: Uuid, profile: &Profile) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.update_profile(id, profile).await,
+            Store::Memory(store) => store.update_profile(id, profile).await,
+        }
+    }
+
+    async fn update_role(&self, id: Uuid, role: Role) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.update_role(id, role).await,
+            Store::Memory(store) => store.update_role(id, role).await,
+        }
+    }
+
+    async fn update_status(&self, id: Uuid, status: &AccountStatus) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.update_status(id, status).await,
+            Store::Memory(store) => store.update_status(id, status).await,
+        }
+    }
+
+    async fn update_username(&self, id: Uuid, username: impl AsRef<str>) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.update_username(id, username).await,
+            Store::Memory(store) => store.update_username(id, username).await,
+        }
+    }
+
+    async fn update_email(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.update_email(id, email).await,
+            Store::Memory(store) => store.update_email(id, email).await,
+        }
+    }
+
+    async fn delete_user(&self, id: Uuid) -> Result<Option<User>, Problem> {
+        match self {
+            Store::Mongo(db) => db.delete_user(id).await,
+            Store::Memory(store) => store.delete_user(id).await,
+        }
+    }
+}
+
+impl UserTokenDbExt for Store {
+    async fn create_user_token(
+        &self,
+        user: Uuid,
+        purpose: TokenPurpose,
+        email: Option<String>,
+        ttl: Duration,
+    ) -> Result<String, Problem> {
+        match self {
+            Store::Mongo(db) => db.create_user_token(user, purpose, email, ttl).await,
+            Store::Memory(store) => store.create_user_token(user, purpose, email, ttl).await,
+        }
+    }
+
+    async fn consume_user_token(
+        &self,
+        token: impl AsRef<str>,
+        purpose: TokenPurpose,
+    ) -> Result<Option<UserToken>, Problem> {
+        match self {
+            Store::Mongo(db) => db.consume_user_token(token, purpose).await,
+            Store::Memory(store) => store.consume_user_token(token, purpose).await,
+        }
+    }
+
+    async fn delete_user_tokens(&self, user: Uuid, purpose: TokenPurpose) -> Result<u64, Problem> {
+        match self {
+            Store::Mongo(db) => db.delete_user_tokens(user, purpose).await,
+            Store::Memory(store) => store.delete_user_tokens(user, purpose).await,
+        }
+    }
+}
+
+impl UserAuditDbExt for Store {
+    async fn record_audit(&self, entry: &AuditEntry) -> Result<(), Problem> {
+        match self {
+            Store::Mongo(db) => db.record_audit(entry).await,
+            Store::Memory(store) => store.record_audit(entry).await,
+        }
+    }
+
+    async fn user_audit(&self, user: Uuid) -> Result<Vec<AuditEntry>, Problem> {
+        match self {
+            Store::Mongo(db) => db.user_audit(user).await,
+            Store::Memory(store) => store.user_audit(user).await,
+        }
+    }
+}
+
+impl UserSettingsDbExt for Store {
+    async fn get_user_settings(&self, user: Uuid) -> Result<UserSettings, Problem> {
+        match self {
+            Store::Mongo(db) => db.get_user_settings(user).await,
+            Store::Memory(store) => store.get_user_settings(user).await,
+        }
+    }
+
+    async fn save_user_settings(&self, user: Uuid, settings: &UserSettings) -> Result<(), Problem> {
+        match self {
+            Store::Mongo(db) => db.save_user_settings(user, settings).await,
+            Store::Memory(store) => store.save_user_settings(user, settings).await,
+        }
+    }
+}
+
+impl QuizRepository for Store {
+    async fn create_quiz(&self, quiz: &Quiz) -> Result<(), Problem> {
+        match self {
+            Store::Mongo(db) => db.create_quiz(quiz).await,
+            Store::Memory(store) => store.create_quiz(quiz).await,
+        }
+    }
+
+    async fn get_quiz(&self, id: Uuid) -> Result<Option<Quiz>, Problem> {
+        match self {
+            Store::Mongo(db) => db.get_quiz(id).await,
+            Store::Memory(store) => store.get_quiz(id).await,
+        }
+    }
+
+    async fn delete_quiz(&self, id: Uuid) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.delete_quiz(id).await,
+            Store::Memory(store) => store.delete_quiz(id).await,
+        }
+    }
+}
+
+impl ClassRepository for Store {
+    async fn create_class(&self, class: &Class) -> Result<(), Problem> {
+        match self {
+            Store::Mongo(db) => db.create_class(class).await,
+            Store::Memory(store) => store.create_class(class).await,
+        }
+    }
+
+    async fn get_class(&self, id: Uuid) -> Result<Option<Class>, Problem> {
+        match self {
+            Store::Mongo(db) => db.get_class(id).await,
+            Store::Memory(store) => store.get_class(id).await,
+        }
+    }
+
+    async fn delete_class(&self, id: Uuid) -> Result<bool, Problem> {
+        match self {
+            Store::Mongo(db) => db.delete_class(id).await,
+            Store::Memory(store) => store.delete_class(id).await,
+        }
+    }
+}
+
+impl AttemptRepository for Store {
+    async fn record_attempt(&self, attempt: &QuizAttempt) -> Result<(), Problem> {
+        match self {
+            Store::Mongo(db) => db.record_attempt(attempt).await,
+            Store::Memory(store) => store.record_attempt(attempt).await,
+        }
+    }
+
+    async fn quiz_attempts(&self, quiz: Uuid, user: Uuid) -> Result<Vec<QuizAttempt>, Problem> {
+        match self {
+            Store::Mongo(db) => db.quiz_attempts(quiz, user).await,
+            Store::Memory(store) => store.quiz_attempts(quiz, user).await,
+        }
+    }
+}
+
+impl SearchDbExt for Store {
+    async fn search_quizzes(
+        &self,
+        query: &str,
+        viewer: Option<Uuid>,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem> {
+        match self {
+            Store::Mongo(db) => db.search_quizzes(query, viewer, limit).await,
+            Store::Memory(store) => store.search_quizzes(query, viewer, limit).await,
+        }
+    }
+
+    async fn search_classes(
+        &self,
+        query: &str,
+        viewer: Uuid,
+        limit: i64,
+    ) -> Result<Vec<SearchHit>, Problem> {
+        match self {
+            Store::Mongo(db) => db.search_classes(query, viewer, limit).await,
+            Store::Memory(store) => store.search_classes(query, viewer, limit).await,
+        }
+    }
+
+    async fn search_users(&self, query: &str, limit: i64) -> Result<Vec<SearchHit>, Problem> {
+        match self {
+            Store::Mongo(db) => db.search_users(query, limit).await,
+            Store::Memory(store) => store.search_users(query, limit).await,
+        }
+    }
+}
+
+impl TagDbExt for Store {
+    async fn tag_usage(&self, viewer: Option<Uuid>) -> Result<Vec<TagUsage>, Problem> {
+        match self {
+            Store::Mongo(db) => db.tag_usage(viewer).await,
+            Store::Memory(store) => store.tag_usage(viewer).await,
+        }
+    }
+
+    async fn recommended_quizzes<R: DeserializeOwned>(
+        &self,
+        viewer: Uuid,
+        interests: &[Tag],
+        projection: Document,
+        limit: i64,
+    ) -> Result<Vec<R>, Problem> {
+        match self {
+            Store::Mongo(db) => {
+                db.recommended_quizzes(viewer, interests, projection, limit)
+                    .await
+            }
+            Store::Memory(store) => {
+                store
+                    .recommended_quizzes(viewer, interests, projection, limit)
+                    .await
+            }
+        }
+    }
+}
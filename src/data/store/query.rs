@@ -0,0 +1,262 @@
+//! Evaluation of MongoDB queries over documents of [`super::MemoryStore`].
+//!
+//! Only the parts of the query language routes use are supported: `$and`, `$or`, field
+//! equality (including values in arrays and `null` for missing fields), `$ne`, `$in`, `$gt`,
+//! `$gte`, `$lt`, `$lte` and `$regex` with patterns built by [`crate::util::escape_regex`], which
+//! are matched as plain text. Fields can be nested paths, e.g. `participants.user_id`.
+
+use std::cmp::Ordering;
+
+use bson::{Bson, Document};
+
+/// Checks whether `document` matches `filter`. Unsupported operators never match.
+pub fn matches(document: &Document, filter: &Document) -> bool {
+    filter.iter().all(|(key, condition)| match key.as_str() {
+        "$and" => conditions(condition).all(|it| matches(document, it)),
+        "$or" => conditions(condition).any(|it| matches(document, it)),
+        _ => matches_field(&field_values(document, key), condition),
+    })
+}
+
+fn items(value: &Bson) -> impl Iterator<Item = &Bson> {
+    value.as_array().into_iter().flatten()
+}
+
+fn conditions(value: &Bson) -> impl Iterator<Item = &Document> {
+    items(value).filter_map(Bson::as_document)
+}
+
+fn is_operator_document(value: &Bson) -> bool {
+    match value {
+        Bson::Document(doc) => doc.keys().next().map_or(false, |it| it.starts_with('$')),
+        _ => false,
+    }
+}
+
+fn matches_field(values: &[&Bson], condition: &Bson) -> bool {
+    let operators = match condition {
+        Bson::Document(operators) if is_operator_document(condition) => operators,
+        _ => return equals_any(values, condition),
+    };
+
+    operators
+        .iter()
+        .all(|(operator, operand)| match operator.as_str() {
+            "$eq" => equals_any(values, operand),
+            "$ne" => !equals_any(values, operand),
+            "$in" => items(operand).any(|it| equals_any(values, it)),
+            "$gt" => compares_any(values, operand, |it| it == Ordering::Greater),
+            "$gte" => compares_any(values, operand, |it| it != Ordering::Less),
+            "$lt" => compares_any(values, operand, |it| it == Ordering::Less),
+            "$lte" => compares_any(values, operand, |it| it != Ordering::Greater),
+            "$regex" => {
+                let ignore_case = operators
+                    .get_str("$options")
+                    .map_or(false, |it| it.contains('i'));
+                operand
+                    .as_str()
+                    .map_or(false, |pattern| contains_any(values, pattern, ignore_case))
+            }
+            "$options" => true,
+            _ => false,
+        })
+}
+
+/// Values at `path` of `document`, with items of arrays on the way and at the end.
+fn field_values<'a>(document: &'a Document, path: &str) -> Vec<&'a Bson> {
+    let mut values = vec![];
+    let path: Vec<&str> = path.split('.').collect();
+    if let Some(value) = document.get(path[0]) {
+        collect_values(value, &path[1..], &mut values);
+    }
+    values
+}
+
+fn collect_values<'a>(value: &'a Bson, path: &[&str], values: &mut Vec<&'a Bson>) {
+    match (value, path.split_first()) {
+        (Bson::Array(items), None) => {
+            values.push(value);
+            values.extend(items.iter());
+        }
+        (Bson::Array(items), Some(_)) => {
+            for it in items {
+                collect_values(it, path, values);
+            }
+        }
+        (_, None) => values.push(value),
+        (Bson::Document(doc), Some((key, rest))) => {
+            if let Some(it) = doc.get(*key) {
+                collect_values(it, rest, values);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn equals_any(values: &[&Bson], expected: &Bson) -> bool {
+    match expected {
+        // Missing fields are equal to null
+        Bson::Null => values.is_empty() || values.iter().any(|it| **it == Bson::Null),
+        _ => values
+            .iter()
+            .any(|it| compare(it, expected) == Some(Ordering::Equal)),
+    }
+}
+
+fn compares_any(values: &[&Bson], operand: &Bson, accept: impl Fn(Ordering) -> bool) -> bool {
+    values
+        .iter()
+        .any(|it| compare(it, operand).map_or(false, &accept))
+}
+
+fn contains_any(values: &[&Bson], pattern: &str, ignore_case: bool) -> bool {
+    let mut literal = String::with_capacity(pattern.len());
+    let mut escaped = false;
+    for c in pattern.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            _ => {
+                literal.push(c);
+                escaped = false;
+            }
+        }
+    }
+
+    let fold = |text: &str| match ignore_case {
+        true => text.to_lowercase(),
+        false => text.to_string(),
+    };
+    let literal = fold(&literal);
+    values
+        .iter()
+        .filter_map(|it| it.as_str())
+        .any(|it| fold(it).contains(&literal))
+}
+
+/// Compares values of the same kind, numbers are compared with each other.
+pub fn compare(a: &Bson, b: &Bson) -> Option<Ordering> {
+    let number = |value: &Bson| match value {
+        Bson::Int32(it) => Some(*it as f64),
+        Bson::Int64(it) => Some(*it as f64),
+        Bson::Double(it) => Some(*it),
+        _ => None,
+    };
+
+    match (a, b) {
+        (Bson::String(a), Bson::String(b)) => Some(a.cmp(b)),
+        (Bson::Boolean(a), Bson::Boolean(b)) => Some(a.cmp(b)),
+        (Bson::DateTime(a), Bson::DateTime(b)) => Some(a.cmp(b)),
+        (Bson::Binary(a), Bson::Binary(b)) => Some(
+            a.bytes
+                .len()
+                .cmp(&b.bytes.len())
+                .then(a.bytes.cmp(&b.bytes)),
+        ),
+        (Bson::Null, Bson::Null) => Some(Ordering::Equal),
+        _ => number(a)?.partial_cmp(&number(b)?),
+    }
+}
+
+/// Orders documents by `sort`, a MongoDB sort document. Missing values come first.
+pub fn sort(documents: &mut [Document], sort: &Document) {
+    documents.sort_by(|a, b| {
+        sort.iter()
+            .map(|(key, direction)| {
+                let ordering = match (a.get(key), b.get(key)) {
+                    (Some(a), Some(b)) => compare(a, b).unwrap_or(Ordering::Equal),
+                    (a, b) => a.is_some().cmp(&b.is_some()),
+                };
+                match direction.as_i32() == Some(-1) || direction.as_i64() == Some(-1) {
+                    true => ordering.reverse(),
+                    false => ordering,
+                }
+            })
+            .find(|it| *it != Ordering::Equal)
+            .unwrap_or(Ordering::Equal)
+    });
+}
+
+/// Keeps only top level fields included by `projection` and `_id`.
+pub fn project(document: Document, projection: &Document) -> Document {
+    let included: Vec<&str> = projection
+        .keys()
+        .map(|it| it.split('.').next().unwrap_or(it))
+        .collect();
+    document
+        .into_iter()
+        .filter(|(key, _)| key == "_id" || included.contains(&key.as_str()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::data::id;
+    use crate::util::escape_regex;
+
+    #[test]
+    fn filters_match_like_mongodb() {
+        let author = Uuid::new_v4();
+        let document = doc! {
+            "_id": id::to_bson(Uuid::new_v4()),
+            "name": "Cell biology (part 1)",
+            "author": id::to_bson(author),
+            "created": "2023-01-31T12:00:00.000Z",
+            "tags": ["biology", "cells"],
+            "open_on": null,
+            "participants": [{ "user_id": id::to_bson(author) }],
+        };
+
+        let matching = [
+            doc! {},
+            id::eq("author", author),
+            id::eq("participants.user_id", author),
+            doc! { "tags": "cells" },
+            doc! { "close_on": null, "open_on": null },
+            doc! { "public": { "$ne": false } },
+            doc! { "tags": { "$in": ["physics", "biology"] } },
+            doc! { "created": { "$gte": "2023-01-31T12:00:00.000Z", "$lt": "2023-02-01" } },
+            doc! { "name": { "$regex": escape_regex("BIOLOGY (PART"), "$options": "i" } },
+            doc! { "$or": [{ "tags": "physics" }, { "$and": [{ "tags": "biology" }] }] },
+        ];
+        for filter in &matching {
+            assert!(matches(&document, filter), "{} should match", filter);
+        }
+
+        let other = Uuid::new_v4();
+        let not_matching = [
+            id::eq("author", other),
+            id::eq("participants.user_id", other),
+            doc! { "tags": "physics" },
+            doc! { "name": null },
+            doc! { "tags": { "$ne": "cells" } },
+            doc! { "created": { "$gt": "2023-01-31T12:00:00.000Z" } },
+            doc! { "name": { "$regex": escape_regex("BIOLOGY") } },
+            doc! { "$and": [{ "tags": "biology" }, { "tags": "physics" }] },
+            doc! { "name": { "$exists": true } },
+        ];
+        for filter in &not_matching {
+            assert!(!matches(&document, filter), "{} shouldn't match", filter);
+        }
+    }
+
+    #[test]
+    fn documents_are_sorted_by_keys() {
+        let mut documents = vec![
+            doc! { "name": "b", "count": 1 },
+            doc! { "name": "a", "count": 2_i64 },
+            doc! { "name": "c", "count": 1 },
+            doc! { "count": 1 },
+        ];
+
+        sort(&mut documents, &doc! { "count": -1, "name": 1 });
+        let names: Vec<&str> = documents
+            .iter()
+            .map(|it| it.get_str("name").unwrap_or_default())
+            .collect();
+        assert_eq!(names, ["a", "", "b", "c"]);
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::BTreeMap;
+
+use bson::{doc, from_document, Document};
+use mongodb::Database;
+use rocket::futures::TryStreamExt;
+use serde::de::DeserializeOwned;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::data::class::CLASS_COLLECTION_NAME;
+use crate::data::id;
+use crate::data::quiz::QUIZ_COLLECTION_NAME;
+use crate::data::user::profile::Tag;
+use crate::resp::problem::Problem;
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TagUsage {
+    pub tag: Tag,
+    /// Number of public quizzes with the tag
+    pub quizzes: u64,
+    /// Number of the caller's classes with the tag
+    pub classes: u64,
+}
+
+impl TagUsage {
+    pub fn total(&self) -> u64 {
+        self.quizzes + self.classes
+    }
+}
+
+async fn count_tags(
+    db: &Database,
+    collection: &str,
+    filter: Document,
+) -> Result<Vec<(String, u64)>, Problem> {
+    let pipeline = [
+        doc! { "$match": filter },
+        doc! { "$unwind": "$tags" },
+        doc! { "$group": { "_id": "$tags", "count": { "$sum": 1 } } },
+    ];
+
+    let documents: Vec<Document> = db
+        .collection::<Document>(collection)
+        .aggregate(pipeline, None)
+        .await
+        .map_err(Problem::from)?
+        .try_collect()
+        .await
+        .map_err(Problem::from)?;
+
+    Ok(documents
+        .into_iter()
+        .filter_map(|it| {
+            let tag = it.get_str("_id").ok()?.to_string();
+            let count = match it.get("count")? {
+                bson::Bson::Int32(count) => *count as u64,
+                bson::Bson::Int64(count) => *count as u64,
+                _ => return None,
+            };
+            Some((tag, count))
+        })
+        .collect())
+}
+
+/// Combines tag counts of quizzes and classes, most used tags first.
+pub fn combine_usage(
+    quiz_tags: Vec<(String, u64)>,
+    class_tags: Vec<(String, u64)>,
+) -> Vec<TagUsage> {
+    let mut usage: BTreeMap<String, TagUsage> = BTreeMap::new();
+    for (tag, count, is_quiz) in quiz_tags
+        .into_iter()
+        .map(|(tag, count)| (tag, count, true))
+        .chain(
+            class_tags
+                .into_iter()
+                .map(|(tag, count)| (tag, count, false)),
+        )
+    {
+        let entry = usage.entry(tag.clone()).or_insert_with(|| TagUsage {
+            tag: Tag::from(tag),
+            quizzes: 0,
+            classes: 0,
+        });
+        match is_quiz {
+            true => entry.quizzes = count,
+            false => entry.classes = count,
+        }
+    }
+
+    let mut result: Vec<TagUsage> = usage.into_values().collect();
+    result.sort_by(|a, b| b.total().cmp(&a.total()));
+    result
+}
+
+pub trait TagDbExt {
+    /// Lists tags of public quizzes and of classes `viewer` participates in, most used first.
+    async fn tag_usage(&self, viewer: Option<Uuid>) -> Result<Vec<TagUsage>, Problem>;
+
+    /// Lists public quizzes not authored by `viewer` that are tagged with some of the
+    /// `interests`. Quizzes matching more interests come first, then newer ones.
+    async fn recommended_quizzes<R: DeserializeOwned>(
+        &self,
+        viewer: Uuid,
+        interests: &[Tag],
+        projection: Document,
+        limit: i64,
+    ) -> Result<Vec<R>, Problem>;
+}
+
+impl TagDbExt for Database {
+    async fn tag_usage(&self, viewer: Option<Uuid>) -> Result<Vec<TagUsage>, Problem> {
+        // Private quizzes aren't counted so their topics aren't revealed
+        let quiz_tags = count_tags(
+            self,
+            QUIZ_COLLECTION_NAME,
+            doc! { "public": { "$ne": false } },
+        )
+        .await?;
+        let class_tags = match viewer {
+            Some(viewer) => {
+                count_tags(
+                    self,
+                    CLASS_COLLECTION_NAME,
+                    id::eq("participants.user_id", viewer),
+                )
+                .await?
+            }
+            None => Vec::new(),
+        };
+
+        Ok(combine_usage(quiz_tags, class_tags))
+    }
+
+    async fn recommended_quizzes<R: DeserializeOwned>(
+        &self,
+        viewer: Uuid,
+        interests: &[Tag],
+        projection: Document,
+        limit: i64,
+    ) -> Result<Vec<R>, Problem> {
+        if interests.is_empty() {
+            return Ok(vec![]);
+        }
+        let interests: Vec<&str> = interests.iter().map(AsRef::as_ref).collect();
+
+        let pipeline = [
+            doc! { "$match": {
+                "public": { "$ne": false },
+                "author": { "$ne": id::to_bson(viewer) },
+                "tags": { "$in": interests.clone() },
+            } },
+            doc! { "$addFields": {
+                "matched_interests": { "$size": { "$setIntersection": ["$tags", interests] } },
+            } },
+            doc! { "$sort": { "matched_interests": -1, "created": -1 } },
+            doc! { "$limit": limit },
+            doc! { "$project": projection },
+        ];
+
+        let documents: Vec<Document> = self
+            .collection::<Document>(QUIZ_COLLECTION_NAME)
+            .aggregate(pipeline, None)
+            .await
+            .map_err(Problem::from)?
+            .try_collect()
+            .await
+            .map_err(Problem::from)?;
+
+        Ok(documents
+            .into_iter()
+            .filter_map(|it| match from_document(it) {
+                Ok(quiz) => Some(quiz),
+                Err(err) => {
+                    tracing::warn!("Unable to read recommended quiz: {}", err);
+                    None
+                }
+            })
+            .collect())
+    }
+}
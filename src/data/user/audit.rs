@@ -0,0 +1,86 @@
+use bson::doc;
+use chrono::{DateTime, Utc};
+use mongodb::options::FindOptions;
+use mongodb::Database;
+use rocket::futures::TryStreamExt;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::AccountStatus;
+use crate::data::id;
+use crate::resp::problem::Problem;
+use crate::role::Role;
+
+pub static USER_AUDIT_COLLECTION_NAME: &str = "user.audit";
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub enum AuditAction {
+    RoleChanged {
+        from: Role,
+        to: Role,
+    },
+    StatusChanged {
+        from: AccountStatus,
+        to: AccountStatus,
+    },
+}
+
+/// Record of an administrative change made to a user account.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEntry {
+    #[serde(rename = "_id", with = "crate::data::id::binary")]
+    pub id: Uuid,
+    /// Changed user
+    #[serde(with = "crate::data::id::binary")]
+    pub user: Uuid,
+    /// User who made the change
+    #[serde(with = "crate::data::id::binary")]
+    pub actor: Uuid,
+    pub action: AuditAction,
+    pub reason: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub time: DateTime<Utc>,
+}
+
+impl AuditEntry {
+    pub fn new(user: Uuid, actor: Uuid, action: AuditAction, reason: impl ToString) -> AuditEntry {
+        AuditEntry {
+            id: Uuid::new_v4(),
+            user,
+            actor,
+            action,
+            reason: reason.to_string(),
+            time: Utc::now(),
+        }
+    }
+}
+
+pub trait UserAuditDbExt {
+    async fn record_audit(&self, entry: &AuditEntry) -> Result<(), Problem>;
+
+    /// Lists changes made to `user`, newest first.
+    async fn user_audit(&self, user: Uuid) -> Result<Vec<AuditEntry>, Problem>;
+}
+
+impl UserAuditDbExt for Database {
+    async fn record_audit(&self, entry: &AuditEntry) -> Result<(), Problem> {
+        self.collection::<AuditEntry>(USER_AUDIT_COLLECTION_NAME)
+            .insert_one(entry, None)
+            .await
+            .map_err(Problem::from)?;
+        Ok(())
+    }
+
+    async fn user_audit(&self, user: Uuid) -> Result<Vec<AuditEntry>, Problem> {
+        self.collection::<AuditEntry>(USER_AUDIT_COLLECTION_NAME)
+            .find(
+                id::eq("user", user),
+                FindOptions::builder().sort(doc! { "time": -1 }).build(),
+            )
+            .await
+            .map_err(Problem::from)?
+            .try_collect()
+            .await
+            .map_err(Problem::from)
+    }
+}
@@ -1,26 +1,70 @@
 use std::borrow::Cow;
 
+use bson::doc;
+use mongodb::options::{Collation, CollationStrength, FindOneOptions};
 use mongodb::Database;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::error::BackendError;
+use crate::logging::redact_email;
 use crate::resp::jwt::UserRoleToken;
+use crate::resp::validation::{FieldError, FieldErrorCode, Validate, Validator};
 use crate::{resp::problem::Problem, role::Role};
 
 use super::filter;
-use super::{PasswordHash, User};
+use super::policy::{normalize_username, username_skeleton, CredentialPolicy};
+use super::profile::Profile;
+use super::{AccountStatus, PasswordHash, User};
 
 pub static USER_COLLECTION_NAME: &str = "user";
 pub static USER_SETTINGS_COLLECTION_NAME: &str = "user.settings";
 
+// Unique indexes created by migrations, violations are reported for the field they cover.
+static EMAIL_INDEX_NAME: &str = "email_unique";
+static USERNAME_INDEX_NAME: &str = "username_unique";
+static USERNAME_SKELETON_INDEX_NAME: &str = "username_skeleton_unique";
+
+/// Collation used for comparing emails and usernames, ignores letter case.
+pub fn case_insensitive() -> Collation {
+    Collation::builder()
+        .locale("en")
+        .strength(CollationStrength::Secondary)
+        .build()
+}
+
+/// Translates unique index violations into a [`Problem`] for the conflicting field.
+fn unique_problem(e: mongodb::error::Error, email: &str, username: &str) -> Problem {
+    use mongodb::error::{ErrorKind, WriteFailure};
+
+    let message = match e.kind.as_ref() {
+        ErrorKind::Write(WriteFailure::WriteError(err)) if err.code == 11000 => err.message.clone(),
+        _ => return Problem::from(e),
+    };
+
+    if message.contains(EMAIL_INDEX_NAME) {
+        problem::bad_email(email, "Email already registered.")
+    } else if message.contains(USERNAME_SKELETON_INDEX_NAME) {
+        problem::bad_username(
+            username,
+            "Username looks too similar to an already used one.",
+        )
+    } else if message.contains(USERNAME_INDEX_NAME) {
+        problem::bad_username(username, "Username already used.")
+    } else {
+        Problem::from(e)
+    }
+}
+
 pub mod problem {
+    use crate::data::user::AccountStatus;
+    use crate::resp::catalog::ProblemType;
     use crate::resp::problem::Problem;
-    use rocket::http::Status;
     use uuid::Uuid;
 
     #[inline]
     pub fn bad_email(email: impl ToString, detail: impl ToString) -> Problem {
-        Problem::new_untyped(Status::BadRequest, "Bad email.")
+        Problem::typed(ProblemType::UserBadEmail)
             .insert_str("email", email)
             .detail(detail)
             .to_owned()
@@ -28,36 +72,54 @@ pub mod problem {
 
     #[inline]
     pub fn bad_username(username: impl ToString, detail: impl ToString) -> Problem {
-        Problem::new_untyped(Status::BadRequest, "Bad username.")
+        Problem::typed(ProblemType::UserBadUsername)
             .insert_str("username", username)
             .detail(detail)
             .to_owned()
     }
 
     #[inline]
-    pub fn bad_password(detail: impl ToString) -> Problem {
-        Problem::new_untyped(Status::BadRequest, "Bad password.")
-            .detail(detail)
+    pub fn bad_token() -> Problem {
+        Problem::typed(ProblemType::UserBadToken)
+            .detail("Token is invalid, expired or was already used.")
+            .to_owned()
+    }
+
+    #[inline]
+    pub fn account_inactive(status: &AccountStatus) -> Problem {
+        let kind = match status {
+            AccountStatus::Banned => ProblemType::AuthAccountBanned,
+            _ => ProblemType::AuthAccountSuspended,
+        };
+
+        Problem::typed(kind)
+            .insert("account_status", status)
+            .to_owned()
+    }
+
+    #[inline]
+    pub fn email_not_verified() -> Problem {
+        Problem::typed(ProblemType::AuthEmailNotVerified)
+            .detail("Verify your email address before participating in quizzes.")
             .to_owned()
     }
 
     #[inline]
     pub fn not_found(id: Uuid) -> Problem {
-        Problem::new_untyped(Status::NotFound, "User doesn't exist.")
+        Problem::typed(ProblemType::UserNotFound)
             .insert("id", id.to_string())
             .clone()
     }
 
     #[inline]
     pub fn bad_login(is_email: bool) -> Problem {
-        Problem::new_untyped(
-            Status::Unauthorized,
-            if is_email {
+        Problem::typed(ProblemType::AuthBadLogin)
+            .detail(if is_email {
                 "Bad email or password."
             } else {
                 "Bad username or password."
-            },
-        )
+            })
+            .to_owned()
     }
 }
 
@@ -71,15 +133,6 @@ pub struct UserSignupData<'r> {
 }
 
 impl<'r> UserSignupData<'r> {
-    pub fn id(&self) -> Uuid {
-        Uuid::new_v5(
-            &Uuid::NAMESPACE_OID,
-            [self.email.as_ref(), self.username.as_ref()]
-                .join("")
-                .as_bytes(),
-        )
-    }
-
     pub fn to_user(&self, salt: impl AsRef<[u8]>) -> User {
         User::new(&self.email, &self.username, &self.password, salt)
     }
@@ -91,42 +144,51 @@ impl std::fmt::Debug for UserSignupData<'_> {
     }
 }
 
-impl UserSignupData<'_> {
-    pub fn validate(&self) -> Result<(), Problem> {
-        if !self.email.contains("@") {
-            return Err(problem::bad_email(
-                self.email.to_string(),
-                "Not a valid e-mail address.",
-            ));
-        }
-
-        if self.username.len() < 5 {
-            return Err(problem::bad_username(
-                self.username.to_string(),
-                "Username must be at least 5 characters (bytes) long.",
-            ));
-        }
-
-        if self.username.len() > 32 {
-            return Err(problem::bad_username(
-                self.username.to_string(),
-                "Username can't be longer than 32 (bytes) characters.",
-            ));
-        }
+/// Checks whether `email` is shaped like a deliverable address.
+///
+/// Actual ownership is confirmed through email verification.
+pub fn is_valid_email(email: impl AsRef<str>) -> bool {
+    let email = email.as_ref();
+    if email.len() > 254 || email.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return false;
+    }
 
-        if self.password.len() <= 8 {
-            return Err(problem::bad_password(
-                "Password must be at least 8 characters (bytes) long.",
-            ));
-        }
+    let (local, domain) = match email.rsplit_once('@') {
+        Some(it) => it,
+        None => return false,
+    };
+
+    !local.is_empty()
+        && local.len() <= 64
+        && !local.contains('@')
+        && domain.contains('.')
+        && domain
+            .split('.')
+            .all(|label| !label.is_empty() && !label.starts_with('-') && !label.ends_with('-'))
+}
 
-        if self.password.len() > 1024 {
-            return Err(problem::bad_password(
-                "Passwords longer than 1024 characters aren't supported.",
-            ));
-        }
+impl Validate for UserSignupData<'_> {
+    fn check(&self, validator: &mut Validator<'_>) {
+        let policy = CredentialPolicy::current(validator);
+        check_email(validator, "email", &self.email);
+        policy.check_username(validator, "username", &self.username);
+
+        let email_name = self.email.split('@').next().unwrap_or_default();
+        policy.check_password(
+            validator,
+            "password",
+            &self.password,
+            &[&self.username, email_name],
+        );
+    }
+}
 
-        Ok(())
+pub fn check_email(validator: &mut Validator<'_>, field: &str, email: impl AsRef<str>) {
+    let email = email.as_ref();
+    if email.is_empty() {
+        validator.push(FieldError::new(field, FieldErrorCode::Required));
+    } else if !is_valid_email(email) {
+        validator.push(FieldError::new(field, FieldErrorCode::BadFormat));
     }
 }
 
@@ -139,7 +201,7 @@ pub struct UserLoginData {
 
 impl std::fmt::Debug for UserLoginData {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "UserLoginInfo:{}", self.username)
+        write!(f, "UserLoginInfo:{}", redact_email(&self.username))
     }
 }
 
@@ -147,22 +209,153 @@ impl UserLoginData {
     pub fn is_email(&self) -> bool {
         self.username.contains("@")
     }
+}
 
-    pub fn validate(&self, is_email: bool) -> Result<(), Problem> {
-        if self.username.len() < 5
-            || self.username.len() > 32
-            || self.password.len() < 8
-            || self.password.len() > 50
-        {
-            return Err(problem::bad_login(is_email));
+impl Validate for UserLoginData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        let policy = CredentialPolicy::current(validator);
+        match self.is_email() {
+            true => check_email(validator, "username", &self.username),
+            false => policy.check_login_username(validator, "username", &self.username),
         }
+        policy.check_login_password(validator, "password", &self.password);
+    }
+}
+
+#[derive(Clone, FromForm, ToSchema)]
+pub struct PasswordChangeData {
+    #[schema(format = "password")]
+    pub current_password: String,
+    #[schema(format = "password")]
+    pub new_password: String,
+}
+
+impl std::fmt::Debug for PasswordChangeData {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PasswordChangeData")
+    }
+}
+
+impl Validate for PasswordChangeData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        validator.check(
+            !self.current_password.is_empty(),
+            "current_password",
+            FieldErrorCode::Required,
+        );
+        let policy = CredentialPolicy::current(validator);
+        policy.check_password(validator, "new_password", &self.new_password, &[]);
+    }
+}
+
+#[derive(Debug, Clone, FromForm, ToSchema)]
+pub struct UsernameChangeData {
+    pub username: String,
+}
+
+impl Validate for UsernameChangeData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        let policy = CredentialPolicy::current(validator);
+        policy.check_username(validator, "username", &self.username);
+    }
+}
+
+#[derive(Clone, FromForm, ToSchema)]
+pub struct EmailChangeData {
+    #[schema(format = "email")]
+    pub email: String,
+    /// Current password of the user
+    #[schema(format = "password")]
+    pub password: String,
+}
+
+impl std::fmt::Debug for EmailChangeData {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EmailChangeData:{}", redact_email(&self.email))
+    }
+}
+
+impl Validate for EmailChangeData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        check_email(validator, "email", &self.email);
+        validator.check(
+            !self.password.is_empty(),
+            "password",
+            FieldErrorCode::Required,
+        );
+    }
+}
+
+#[derive(Clone, FromForm, ToSchema)]
+pub struct EmailVerificationData {
+    /// Token received by email
+    pub token: String,
+}
+
+impl std::fmt::Debug for EmailVerificationData {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EmailVerificationData")
+    }
+}
+
+impl Validate for EmailVerificationData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        validator.check(!self.token.is_empty(), "token", FieldErrorCode::Required);
+    }
+}
+
+#[derive(Clone, FromForm, ToSchema)]
+pub struct PasswordResetRequestData {
+    /// Email or username of the account
+    pub identifier: String,
+}
 
-        Ok(())
+impl std::fmt::Debug for PasswordResetRequestData {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PasswordResetRequestData:{}",
+            redact_email(&self.identifier)
+        )
+    }
+}
+
+impl Validate for PasswordResetRequestData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        validator.check(
+            !self.identifier.trim().is_empty(),
+            "identifier",
+            FieldErrorCode::Required,
+        );
+    }
+}
+
+#[derive(Clone, FromForm, ToSchema)]
+pub struct PasswordResetData {
+    /// Token received by email
+    pub token: String,
+    #[schema(format = "password")]
+    pub password: String,
+}
+
+impl std::fmt::Debug for PasswordResetData {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PasswordResetData")
+    }
+}
+
+impl Validate for PasswordResetData {
+    fn check(&self, validator: &mut Validator<'_>) {
+        validator.check(!self.token.is_empty(), "token", FieldErrorCode::Required);
+        let policy = CredentialPolicy::current(validator);
+        policy.check_password(validator, "password", &self.password, &[]);
     }
 }
 
-// TODO: CreateUserDbExt fns shouldn't be erroring with Problem
-pub trait CreateUserDbExt {
+/// Stored user accounts.
+// TODO: UserRepository fns shouldn't be erroring with Problem
+pub trait UserRepository {
+    /// Stores a new user. Fails if email or username are already used.
     async fn create_user<'a>(
         &self,
         create_user: UserSignupData<'_>,
@@ -178,42 +371,32 @@ pub trait CreateUserDbExt {
         username: impl AsRef<str>,
     ) -> Result<Option<User>, Problem>;
 
+    async fn update_password(&self, id: Uuid, pw_hash: PasswordHash) -> Result<bool, Problem>;
+
+    /// Marks `email` of user with `id` as verified, if it's still their current email.
+    async fn set_email_verified(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem>;
+
+    async fn update_profile(&self, id: Uuid, profile: &Profile) -> Result<bool, Problem>;
+
+    async fn update_role(&self, id: Uuid, role: Role) -> Result<bool, Problem>;
+
+    async fn update_status(&self, id: Uuid, status: &AccountStatus) -> Result<bool, Problem>;
+
+    async fn update_username(&self, id: Uuid, username: impl AsRef<str>) -> Result<bool, Problem>;
+
+    /// Changes user email and marks it as unverified.
+    async fn update_email(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem>;
+
     async fn delete_user(&self, id: Uuid) -> Result<Option<User>, Problem>;
 }
 
-impl CreateUserDbExt for Database {
+impl UserRepository for Database {
     async fn create_user<'a>(
         &self,
         create_user: UserSignupData<'_>,
         salt: impl AsRef<[u8]>,
         admin_names: impl AsRef<[String]>,
     ) -> Result<(UserRoleToken, User), Problem> {
-        let existing_email = self.find_user_by_email(&create_user.email).await?;
-
-        if let Some(existing) = existing_email {
-            let create_hash = PasswordHash::new(create_user.password.as_ref(), salt);
-            return if existing.pw_hash == create_hash {
-                let urt = UserRoleToken::new(&existing);
-                Ok((urt, existing))
-            } else {
-                Err(problem::bad_email(
-                    create_user.email.to_string(),
-                    "Email already registered.",
-                ))
-            };
-        }
-
-        if self
-            .find_user_by_username(&create_user.username)
-            .await?
-            .is_some()
-        {
-            return Err(problem::bad_username(
-                create_user.username.to_string(),
-                "Username already used.",
-            ));
-        }
-
         let mut user = create_user.to_user(salt);
 
         if admin_names.as_ref().contains(&user.username) {
@@ -222,13 +405,11 @@ impl CreateUserDbExt for Database {
 
         let urt = UserRoleToken::new(&user);
 
+        // Uniqueness is enforced by indexes so concurrent signups can't race each other
         self.collection(USER_COLLECTION_NAME)
-            .insert_one(
-                bson::to_document(&user).expect("User must be serializable to BSON"),
-                None,
-            )
+            .insert_one(bson::to_document(&user).map_err(BackendError::from)?, None)
             .await
-            .map_err(|e| Problem::from(e))?;
+            .map_err(|e| unique_problem(e, &user.email, &user.username))?;
 
         Ok((urt, user))
     }
@@ -242,7 +423,12 @@ impl CreateUserDbExt for Database {
 
     async fn find_user_by_email(&self, email: impl AsRef<str>) -> Result<Option<User>, Problem> {
         self.collection(USER_COLLECTION_NAME)
-            .find_one(filter::by_email(email.as_ref().to_string()), None)
+            .find_one(
+                filter::by_email(email.as_ref().to_string()),
+                FindOneOptions::builder()
+                    .collation(case_insensitive())
+                    .build(),
+            )
             .await
             .map_err(Problem::from)
     }
@@ -252,11 +438,112 @@ impl CreateUserDbExt for Database {
         username: impl AsRef<str>,
     ) -> Result<Option<User>, Problem> {
         self.collection(USER_COLLECTION_NAME)
-            .find_one(filter::by_username(username.as_ref().to_string()), None)
+            .find_one(
+                filter::by_username(normalize_username(username)),
+                FindOneOptions::builder()
+                    .collation(case_insensitive())
+                    .build(),
+            )
+            .await
+            .map_err(Problem::from)
+    }
+
+    async fn update_password(&self, id: Uuid, pw_hash: PasswordHash) -> Result<bool, Problem> {
+        let pw_hash = bson::to_bson(&pw_hash).map_err(BackendError::from)?;
+
+        self.collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                filter::by_id(id),
+                doc! { "$set": { "pw_hash": pw_hash } },
+                None,
+            )
+            .await
+            .map(|it| it.matched_count > 0)
+            .map_err(Problem::from)
+    }
+
+    async fn set_email_verified(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem> {
+        let mut filter = filter::by_id(id);
+        filter.insert("email", email.as_ref());
+
+        self.collection::<User>(USER_COLLECTION_NAME)
+            .update_one(filter, doc! { "$set": { "email_verified": true } }, None)
+            .await
+            .map(|it| it.matched_count > 0)
+            .map_err(Problem::from)
+    }
+
+    async fn update_profile(&self, id: Uuid, profile: &Profile) -> Result<bool, Problem> {
+        let profile = bson::to_bson(profile).map_err(BackendError::from)?;
+
+        self.collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                filter::by_id(id),
+                doc! { "$set": { "profile": profile } },
+                None,
+            )
             .await
+            .map(|it| it.matched_count > 0)
             .map_err(Problem::from)
     }
 
+    async fn update_role(&self, id: Uuid, role: Role) -> Result<bool, Problem> {
+        let role = bson::to_bson(&role).map_err(BackendError::from)?;
+
+        self.collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                filter::by_id(id),
+                doc! { "$set": { "user_role": role } },
+                None,
+            )
+            .await
+            .map(|it| it.matched_count > 0)
+            .map_err(Problem::from)
+    }
+
+    async fn update_status(&self, id: Uuid, status: &AccountStatus) -> Result<bool, Problem> {
+        let status = bson::to_bson(status).map_err(BackendError::from)?;
+
+        self.collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                filter::by_id(id),
+                doc! { "$set": { "status": status } },
+                None,
+            )
+            .await
+            .map(|it| it.matched_count > 0)
+            .map_err(Problem::from)
+    }
+
+    async fn update_username(&self, id: Uuid, username: impl AsRef<str>) -> Result<bool, Problem> {
+        let username = normalize_username(username);
+
+        self.collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                filter::by_id(id),
+                doc! { "$set": {
+                    "username": username.as_str(),
+                    "username_skeleton": username_skeleton(&username),
+                } },
+                None,
+            )
+            .await
+            .map(|it| it.matched_count > 0)
+            .map_err(|e| unique_problem(e, "", &username))
+    }
+
+    async fn update_email(&self, id: Uuid, email: impl AsRef<str>) -> Result<bool, Problem> {
+        self.collection::<User>(USER_COLLECTION_NAME)
+            .update_one(
+                filter::by_id(id),
+                doc! { "$set": { "email": email.as_ref(), "email_verified": false } },
+                None,
+            )
+            .await
+            .map(|it| it.matched_count > 0)
+            .map_err(|e| unique_problem(e, email.as_ref(), ""))
+    }
+
     async fn delete_user(&self, id: Uuid) -> Result<Option<User>, Problem> {
         self.collection(USER_COLLECTION_NAME)
             .find_one_and_delete(filter::by_id(id), None)
@@ -1,6 +1,8 @@
+use crate::resp::catalog::ProblemType;
 use crate::resp::problem::Problem;
 use bson::spec::BinarySubtype;
 use bson::{doc, Binary, Bson};
+use chrono::{DateTime, Utc};
 use crypto::bcrypt::bcrypt;
 use rocket::http::{ContentType, Status};
 use rocket::response::Responder;
@@ -11,10 +13,15 @@ use std::io::Cursor;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+pub mod audit;
 pub mod db;
+pub mod policy;
 pub mod profile;
+pub mod settings;
+pub mod token;
 
 use crate::role::Role;
+use profile::Profile;
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct PasswordHash([u8; 24]);
@@ -57,19 +64,66 @@ impl TryFrom<Bson> for PasswordHash {
     }
 }
 
-// TODO: Give a password reset form instead.
 fn password_lost_err() -> Problem {
-    Problem::new_untyped(Status::InternalServerError, "Unable to check password.")
+    Problem::typed(ProblemType::UserPasswordUnreadable)
+        .detail("Stored password is unreadable. Request a password reset to set a new one.")
+        .insert_str("reset", "/api/v1/user/password/reset")
+        .clone()
+}
+
+/// Whether a user is allowed to log in and use the API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "status")]
+pub enum AccountStatus {
+    Active,
+    /// Account is temporarily unusable. Suspensions without `until` last until restored.
+    Suspended {
+        #[serde(default)]
+        until: Option<DateTime<Utc>>,
+    },
+    Banned,
+}
+
+impl Default for AccountStatus {
+    fn default() -> Self {
+        AccountStatus::Active
+    }
+}
+
+impl AccountStatus {
+    pub fn is_active(&self) -> bool {
+        match self {
+            AccountStatus::Active => true,
+            AccountStatus::Suspended { until: Some(until) } => *until <= Utc::now(),
+            AccountStatus::Suspended { until: None } => false,
+            AccountStatus::Banned => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
-    #[serde(rename = "_id", with = "bson::serde_helpers::uuid_1_as_binary")]
+    /// Random user id, it never changes once the user is created.
+    ///
+    /// Accounts created before ids were random have UUIDv5 ids derived from their original email
+    /// and username. Those are kept as they are because ids are no longer derived from
+    /// credentials, so quizzes and classes referencing them stay valid.
+    #[serde(rename = "_id", with = "crate::data::id::binary")]
     pub id: Uuid,
     pub email: String,
     pub username: String,
+    /// [`policy::username_skeleton`], used to reject usernames that look like existing ones.
+    /// Missing for users created before it was introduced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_skeleton: Option<String>,
     pub pw_hash: PasswordHash,
     pub user_role: Role,
+    #[serde(default)]
+    pub email_verified: bool,
+    #[serde(default)]
+    pub profile: Profile,
+    #[serde(default)]
+    pub status: AccountStatus,
 }
 
 impl User {
@@ -79,22 +133,34 @@ impl User {
         password: impl AsRef<str>,
         salt: impl AsRef<[u8]>,
     ) -> User {
-        let pw_hash = PasswordHash::new(password, salt);
+        let username = policy::normalize_username(username);
+        let pw_hash = PasswordHash::new(policy::normalize_password(password), salt);
 
-        let id = Uuid::new_v5(
-            &Uuid::NAMESPACE_OID,
-            [email.as_ref(), username.as_ref()].join("").as_bytes(),
-        );
+        let id = Uuid::new_v4();
         tracing::info!("Creating a new user with UUID: {}", id.to_string());
 
         User {
             id,
             email: email.as_ref().to_string(),
-            username: username.as_ref().to_string(),
+            username_skeleton: Some(policy::username_skeleton(&username)),
+            username,
             pw_hash,
             user_role: Role::Normal,
+            email_verified: false,
+            profile: Profile::default(),
+            status: AccountStatus::Active,
         }
     }
+
+    pub fn password_matches(&self, password: impl AsRef<str>, salt: impl AsRef<[u8]>) -> bool {
+        let normalized = policy::normalize_password(&password);
+        if self.pw_hash == PasswordHash::new(&normalized, &salt) {
+            return true;
+        }
+
+        // Passwords set before normalization was introduced were hashed as they were submitted
+        normalized != password.as_ref() && self.pw_hash == PasswordHash::new(password, salt)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -107,6 +173,10 @@ pub struct UserResponse {
     pub username: String,
     /// User role
     pub user_role: Role,
+    /// Whether user confirmed their email address
+    pub email_verified: bool,
+    /// Whether user can log in
+    pub status: AccountStatus,
 }
 
 impl UserResponse {
@@ -122,13 +192,55 @@ impl From<User> for UserResponse {
             email: user.email,
             username: user.username,
             user_role: user.user_role,
+            email_verified: user.email_verified,
+            status: user.status,
+        }
+    }
+}
+
+/// Entry in a list of users.
+///
+/// Private fields are only filled in for admins.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserSummary {
+    pub id: Uuid,
+    pub username: String,
+    pub user_role: Role,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<AccountStatus>,
+}
+
+impl UserSummary {
+    /// Summary without email address and account status.
+    pub fn public(user: User) -> Self {
+        UserSummary {
+            id: user.id,
+            username: user.username,
+            user_role: user.user_role,
+            email: None,
+            status: None,
+        }
+    }
+
+    pub fn full(user: User) -> Self {
+        UserSummary {
+            id: user.id,
+            username: user.username,
+            user_role: user.user_role,
+            email: Some(user.email),
+            status: Some(user.status),
         }
     }
 }
 
 impl<'r> Responder<'r, 'static> for UserResponse {
     fn respond_to(self, _: &Request) -> response::Result<'static> {
-        let body: String = self.json().expect("unable to serialize UserResponse");
+        let body: String = self.json().map_err(|e| {
+            tracing::error!("unable to serialize UserResponse: {}", e);
+            Status::InternalServerError
+        })?;
 
         Response::build()
             .header(ContentType::JSON)
@@ -143,9 +255,7 @@ pub mod filter {
 
     #[inline]
     pub fn by_id(id: Uuid) -> Document {
-        doc! {
-            "_id": bson::Uuid::from(id)
-        }
+        crate::data::id::by_id(id)
     }
 
     #[inline]
@@ -162,3 +272,15 @@ pub mod filter {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_ids_are_independent_of_credentials() {
+        let a = User::new("ab@example.com", "c", "password", [0u8; 16]);
+        let b = User::new("ab@example.com", "c", "password", [0u8; 16]);
+        assert_ne!(a.id, b.id, "user ids must not be derived from credentials");
+    }
+}
@@ -0,0 +1,372 @@
+//! Rules usernames and passwords have to follow.
+//!
+//! Credentials are normalized before they're checked, stored or compared, and their lengths are
+//! counted in graphemes (what users perceive as characters) instead of bytes.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use unicode_normalization::UnicodeNormalization;
+use unicode_security::{skeleton, MixedScript};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::error::BackendError;
+use crate::resp::validation::{FieldError, FieldErrorCode, Validator};
+use crate::settings::Settings;
+
+/// Most common leaked passwords, always rejected.
+static COMMON_PASSWORDS: &[&str] = &[
+    "123456",
+    "123456789",
+    "12345678",
+    "1234567890",
+    "password",
+    "password1",
+    "password123",
+    "qwerty",
+    "qwerty123",
+    "qwertyuiop",
+    "1q2w3e4r",
+    "1qaz2wsx",
+    "abc123",
+    "abcd1234",
+    "111111",
+    "000000",
+    "1234567",
+    "123123",
+    "123321",
+    "654321",
+    "666666",
+    "987654321",
+    "iloveyou",
+    "princess",
+    "sunshine",
+    "football",
+    "baseball",
+    "welcome",
+    "welcome1",
+    "monkey",
+    "dragon",
+    "letmein",
+    "master",
+    "shadow",
+    "superman",
+    "trustno1",
+    "whatever",
+    "starwars",
+    "passw0rd",
+    "p@ssw0rd",
+    "admin",
+    "admin123",
+    "administrator",
+    "login",
+    "zaq12wsx",
+    "knowmark",
+];
+
+fn default_min_username_length() -> usize {
+    5
+}
+
+fn default_max_username_length() -> usize {
+    32
+}
+
+fn default_username_symbols() -> String {
+    String::from("_-.")
+}
+
+fn default_min_password_length() -> usize {
+    8
+}
+
+fn default_max_password_length() -> usize {
+    1024
+}
+
+fn default_min_password_score() -> u8 {
+    2
+}
+
+fn true_bool() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CredentialPolicy {
+    #[serde(default = "default_min_username_length")]
+    pub min_username_length: usize,
+    #[serde(default = "default_max_username_length")]
+    pub max_username_length: usize,
+    /// Allow letters and digits outside of ASCII in usernames.
+    #[serde(default = "true_bool")]
+    pub unicode_usernames: bool,
+    /// Symbols allowed in usernames besides letters and digits.
+    #[serde(default = "default_username_symbols")]
+    pub username_symbols: String,
+    /// Allow usernames that mix scripts, e.g. Latin and Cyrillic letters.
+    #[serde(default)]
+    pub mixed_script_usernames: bool,
+
+    #[serde(default = "default_min_password_length")]
+    pub min_password_length: usize,
+    #[serde(default = "default_max_password_length")]
+    pub max_password_length: usize,
+    /// Minimum [`password_score`] of new passwords, from 0 to 4.
+    #[serde(default = "default_min_password_score")]
+    pub min_password_score: u8,
+    /// File with additional common passwords that are rejected, one per line.
+    #[serde(default)]
+    pub common_passwords_file: Option<PathBuf>,
+    #[serde(skip)]
+    common_passwords: HashSet<String>,
+}
+
+impl Default for CredentialPolicy {
+    fn default() -> Self {
+        CredentialPolicy {
+            min_username_length: default_min_username_length(),
+            max_username_length: default_max_username_length(),
+            unicode_usernames: true,
+            username_symbols: default_username_symbols(),
+            mixed_script_usernames: false,
+            min_password_length: default_min_password_length(),
+            max_password_length: default_max_password_length(),
+            min_password_score: default_min_password_score(),
+            common_passwords_file: None,
+            common_passwords: HashSet::new(),
+        }
+    }
+}
+
+/// Usernames are stored and looked up in NFC form.
+pub fn normalize_username(username: impl AsRef<str>) -> String {
+    username.as_ref().trim().nfc().collect()
+}
+
+/// Passwords are hashed in NFKC form, so the same password typed on different keyboards matches.
+pub fn normalize_password(password: impl AsRef<str>) -> String {
+    password.as_ref().nfkc().collect()
+}
+
+/// Form of a username used to detect usernames that look alike, e.g. `paypal` and `раураl`.
+pub fn username_skeleton(username: impl AsRef<str>) -> String {
+    skeleton(&normalize_username(username).to_lowercase())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+fn grapheme_count(value: &str) -> usize {
+    value.graphemes(true).count()
+}
+
+/// Estimates password strength from 0 (trivial) to 4 (strong).
+///
+/// The estimate is based on length and used character classes. Repeated and sequential
+/// characters barely count, and passwords containing any of the `inputs`, e.g. the username,
+/// score 0.
+pub fn password_score(password: &str, inputs: &[&str]) -> u8 {
+    let lower = password.to_lowercase();
+    let contains_input = inputs
+        .iter()
+        .map(|it| it.trim().to_lowercase())
+        .any(|it| it.chars().count() >= 3 && lower.contains(&it));
+    if contains_input {
+        return 0;
+    }
+
+    let chars: Vec<char> = password.chars().collect();
+    let mut pool = 0u32;
+    if chars.iter().any(|c| c.is_ascii_lowercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_uppercase()) {
+        pool += 26;
+    }
+    if chars.iter().any(|c| c.is_ascii_digit()) {
+        pool += 10;
+    }
+    if chars.iter().any(|c| c.is_ascii_punctuation() || *c == ' ') {
+        pool += 33;
+    }
+    if chars.iter().any(|c| !c.is_ascii()) {
+        pool += 100;
+    }
+    if pool == 0 {
+        return 0;
+    }
+
+    let predictable = chars
+        .windows(2)
+        .filter(|it| (it[0] as i64 - it[1] as i64).abs() <= 1)
+        .count();
+    let distinct = chars.iter().collect::<HashSet<_>>().len();
+    let effective = (chars.len() as f64 - predictable as f64 / 2.0).min(distinct as f64 * 2.0);
+
+    match effective * (pool as f64).log2() {
+        bits if bits < 25.0 => 0,
+        bits if bits < 35.0 => 1,
+        bits if bits < 50.0 => 2,
+        bits if bits < 70.0 => 3,
+        _ => 4,
+    }
+}
+
+impl CredentialPolicy {
+    /// Policy of the validated request, or the default one outside of requests.
+    pub fn current<'a>(validator: &Validator<'a>) -> Cow<'a, CredentialPolicy> {
+        validator
+            .state::<Settings>()
+            .map(|it| Cow::Borrowed(&it.auth.credentials))
+            .unwrap_or_default()
+    }
+
+    /// Reads [`CredentialPolicy::common_passwords_file`], if there is one.
+    pub fn load_common_passwords(&mut self) -> Result<(), BackendError> {
+        let path = match &self.common_passwords_file {
+            Some(it) => it,
+            None => return Ok(()),
+        };
+
+        let content =
+            std::fs::read_to_string(path).map_err(|source| BackendError::CommonPasswords {
+                path: path.clone(),
+                source,
+            })?;
+        self.common_passwords = content
+            .lines()
+            .map(|it| normalize_password(it.trim()).to_lowercase())
+            .filter(|it| !it.is_empty())
+            .collect();
+
+        tracing::info!("Loaded {} common passwords", self.common_passwords.len());
+        Ok(())
+    }
+
+    pub fn is_common_password(&self, password: &str) -> bool {
+        let password = normalize_password(password).to_lowercase();
+        COMMON_PASSWORDS.contains(&password.as_str()) || self.common_passwords.contains(&password)
+    }
+
+    fn is_allowed_username_char(&self, c: char) -> bool {
+        let alphanumeric = match self.unicode_usernames {
+            true => c.is_alphanumeric(),
+            false => c.is_ascii_alphanumeric(),
+        };
+        alphanumeric || self.username_symbols.contains(c)
+    }
+
+    /// Checks a new username.
+    pub fn check_username(&self, validator: &mut Validator<'_>, field: &str, username: &str) {
+        let username = normalize_username(username);
+        let length = grapheme_count(&username);
+        validator.length(
+            field,
+            length,
+            self.min_username_length,
+            self.max_username_length,
+        );
+        if length == 0 {
+            return;
+        }
+
+        if !username.chars().all(|c| self.is_allowed_username_char(c)) {
+            validator.push(FieldError::new(field, FieldErrorCode::BadFormat));
+        } else if !self.mixed_script_usernames && !username.as_str().is_single_script() {
+            validator.push(FieldError::new(field, FieldErrorCode::Confusable));
+        }
+    }
+
+    /// Checks a new password. `inputs` are other values the user submitted, like their
+    /// username, which the password shouldn't contain.
+    pub fn check_password(
+        &self,
+        validator: &mut Validator<'_>,
+        field: &str,
+        password: &str,
+        inputs: &[&str],
+    ) {
+        let password = normalize_password(password);
+        let length = grapheme_count(&password);
+        validator.length(
+            field,
+            length,
+            self.min_password_length,
+            self.max_password_length,
+        );
+        if length < self.min_password_length || length > self.max_password_length {
+            return;
+        }
+
+        if self.is_common_password(&password) {
+            validator.push(FieldError::new(field, FieldErrorCode::TooCommon));
+        } else if password_score(&password, inputs) < self.min_password_score {
+            validator.push(
+                FieldError::new(field, FieldErrorCode::TooWeak)
+                    .limit(self.min_password_score as i64),
+            );
+        }
+    }
+
+    /// Checks username submitted to log in.
+    ///
+    /// Only the upper limit is checked, so accounts created under a less strict policy can
+    /// still log in.
+    pub fn check_login_username(&self, validator: &mut Validator<'_>, field: &str, username: &str) {
+        let length = grapheme_count(&normalize_username(username));
+        validator.length(field, length, 1, self.max_username_length);
+    }
+
+    /// Checks password submitted to log in, only the upper limit is checked.
+    pub fn check_login_password(&self, validator: &mut Validator<'_>, field: &str, password: &str) {
+        let length = grapheme_count(&normalize_password(password));
+        validator.length(field, length, 1, self.max_password_length);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_are_counted_in_graphemes() {
+        let policy = CredentialPolicy::default();
+        let mut validator = Validator::new();
+        // 5 graphemes, 15 bytes
+        policy.check_username(&mut validator, "username", "čćžšđ");
+        // 'c' with a combining cedilla is composed into a single 'ç'
+        policy.check_username(&mut validator, "username", "abc\u{327}d");
+        assert_eq!(
+            validator.errors(),
+            [FieldError::new("username", FieldErrorCode::TooShort).limit(5)]
+        );
+    }
+
+    #[test]
+    fn rejects_confusable_usernames_and_weak_passwords() {
+        let policy = CredentialPolicy::default();
+        let mut validator = Validator::new();
+        // Cyrillic 'а' mixed with Latin letters
+        policy.check_username(&mut validator, "username", "pаypal");
+        policy.check_username(&mut validator, "username", "has space");
+        policy.check_password(&mut validator, "password", "Password1", &[]);
+        policy.check_password(&mut validator, "password", "aaaaaaaaaa", &[]);
+        policy.check_password(&mut validator, "password", "someone_2023", &["someone"]);
+        policy.check_password(&mut validator, "password", "correct horse battery", &[]);
+
+        let codes: Vec<FieldErrorCode> = validator.errors().iter().map(|it| it.code).collect();
+        assert_eq!(
+            codes,
+            [
+                FieldErrorCode::Confusable,
+                FieldErrorCode::BadFormat,
+                FieldErrorCode::TooCommon,
+                FieldErrorCode::TooWeak,
+                FieldErrorCode::TooWeak,
+            ]
+        );
+        assert_eq!(username_skeleton("pаypal"), username_skeleton("PayPal"));
+    }
+}
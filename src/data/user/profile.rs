@@ -1,6 +1,14 @@
 use std::cmp::Ordering;
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+use utoipa::ToSchema;
+
+use crate::resp::validation::{FieldErrorCode, Validate, Validator};
+
+const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+const MAX_INTERESTS: usize = 32;
+pub const MAX_TAG_LENGTH: usize = 32;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, ToSchema)]
 pub struct BirthDate {
     pub month: u8,
     pub day: u8,
@@ -20,7 +28,21 @@ impl Ord for BirthDate {
     }
 }
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+impl BirthDate {
+    /// Checks whether the date exists in a (leap) year.
+    pub fn is_valid(&self) -> bool {
+        let days = match self.month {
+            1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+            4 | 6 | 9 | 11 => 30,
+            2 => 29,
+            _ => return false,
+        };
+        self.day >= 1 && self.day <= days
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(transparent)]
 pub struct Tag(String);
 
 impl<S: ToString> From<S> for Tag {
@@ -29,11 +51,84 @@ impl<S: ToString> From<S> for Tag {
     }
 }
 
-#[derive(Debug, Clone, Hash)]
+impl AsRef<str> for Tag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Tag {
+    /// Tag in the form it's stored and compared in: trimmed and lower case.
+    pub fn normalized(&self) -> Tag {
+        Tag(self.0.trim().to_lowercase())
+    }
+}
+
+/// Normalizes `tags` and removes duplicates, keeping the original order.
+pub fn normalize_tags(tags: &[Tag]) -> Vec<Tag> {
+    let mut result: Vec<Tag> = Vec::with_capacity(tags.len());
+    for tag in tags.iter().map(Tag::normalized) {
+        if !result.contains(&tag) {
+            result.push(tag);
+        }
+    }
+    result
+}
+
+#[derive(Debug, Clone, Default, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(transparent)]
 pub struct Interests(Vec<Tag>);
 
-#[derive(Debug, Clone, Hash)]
+impl Interests {
+    pub fn tags(&self) -> &[Tag] {
+        &self.0
+    }
+}
+
+impl From<Vec<Tag>> for Interests {
+    fn from(tags: Vec<Tag>) -> Self {
+        Interests(tags)
+    }
+}
+
+#[derive(Debug, Clone, Default, Hash, Serialize, Deserialize, ToSchema)]
 pub struct Profile {
-    pub birth_date: BirthDate,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub birth_date: Option<BirthDate>,
+    #[serde(default)]
     pub interests: Interests,
 }
+
+/// Checks that there are at most `max` `tags` and that each of them is valid.
+pub fn check_tags(validator: &mut Validator<'_>, field: &str, tags: &[Tag], max: usize) {
+    validator.count(field, tags.len(), max);
+    for (i, tag) in tags.iter().enumerate() {
+        validator.length(
+            format!("{}[{}]", field, i),
+            tag.0.trim().chars().count(),
+            1,
+            MAX_TAG_LENGTH,
+        );
+    }
+}
+
+impl Validate for Profile {
+    fn check(&self, validator: &mut Validator<'_>) {
+        if let Some(name) = &self.display_name {
+            validator.length(
+                "display_name",
+                name.chars().count(),
+                0,
+                MAX_DISPLAY_NAME_LENGTH,
+            );
+        }
+
+        if let Some(date) = &self.birth_date {
+            validator.check(date.is_valid(), "birth_date", FieldErrorCode::OutOfRange);
+        }
+
+        check_tags(validator, "interests", self.interests.tags(), MAX_INTERESTS);
+    }
+}
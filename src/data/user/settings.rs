@@ -0,0 +1,124 @@
+use bson::doc;
+use mongodb::options::ReplaceOptions;
+use mongodb::Database;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use super::db::USER_SETTINGS_COLLECTION_NAME;
+use super::filter;
+use crate::error::BackendError;
+use crate::resp::l10n::Language;
+use crate::resp::problem::Problem;
+use crate::resp::validation::{FieldErrorCode, Validate, Validator};
+
+fn default_locale() -> String {
+    "en".to_string()
+}
+
+fn true_bool() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct NotificationSettings {
+    /// Mail about quizzes opening soon
+    #[serde(default = "true_bool")]
+    pub quiz_reminders: bool,
+    /// Mail with graded quiz results
+    #[serde(default = "true_bool")]
+    pub quiz_results: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        NotificationSettings {
+            quiz_reminders: true,
+            quiz_results: true,
+        }
+    }
+}
+
+/// Per-user preferences.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UserSettings {
+    /// Preferred locale as a BCP 47 language tag
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+}
+
+impl Default for UserSettings {
+    fn default() -> Self {
+        UserSettings {
+            locale: default_locale(),
+            notifications: NotificationSettings::default(),
+        }
+    }
+}
+
+impl Validate for UserSettings {
+    fn check(&self, validator: &mut Validator<'_>) {
+        validator.check(
+            Language::from_tag(&self.locale).is_some(),
+            "locale",
+            FieldErrorCode::Invalid,
+        );
+    }
+}
+
+pub trait UserSettingsDbExt {
+    /// Returns stored settings for user, or defaults if none were saved.
+    async fn get_user_settings(&self, user: Uuid) -> Result<UserSettings, Problem>;
+
+    async fn save_user_settings(&self, user: Uuid, settings: &UserSettings) -> Result<(), Problem>;
+}
+
+impl UserSettingsDbExt for Database {
+    async fn get_user_settings(&self, user: Uuid) -> Result<UserSettings, Problem> {
+        Ok(self
+            .collection::<UserSettings>(USER_SETTINGS_COLLECTION_NAME)
+            .find_one(filter::by_id(user), None)
+            .await
+            .map_err(Problem::from)?
+            .unwrap_or_default())
+    }
+
+    async fn save_user_settings(&self, user: Uuid, settings: &UserSettings) -> Result<(), Problem> {
+        let mut document = bson::to_document(settings).map_err(BackendError::from)?;
+        document.extend(filter::by_id(user));
+
+        self.collection(USER_SETTINGS_COLLECTION_NAME)
+            .replace_one(
+                filter::by_id(user),
+                document,
+                ReplaceOptions::builder().upsert(true).build(),
+            )
+            .await
+            .map_err(Problem::from)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_supported_locales_are_accepted() {
+        for locale in ["en", "hr-HR", "en_US"] {
+            let settings = UserSettings {
+                locale: locale.to_string(),
+                ..Default::default()
+            };
+            assert!(settings.validate().is_ok(), "{} was rejected", locale);
+        }
+
+        let settings = UserSettings {
+            locale: "de".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use bson::doc;
+use chrono::{DateTime, Duration, Utc};
+use mongodb::Database;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::data::id;
+use crate::resp::catalog::ProblemType;
+use crate::resp::problem::Problem;
+
+pub static USER_TOKEN_COLLECTION_NAME: &str = "user.tokens";
+
+/// How long a password reset token stays valid.
+pub fn password_reset_ttl() -> Duration {
+    Duration::hours(1)
+}
+
+/// How long an email verification token stays valid.
+pub fn email_verification_ttl() -> Duration {
+    Duration::days(2)
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub enum TokenPurpose {
+    PasswordReset,
+    EmailVerification,
+}
+
+/// Single-use token sent to a user out of band.
+///
+/// Only a hash of the token is stored, the token itself is only known to the recipient.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserToken {
+    #[serde(rename = "_id")]
+    pub hash: String,
+    #[serde(with = "crate::data::id::binary")]
+    pub user: Uuid,
+    pub purpose: TokenPurpose,
+    /// Email address the token was sent to
+    #[serde(default)]
+    pub email: Option<String>,
+    /// MongoDB removes tokens once they expire
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    pub expires: DateTime<Utc>,
+}
+
+fn to_hex(bytes: impl AsRef<[u8]>) -> String {
+    bytes
+        .as_ref()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+pub fn hash_token(token: impl AsRef<str>) -> String {
+    to_hex(Sha256::digest(token.as_ref().as_bytes()))
+}
+
+/// Random plain text token value.
+pub fn generate_token() -> String {
+    to_hex(rand::random::<[u8; 32]>())
+}
+
+/// Stored single-use tokens.
+pub trait UserTokenDbExt {
+    /// Stores a new token for `user` and returns its plain text value.
+    async fn create_user_token(
+        &self,
+        user: Uuid,
+        purpose: TokenPurpose,
+        email: Option<String>,
+        ttl: Duration,
+    ) -> Result<String, Problem>;
+
+    /// Removes a matching, unexpired token and returns it.
+    async fn consume_user_token(
+        &self,
+        token: impl AsRef<str>,
+        purpose: TokenPurpose,
+    ) -> Result<Option<UserToken>, Problem>;
+
+    /// Removes all tokens of `user` with `purpose` and returns how many were removed.
+    async fn delete_user_tokens(&self, user: Uuid, purpose: TokenPurpose) -> Result<u64, Problem>;
+}
+
+impl UserTokenDbExt for Database {
+    async fn create_user_token(
+        &self,
+        user: Uuid,
+        purpose: TokenPurpose,
+        email: Option<String>,
+        ttl: Duration,
+    ) -> Result<String, Problem> {
+        let token = generate_token();
+
+        let entry = UserToken {
+            hash: hash_token(&token),
+            user,
+            purpose,
+            email,
+            expires: Utc::now() + ttl,
+        };
+
+        self.collection::<UserToken>(USER_TOKEN_COLLECTION_NAME)
+            .insert_one(entry, None)
+            .await
+            .map_err(Problem::from)?;
+
+        Ok(token)
+    }
+
+    async fn consume_user_token(
+        &self,
+        token: impl AsRef<str>,
+        purpose: TokenPurpose,
+    ) -> Result<Option<UserToken>, Problem> {
+        let purpose =
+            bson::to_bson(&purpose).map_err(|_| Problem::typed(ProblemType::ServerBson))?;
+
+        self.collection::<UserToken>(USER_TOKEN_COLLECTION_NAME)
+            .find_one_and_delete(
+                doc! {
+                    "_id": hash_token(token),
+                    "purpose": purpose,
+                    "expires": { "$gt": bson::DateTime::now() },
+                },
+                None,
+            )
+            .await
+            .map_err(Problem::from)
+    }
+
+    async fn delete_user_tokens(&self, user: Uuid, purpose: TokenPurpose) -> Result<u64, Problem> {
+        let purpose =
+            bson::to_bson(&purpose).map_err(|_| Problem::typed(ProblemType::ServerBson))?;
+        let mut filter = id::eq("user", user);
+        filter.insert("purpose", purpose);
+
+        self.collection::<UserToken>(USER_TOKEN_COLLECTION_NAME)
+            .delete_many(filter, None)
+            .await
+            .map(|it| it.deleted_count)
+            .map_err(Problem::from)
+    }
+}
@@ -1,5 +1,8 @@
 use thiserror::Error;
 
+use crate::resp::catalog::ProblemType;
+use crate::resp::problem::Problem;
+
 #[derive(Debug, Error)]
 pub enum BackendError {
     // External errors
@@ -8,7 +11,48 @@ pub enum BackendError {
     #[error(transparent)]
     Bson(#[from] bson::de::Error),
     #[error(transparent)]
+    BsonSerialization(#[from] bson::ser::Error),
+    #[error(transparent)]
     Json(#[from] serde_json::Error),
     #[error(transparent)]
     Jwt(#[from] jsonwebtoken::errors::Error),
+    #[error(transparent)]
+    Mail(#[from] crate::mail::MailError),
+
+    // Internal errors
+    #[error("{0} isn't a valid role level")]
+    InvalidRole(u8),
+    #[error("{0} isn't a known role")]
+    UnknownRole(String),
+    #[error("invalid settings: {0}")]
+    InvalidSettings(String),
+    #[error("settings are insecure for production: {}", .0.join("; "))]
+    InsecureSettings(Vec<String>),
+    #[error("database needs migrations {0:?}, apply them with `knowmark-admin migrate`")]
+    PendingMigrations(Vec<u32>),
+    #[error("unable to read common passwords from {path:?}: {source}")]
+    CommonPasswords {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+}
+
+impl From<BackendError> for Problem {
+    fn from(e: BackendError) -> Self {
+        match e {
+            BackendError::Database(e) => Problem::from(e),
+            BackendError::Bson(e) => Problem::from(e),
+            BackendError::BsonSerialization(e) => Problem::from(e),
+            BackendError::Json(e) => Problem::from(e),
+            BackendError::Jwt(e) => Problem::from(e),
+            BackendError::Mail(e) => Problem::from(e),
+            BackendError::InvalidRole(_) | BackendError::UnknownRole(_) => {
+                Problem::typed(ProblemType::ServerInternal)
+            }
+            BackendError::InvalidSettings(_)
+            | BackendError::InsecureSettings(_)
+            | BackendError::PendingMigrations(_) => Problem::typed(ProblemType::ServerInternal),
+            BackendError::CommonPasswords { source, .. } => Problem::from(source),
+        }
+    }
 }
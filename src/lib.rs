@@ -9,20 +9,32 @@ extern crate rocket;
 extern crate serde;
 
 use error::BackendError;
-use mongodb::Client;
+use mongodb::options::ClientOptions;
+use mongodb::{Client, Database};
+use rocket::fairing::AdHoc;
+use rocket::figment::Figment;
 use rocket::http::Method;
-use rocket::{Config, Rocket};
-use rocket_cors::{AllowedHeaders, AllowedOrigins, Origins};
-use std::process::exit;
-use tracing::Level;
-use tracing_subscriber::FmtSubscriber;
+use rocket::{Build, Rocket};
+use rocket_cors::{AllowedHeaders, AllowedOrigins, Cors};
+use std::str::FromStr;
+use std::sync::Arc;
 
+use crate::data::migration;
+use crate::data::store::Store;
+use crate::mail::Mailer;
+use crate::middleware::metrics::Metrics;
+use crate::middleware::request_id::RequestIds;
+use crate::route::metrics::metrics_get;
 use crate::route::mount_api;
 use crate::security::Security;
-use crate::settings::{Settings, CONFIG_FILE_NAME};
+use crate::settings::{
+    redact_uri, CorsSettings, DatabaseSettings, MetricsSettings, Settings, CONFIG_FILE_NAME,
+};
 
 pub mod data;
 pub mod error;
+pub mod logging;
+pub mod mail;
 pub mod middleware;
 pub mod resp;
 pub mod role;
@@ -31,15 +43,35 @@ pub mod security;
 pub mod settings;
 pub mod util;
 
-pub async fn create(log_level: Option<Level>) -> Result<Rocket<rocket::Build>, BackendError> {
-    if let Some(l) = log_level {
-        let subscriber = FmtSubscriber::builder().with_max_level(l).finish();
+fn cors(settings: &CorsSettings) -> Result<Cors, BackendError> {
+    let allowed_origins = match settings.origins.iter().any(|it| it == "*") {
+        true => AllowedOrigins::all(),
+        false => AllowedOrigins::some_exact(&settings.origins),
+    };
 
-        if let Err(err) = tracing::subscriber::set_global_default(subscriber) {
-            eprintln!("Unable to set global logger: {}", err);
-        };
+    let allowed_methods = settings
+        .methods
+        .iter()
+        .map(|it| {
+            Method::from_str(&it.to_uppercase())
+                .map(From::from)
+                .map_err(|_| BackendError::InvalidSettings(format!("unknown CORS method {}", it)))
+        })
+        .collect::<Result<_, _>>()?;
+
+    rocket_cors::CorsOptions {
+        allowed_origins,
+        allowed_methods,
+        allowed_headers: AllowedHeaders::All,
+        allow_credentials: settings.allow_credentials,
+        ..Default::default()
     }
+    .to_cors()
+    .map_err(|e| BackendError::InvalidSettings(e.to_string()))
+}
 
+/// Reads `.env` file and extracts [`Settings`] from the configuration file and environment.
+pub fn load_settings() -> Result<Settings, BackendError> {
     tracing::info!("Reading .env file...");
     if dotenv::dotenv().is_err() {
         tracing::warn!("Unable to load .env file.");
@@ -48,54 +80,225 @@ pub async fn create(log_level: Option<Level>) -> Result<Rocket<rocket::Build>, B
     // override Rocket settings file name
     std::env::set_var("ROCKET_CONFIG", CONFIG_FILE_NAME);
 
-    let settings = Config::figment().extract::<Settings>().unwrap_or_else(|_| {
-        tracing::warn!("Unable to extract Settings from Config figment");
-        Settings::default()
-    });
+    Settings::extract(&Settings::figment())
+}
 
-    tracing::info!("Initializing security salts and keys...");
-    let security = Security::load();
+/// Connects to the configured MongoDB database and checks it's reachable.
+///
+/// Command timings are recorded into `metrics` if they're provided.
+pub async fn connect(
+    settings: &DatabaseSettings,
+    metrics: Option<&Metrics>,
+) -> Result<Database, BackendError> {
+    tracing::info!("Connecting to MongoDB: {}", redact_uri(&settings.uri));
+    let mut options = ClientOptions::parse(settings.uri.as_str()).await?;
+    if let Some(metrics) = metrics {
+        options.command_event_handler = Some(Arc::new(metrics.clone()));
+    }
+    let client = Client::with_options(options)?;
+
+    tracing::info!("Using MongoDB database: {}", settings.name);
+    let db = client.database(settings.name.as_str());
+    db.list_collection_names(None).await?;
+
+    Ok(db)
+}
 
-    tracing::info!("Connecting to MongoDB: {}", settings.mongodb_uri);
-    let db_client = Client::with_uri_str(settings.mongodb_uri.as_str())
-        .await
-        .expect("Unable to init MongoDB client! Is URI valid?");
+/// Prints resolved settings with secrets redacted and reports problems with them.
+///
+/// Returns the process exit code, which isn't 0 if the server wouldn't start with the settings.
+pub fn check_config() -> i32 {
+    let mut settings = match load_settings() {
+        Ok(it) => it,
+        Err(err) => {
+            eprintln!("{}", err);
+            return 1;
+        }
+    };
 
-    tracing::info!("Using MongoDB database: {}", settings.mongodb_db);
-    let db = db_client.database(settings.mongodb_db.as_str());
+    match serde_json::to_string_pretty(&settings.redacted()) {
+        Ok(it) => println!("{}", it),
+        Err(err) => {
+            eprintln!("Unable to print settings: {}", err);
+            return 1;
+        }
+    }
 
-    if db.list_collections(None, None).await.is_err() {
-        tracing::error!("Unable to connect to MongoDB.");
-        exit(1)
+    if let Err(err) = settings.auth.credentials.load_common_passwords() {
+        eprintln!("{}", err);
+        return 1;
     }
 
-    tracing::info!("Initializing Rocket...");
-    let mut r = rocket::build().manage(settings).manage(db).manage(security);
+    if let Err(err) = logging::env_filter(&settings.log) {
+        eprintln!("{}", err);
+        return 1;
+    }
 
-    tracing::info!("Setting up CORS...");
-    let allowed_origins = rocket_cors::AllOrSome::some_exact(&[
-        "http://localhost:5173/",
-        "https://localhost:5173/",
-        "http://localhost:8000/",
-        "https://localhost:8000/",
-    ]);
+    let insecure = settings.insecure_settings();
+    match settings.check_profile() {
+        Ok(()) => {
+            for it in insecure {
+                eprintln!("warning: insecure setting: {}", it);
+            }
+            0
+        }
+        Err(err) => {
+            eprintln!("{}", err);
+            1
+        }
+    }
+}
 
-    // You can also deserialize this
-    let cors = rocket_cors::CorsOptions {
-        allowed_origins,
-        allowed_methods: vec![Method::Get, Method::Put, Method::Post, Method::Delete]
-            .into_iter()
-            .map(From::from)
-            .collect(),
-        allowed_headers: AllowedHeaders::All,
-        allow_credentials: true,
-        ..Default::default()
+/// Builds a backend [`Rocket`] from explicitly provided settings, keys and data store.
+///
+/// Building doesn't read `.env`, configuration or key files, doesn't connect to a database and
+/// doesn't install a global logger, so any number of backends can be built in one process, e.g.
+/// one per test:
+///
+/// ```no_run
+/// # use knowmark_backend::{data::store::Store, security::Security, settings::Settings};
+/// # use knowmark_backend::BackendBuilder;
+/// # fn example(security: Security) -> Result<(), knowmark_backend::error::BackendError> {
+/// let rocket = BackendBuilder::new(Settings::default(), security, Store::memory()).build()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Settings are used as they are, common passwords have to be loaded beforehand.
+pub struct BackendBuilder {
+    settings: Settings,
+    security: Security,
+    store: Store,
+    mailer: Option<Mailer>,
+    metrics: Option<Metrics>,
+    figment: Option<Figment>,
+}
+
+impl BackendBuilder {
+    pub fn new(settings: Settings, security: Security, store: impl Into<Store>) -> BackendBuilder {
+        BackendBuilder {
+            settings,
+            security,
+            store: store.into(),
+            mailer: None,
+            metrics: None,
+            figment: None,
+        }
     }
-    .to_cors()
-    .expect("Unable to configure CORS.");
 
-    r = r.attach(cors);
-    r = mount_api(r);
+    /// Sends mail with `mailer` instead of one created from mail settings.
+    pub fn mailer(mut self, mailer: Mailer) -> BackendBuilder {
+        self.mailer = Some(mailer);
+        self
+    }
+
+    /// Records metrics into `metrics` instead of a new registry.
+    pub fn metrics(mut self, metrics: Metrics) -> BackendBuilder {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Rocket configuration, [`rocket::Config::default`] if not set. Upload limits from settings
+    /// are merged into it.
+    pub fn figment(mut self, figment: Figment) -> BackendBuilder {
+        self.figment = Some(figment);
+        self
+    }
+
+    pub fn build(self) -> Result<Rocket<Build>, BackendError> {
+        let settings = self.settings;
+        settings.check_profile()?;
+
+        let mailer = match self.mailer {
+            Some(it) => it,
+            None => {
+                tracing::info!("Using {:?} mail backend", settings.mail.backend);
+                Mailer::new(&settings.mail)?
+            }
+        };
+
+        tracing::info!("Setting up CORS...");
+        let cors = cors(&settings.cors)?;
+
+        let metrics = self.metrics.unwrap_or_default();
+        let metrics_settings = settings.metrics.clone();
+
+        tracing::info!("Initializing Rocket...");
+        let figment = self
+            .figment
+            .unwrap_or_else(|| Figment::from(rocket::Config::default()))
+            .merge(("limits.form", settings.uploads.max_form_size))
+            .merge(("limits.json", settings.uploads.max_json_size))
+            .merge(("limits.file", settings.uploads.max_file_size));
+        let mut r = rocket::custom(figment)
+            .manage(settings)
+            .manage(self.store)
+            .manage(self.security)
+            .manage(mailer)
+            .manage(metrics.clone());
+
+        r = r.attach(RequestIds);
+        r = r.attach(cors);
+        r = r.attach(metrics.clone());
+        r = match metrics_settings {
+            MetricsSettings { enabled: false, .. } => r,
+            MetricsSettings { port: None, .. } => r.mount("/", routes![metrics_get]),
+            MetricsSettings {
+                port: Some(port), ..
+            } => r.attach(metrics_server(port, metrics)),
+        };
+        r = mount_api(r);
+
+        Ok(r)
+    }
+}
+
+/// Serves metrics on a separate `port` once the server starts.
+fn metrics_server(port: u16, metrics: Metrics) -> AdHoc {
+    AdHoc::on_liftoff("Metrics server", move |rocket| {
+        let figment = rocket.figment().clone().merge(("port", port));
+        Box::pin(async move {
+            tracing::info!("Serving metrics on port {}", port);
+            let server = rocket::custom(figment)
+                .manage(metrics)
+                .mount("/", routes![metrics_get]);
+            tokio::spawn(async move {
+                if let Err(err) = server.launch().await {
+                    tracing::error!("Error launching metrics server: {}", err);
+                }
+            });
+        })
+    })
+}
+
+/// Creates the backend from settings in the environment, installing the global logger they
+/// configure, loading keys from the security directory and connecting to MongoDB.
+pub async fn create() -> Result<Rocket<Build>, BackendError> {
+    let mut settings = load_settings()?;
+    logging::init(&settings.log)?;
+    settings.auth.credentials.load_common_passwords()?;
+    settings.check_profile()?;
+    tracing::info!("Using {:?} profile", settings.profile);
+
+    tracing::info!("Initializing security salts and keys...");
+    let security = Security::load_from(&settings.security.dir);
+
+    let metrics = Metrics::new();
+    let db = connect(&settings.database, Some(&metrics)).await?;
+
+    if settings.database.run_migrations {
+        tracing::info!("Applying database migrations...");
+        migration::migrate(&db, false).await?;
+    } else {
+        let pending = migration::pending_migrations(&db).await?;
+        if !pending.is_empty() {
+            let versions = pending.iter().map(|it| it.version).collect();
+            return Err(BackendError::PendingMigrations(versions));
+        }
+    }
 
-    Ok(r)
+    BackendBuilder::new(settings, security, db)
+        .metrics(metrics)
+        .figment(Settings::figment())
+        .build()
 }
@@ -0,0 +1,156 @@
+//! Log output and redaction of logged values.
+//!
+//! Handlers are traced with `#[tracing::instrument]`, which logs their arguments. Secrets and
+//! personal information must not end up in logs, so:
+//! - passwords, tokens and keys are left out of `Debug` output of types holding them,
+//! - email addresses are logged with [`redact_email`], keeping only their domain,
+//! - cookie jars, quiz answers and profiles are skipped by handlers that receive them.
+
+use tracing_subscriber::EnvFilter;
+
+use crate::error::BackendError;
+use crate::settings::{LogFormat, LogSettings};
+
+/// Parses filter directives of `settings`.
+pub fn env_filter(settings: &LogSettings) -> Result<EnvFilter, BackendError> {
+    EnvFilter::try_new(&settings.filter)
+        .map_err(|e| BackendError::InvalidSettings(format!("log.filter: {}", e)))
+}
+
+/// Installs the global logger configured by `settings`.
+///
+/// Only the first logger installed in a process is used, later calls just report the failure.
+pub fn init(settings: &LogSettings) -> Result<(), BackendError> {
+    let builder = tracing_subscriber::fmt().with_env_filter(env_filter(settings)?);
+    let result = match settings.format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init(),
+    };
+
+    if let Err(err) = result {
+        eprintln!("Unable to set global logger: {}", err);
+    }
+    Ok(())
+}
+
+/// Hides the local part of an email address, e.g. `***@example.com`.
+///
+/// Values that aren't email addresses are returned unchanged.
+pub fn redact_email(value: &str) -> String {
+    match value.rfind('@') {
+        Some(at) => format!("***{}", &value[at..]),
+        None => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::user::db::{EmailVerificationData, UserLoginData};
+
+    #[test]
+    fn secrets_are_left_out_of_debug_output() {
+        assert_eq!(redact_email("jane.doe@example.com"), "***@example.com");
+        assert_eq!(redact_email("jane_doe"), "jane_doe");
+
+        let login = UserLoginData {
+            username: "jane.doe@example.com".to_string(),
+            password: "hunter2".to_string(),
+        };
+        let printed = format!("{:?}", login);
+        assert!(!printed.contains("jane.doe") && !printed.contains("hunter2"));
+
+        let verification = EmailVerificationData {
+            token: "secret-token".to_string(),
+        };
+        assert!(!format!("{:?}", verification).contains("secret-token"));
+
+        let settings = crate::settings::DatabaseSettings {
+            uri: "mongodb://knowmark:hunter2@db:27017".to_string(),
+            ..Default::default()
+        };
+        assert!(!format!("{:?}", settings).contains("hunter2"));
+    }
+
+    /// Log output written by the test's logger.
+    #[cfg(feature = "generate-security")]
+    #[derive(Clone, Default)]
+    struct CapturedLogs(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    #[cfg(feature = "generate-security")]
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "generate-security")]
+    #[rocket::async_test]
+    async fn login_is_logged_without_secrets() {
+        use rocket::http::{ContentType, Status};
+        use rocket::local::asynchronous::Client;
+        use std::borrow::Cow;
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        use crate::data::store::Store;
+        use crate::data::user::db::{UserRepository, UserSignupData};
+        use crate::resp::jwt::AUTH_COOKIE_NAME;
+        use crate::security::Security;
+        use crate::settings::Settings;
+        use crate::BackendBuilder;
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::TRACE)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _logger = tracing::subscriber::set_default(subscriber);
+
+        let backend =
+            BackendBuilder::new(Settings::default(), Security::ephemeral(), Store::memory())
+                .build()
+                .expect("unable to build test backend");
+        let client = Client::tracked(backend).await.expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let user = UserSignupData {
+            email: Cow::Borrowed("logged.login@example.com"),
+            username: Cow::Borrowed("logged_login"),
+            password: Cow::Borrowed("L0gg3d#login"),
+        };
+        db.create_user(user, &security.salt, &[])
+            .await
+            .expect("unable to create test user");
+
+        let response = client
+            .post("/api/v1/login")
+            .header(ContentType::Form)
+            .body("username=logged_login&password=L0gg3d%23login")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let token = response
+            .cookies()
+            .get(AUTH_COOKIE_NAME)
+            .map(|it| it.value().to_string())
+            .expect("no jwt_auth cookie present");
+
+        let printed = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(printed.contains("login_submit"), "login wasn't logged");
+        assert!(!printed.contains("logged.login"));
+        assert!(!printed.contains("L0gg3d"));
+        assert!(!printed.contains(&token));
+    }
+}
@@ -0,0 +1,234 @@
+use std::path::PathBuf;
+
+use chrono::Utc;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::resp::catalog::ProblemType;
+use crate::resp::problem::Problem;
+use crate::settings::{MailBackend, MailSettings};
+
+#[cfg(feature = "smtp")]
+pub mod smtp;
+pub mod template;
+
+use template::MailTemplate;
+
+#[derive(Debug, Clone)]
+pub struct Mail {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Mail {
+    /// Formats the mail as a plain RFC 5322 message.
+    pub fn to_message(&self) -> String {
+        format!(
+            "Date: {}\r\nFrom: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}",
+            Utc::now().to_rfc2822(),
+            self.from,
+            self.to,
+            self.subject,
+            self.body
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("mail backend '{0}' isn't configured")]
+    NotConfigured(&'static str),
+    #[cfg(feature = "smtp")]
+    #[error(transparent)]
+    Address(#[from] lettre::address::AddressError),
+    #[cfg(feature = "smtp")]
+    #[error(transparent)]
+    Message(#[from] lettre::error::Error),
+    #[cfg(feature = "smtp")]
+    #[error(transparent)]
+    Smtp(#[from] lettre::transport::smtp::Error),
+}
+
+impl From<MailError> for Problem {
+    fn from(_: MailError) -> Self {
+        Problem::typed(ProblemType::ServerMail)
+    }
+}
+
+/// Outbound mail transport.
+#[rocket::async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError>;
+}
+
+/// Writes outgoing mail to the log instead of delivering it.
+pub struct LogMailer;
+
+#[rocket::async_trait]
+impl MailSender for LogMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        tracing::info!("Mail to <{}> ({}):\n{}", mail.to, mail.subject, mail.body);
+        Ok(())
+    }
+}
+
+/// Stores outgoing mail as `.eml` files in a directory.
+pub struct FileMailer {
+    pub dir: PathBuf,
+}
+
+#[rocket::async_trait]
+impl MailSender for FileMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+
+        let name = format!(
+            "{}-{}.eml",
+            Utc::now().format("%Y%m%dT%H%M%S%.3f"),
+            Uuid::new_v4()
+        );
+        let path = self.dir.join(name);
+        tokio::fs::write(&path, mail.to_message()).await?;
+
+        tracing::debug!("Stored mail to <{}> in '{}'", mail.to, path.display());
+        Ok(())
+    }
+}
+
+/// Delivers outgoing mail into a [maildir](https://cr.yp.to/proto/maildir.html).
+///
+/// Mail clients can open the directory directly, which makes it handy for development and tests.
+pub struct MaildirMailer {
+    pub dir: PathBuf,
+}
+
+#[rocket::async_trait]
+impl MailSender for MaildirMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        for sub in ["tmp", "new", "cur"] {
+            tokio::fs::create_dir_all(self.dir.join(sub)).await?;
+        }
+
+        let name = format!(
+            "{}.{}.knowmark",
+            Utc::now().timestamp_millis(),
+            Uuid::new_v4().simple()
+        );
+        let tmp = self.dir.join("tmp").join(&name);
+        let new = self.dir.join("new").join(&name);
+
+        // Mail is moved into 'new' only after it's fully written
+        tokio::fs::write(&tmp, mail.to_message()).await?;
+        tokio::fs::rename(&tmp, &new).await?;
+
+        tracing::debug!("Delivered mail to <{}> into '{}'", mail.to, new.display());
+        Ok(())
+    }
+}
+
+/// Managed state used by routes to send mail through the configured backend.
+pub struct Mailer {
+    pub from: String,
+    pub public_url: String,
+    sender: Box<dyn MailSender>,
+}
+
+impl Mailer {
+    pub fn new(settings: &MailSettings) -> Result<Mailer, MailError> {
+        let sender: Box<dyn MailSender> = match settings.backend {
+            MailBackend::Log => Box::new(LogMailer),
+            MailBackend::File => Box::new(FileMailer {
+                dir: settings.dir.clone(),
+            }),
+            MailBackend::Maildir => Box::new(MaildirMailer {
+                dir: settings.dir.clone(),
+            }),
+            #[cfg(feature = "smtp")]
+            MailBackend::Smtp => match &settings.smtp {
+                Some(smtp) => Box::new(smtp::SmtpMailer::new(smtp)?),
+                None => return Err(MailError::NotConfigured("smtp")),
+            },
+        };
+
+        Ok(Mailer::with_sender(settings, sender))
+    }
+
+    pub fn with_sender(settings: &MailSettings, sender: Box<dyn MailSender>) -> Mailer {
+        Mailer {
+            from: settings.from.clone(),
+            public_url: settings.public_url.trim_end_matches('/').to_string(),
+            sender,
+        }
+    }
+
+    /// Creates an absolute client URL for `path`.
+    pub fn link(&self, path: impl AsRef<str>) -> String {
+        format!("{}{}", self.public_url, path.as_ref())
+    }
+
+    pub async fn send(
+        &self,
+        to: impl ToString,
+        subject: impl ToString,
+        body: impl ToString,
+    ) -> Result<(), MailError> {
+        let mail = Mail {
+            from: self.from.clone(),
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body: body.to_string(),
+        };
+
+        self.sender.send(&mail).await
+    }
+
+    pub async fn send_template(
+        &self,
+        to: impl ToString,
+        template: MailTemplate<'_>,
+    ) -> Result<(), MailError> {
+        self.send(to, template.subject(), template.body()).await
+    }
+}
+
+impl std::fmt::Debug for Mailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mailer:{}", self.from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[rocket::async_test]
+    async fn maildir_mailer_delivers_into_new() {
+        let dir = std::env::temp_dir().join(format!("knowmark-maildir-{}", Uuid::new_v4()));
+        let settings = MailSettings {
+            backend: MailBackend::Maildir,
+            dir: dir.clone(),
+            ..Default::default()
+        };
+        let mailer = Mailer::new(&settings).expect("unable to create maildir mailer");
+
+        mailer
+            .send("user@example.com", "Subject", "Body")
+            .await
+            .expect("unable to send mail");
+
+        let delivered: Vec<_> = std::fs::read_dir(dir.join("new"))
+            .expect("maildir 'new' wasn't created")
+            .collect();
+        assert_eq!(delivered.len(), 1, "expected a single delivered mail");
+
+        let content = std::fs::read_to_string(delivered[0].as_ref().unwrap().path()).unwrap();
+        assert!(content.contains("To: user@example.com"));
+        assert!(content.ends_with("Body"));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,45 @@
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+use super::{Mail, MailError, MailSender};
+use crate::settings::SmtpSettings;
+
+/// Sends outgoing mail through an SMTP relay.
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpMailer {
+    pub fn new(settings: &SmtpSettings) -> Result<SmtpMailer, MailError> {
+        let mut builder = if settings.implicit_tls {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(&settings.host)?
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&settings.host)?
+        }
+        .port(settings.port);
+
+        if let (Some(username), Some(password)) = (&settings.username, &settings.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpMailer {
+            transport: builder.build(),
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl MailSender for SmtpMailer {
+    async fn send(&self, mail: &Mail) -> Result<(), MailError> {
+        let message = Message::builder()
+            .from(mail.from.parse()?)
+            .to(mail.to.parse()?)
+            .subject(&mail.subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(mail.body.clone())?;
+
+        self.transport.send(message).await?;
+        Ok(())
+    }
+}
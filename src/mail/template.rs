@@ -0,0 +1,65 @@
+use chrono::Duration;
+
+/// Mail sent by the backend.
+#[derive(Debug, Clone)]
+pub enum MailTemplate<'a> {
+    PasswordReset {
+        username: &'a str,
+        link: &'a str,
+        expires_in: Duration,
+    },
+    EmailVerification {
+        username: &'a str,
+        link: &'a str,
+        expires_in: Duration,
+    },
+}
+
+fn expiry(duration: &Duration) -> String {
+    if duration.num_hours() >= 48 {
+        format!("{} days", duration.num_days())
+    } else if duration.num_minutes() >= 120 {
+        format!("{} hours", duration.num_hours())
+    } else {
+        format!("{} minutes", duration.num_minutes())
+    }
+}
+
+impl MailTemplate<'_> {
+    pub fn subject(&self) -> String {
+        match self {
+            MailTemplate::PasswordReset { .. } => "Knowmark password reset".to_string(),
+            MailTemplate::EmailVerification { .. } => "Verify your Knowmark email".to_string(),
+        }
+    }
+
+    pub fn body(&self) -> String {
+        match self {
+            MailTemplate::PasswordReset {
+                username,
+                link,
+                expires_in,
+            } => format!(
+                "Hello {},\n\n\
+                A password reset was requested for your Knowmark account.\n\
+                Use the following link to set a new password:\n\n{}\n\n\
+                The link expires in {}. If you didn't request a reset, ignore this mail.\n",
+                username,
+                link,
+                expiry(expires_in)
+            ),
+            MailTemplate::EmailVerification {
+                username,
+                link,
+                expires_in,
+            } => format!(
+                "Hello {},\n\n\
+                Please confirm this is your email address by opening the following link:\n\n{}\n\n\
+                The link expires in {}. If you didn't create a Knowmark account, ignore this mail.\n",
+                username,
+                link,
+                expiry(expires_in)
+            ),
+        }
+    }
+}
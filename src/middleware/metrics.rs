@@ -0,0 +1,214 @@
+//! Prometheus metrics.
+//!
+//! [`Metrics`] holds its own registry, so every backend built in a process reports separately. It
+//! is managed by Rocket, attached as a fairing recording HTTP requests and, when given to
+//! [`crate::connect`], records MongoDB command timings.
+
+use std::time::Instant;
+
+use mongodb::event::command::{CommandEventHandler, CommandFailedEvent, CommandSucceededEvent};
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry,
+    TextEncoder,
+};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::{Data, Request, Response};
+
+/// Label of requests that didn't match any route, so unknown paths don't create new series.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    db_duration: HistogramVec,
+    logins: IntCounterVec,
+    submissions_in_progress: IntGauge,
+    grading_duration: Histogram,
+}
+
+/// Time request handling started at.
+struct RequestStart(Instant);
+
+/// Counts a quiz answer submission as in progress until dropped.
+pub struct SubmissionInProgress(IntGauge);
+
+impl Drop for SubmissionInProgress {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Metrics {
+        let http_requests = IntCounterVec::new(
+            Opts::new("knowmark_http_requests_total", "Handled HTTP requests"),
+            &["route", "method", "status"],
+        )
+        .expect("invalid metric");
+        let http_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "knowmark_http_request_duration_seconds",
+                "Time spent handling HTTP requests",
+            ),
+            &["route", "method", "status"],
+        )
+        .expect("invalid metric");
+        let db_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "knowmark_db_command_duration_seconds",
+                "Time spent executing MongoDB commands",
+            ),
+            &["command", "outcome"],
+        )
+        .expect("invalid metric");
+        let logins = IntCounterVec::new(
+            Opts::new("knowmark_logins_total", "Login attempts"),
+            &["outcome"],
+        )
+        .expect("invalid metric");
+        let submissions_in_progress = IntGauge::new(
+            "knowmark_quiz_submissions_in_progress",
+            "Quiz answer submissions being graded and recorded",
+        )
+        .expect("invalid metric");
+        let grading_duration = Histogram::with_opts(HistogramOpts::new(
+            "knowmark_quiz_grading_duration_seconds",
+            "Time spent grading quiz answers",
+        ))
+        .expect("invalid metric");
+
+        let registry = Registry::new();
+        registry
+            .register(Box::new(http_requests.clone()))
+            .and_then(|_| registry.register(Box::new(http_duration.clone())))
+            .and_then(|_| registry.register(Box::new(db_duration.clone())))
+            .and_then(|_| registry.register(Box::new(logins.clone())))
+            .and_then(|_| registry.register(Box::new(submissions_in_progress.clone())))
+            .and_then(|_| registry.register(Box::new(grading_duration.clone())))
+            .expect("metric names must be unique");
+
+        Metrics {
+            registry,
+            http_requests,
+            http_duration,
+            db_duration,
+            logins,
+            submissions_in_progress,
+            grading_duration,
+        }
+    }
+
+    /// Counts a login attempt.
+    pub fn record_login(&self, success: bool) {
+        let outcome = match success {
+            true => "success",
+            false => "failure",
+        };
+        self.logins.with_label_values(&[outcome]).inc();
+    }
+
+    /// Marks a quiz answer submission as in progress until the returned value is dropped.
+    pub fn submission_started(&self) -> SubmissionInProgress {
+        self.submissions_in_progress.inc();
+        SubmissionInProgress(self.submissions_in_progress.clone())
+    }
+
+    /// Runs `grade` and records how long it took.
+    pub fn time_grading<T>(&self, grade: impl FnOnce() -> T) -> T {
+        let timer = self.grading_duration.start_timer();
+        let result = grade();
+        timer.observe_duration();
+        result
+    }
+
+    /// Current values in Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Unable to encode metrics: {}", err);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Metrics")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for Metrics {
+    fn info(&self) -> Info {
+        Info {
+            name: "Prometheus metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let start = request.local_cache(|| RequestStart(Instant::now()));
+        let route = request
+            .route()
+            .map(|it| it.uri.to_string())
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+        let method = request.method().as_str();
+        let status = response.status().code.to_string();
+
+        let labels = [route.as_str(), method, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_duration
+            .with_label_values(&labels)
+            .observe(start.0.elapsed().as_secs_f64());
+    }
+}
+
+impl CommandEventHandler for Metrics {
+    fn handle_command_succeeded_event(&self, event: CommandSucceededEvent) {
+        self.db_duration
+            .with_label_values(&[event.command_name.as_str(), "success"])
+            .observe(event.duration.as_secs_f64());
+    }
+
+    fn handle_command_failed_event(&self, event: CommandFailedEvent) {
+        self.db_duration
+            .with_label_values(&[event.command_name.as_str(), "failure"])
+            .observe(event.duration.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_recorded_values() {
+        let metrics = Metrics::new();
+        metrics.record_login(true);
+        metrics.record_login(false);
+        metrics.record_login(false);
+        {
+            let _submission = metrics.submission_started();
+            assert_eq!(metrics.submissions_in_progress.get(), 1);
+        }
+        assert_eq!(metrics.submissions_in_progress.get(), 0);
+        metrics.time_grading(|| ());
+
+        let text = metrics.render();
+        assert!(text.contains("knowmark_logins_total{outcome=\"success\"} 1"));
+        assert!(text.contains("knowmark_logins_total{outcome=\"failure\"} 2"));
+        assert!(text.contains("knowmark_quiz_grading_duration_seconds_count 1"));
+    }
+}
@@ -1 +1,3 @@
+pub mod metrics;
 pub mod paging;
+pub mod request_id;
@@ -1,21 +1,35 @@
-use std::convert::TryFrom;
+use std::fmt::Display;
+use std::io::Cursor as IoCursor;
 use std::marker::PhantomData;
 
-use bson::{from_document, Document};
-use mongodb::{Cursor, Database};
+use bson::{doc, from_document, Bson, Document};
+use mongodb::options::FindOptions;
 use rocket::form::FromForm;
-use rocket::http::Status;
+use rocket::http::hyper::header::LINK;
+use rocket::http::{ContentType, RawStr, Status};
 use rocket::request::{FromRequest, Outcome};
-use rocket::Request;
+use rocket::response::{self, Responder};
+use rocket::{Request, Response};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use utoipa::openapi::{
+    ArrayBuilder, KnownFormat, ObjectBuilder, Ref, RefOr, Schema, SchemaFormat, SchemaType,
+};
+use uuid::Uuid;
 
+use crate::data::id;
+use crate::data::store::Store;
 use crate::resp::problem::Problem;
 
 mod sealed {
+    use super::SortOrder;
+
     pub struct HasContext<'k> {
         pub collection: &'k str,
         pub key: &'k str,
+        pub filter: bson::Document,
+        pub projection: Option<bson::Document>,
+        pub order: SortOrder,
     }
     pub struct NoContext;
 
@@ -25,15 +39,199 @@ mod sealed {
 }
 use sealed::*;
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, FromFormField, Serialize, Deserialize)]
+pub enum SortOrder {
+    Asc,
+    Desc,
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        SortOrder::Asc
+    }
+}
+
+impl SortOrder {
+    /// Value used for this order in MongoDB sort documents.
+    pub fn direction(self) -> i32 {
+        match self {
+            SortOrder::Asc => 1,
+            SortOrder::Desc => -1,
+        }
+    }
+
+    pub fn reverse(self) -> SortOrder {
+        match self {
+            SortOrder::Asc => SortOrder::Desc,
+            SortOrder::Desc => SortOrder::Asc,
+        }
+    }
+
+    /// Comparison operator selecting documents that come after a key in this order.
+    fn after_operator(self) -> &'static str {
+        match self {
+            SortOrder::Asc => "$gt",
+            SortOrder::Desc => "$lt",
+        }
+    }
+}
+
+/// Value of a unique document field that pages are keyed by.
+pub trait PageKey: Sized + Display {
+    /// Value as it's stored in documents.
+    fn to_bson(&self) -> Bson;
+    fn from_bson(value: &Bson) -> Option<Self>;
+}
+
+impl PageKey for String {
+    fn to_bson(&self) -> Bson {
+        Bson::String(self.clone())
+    }
+
+    fn from_bson(value: &Bson) -> Option<Self> {
+        value.as_str().map(str::to_string)
+    }
+}
+
+impl PageKey for Uuid {
+    fn to_bson(&self) -> Bson {
+        id::to_bson(*self)
+    }
+
+    fn from_bson(value: &Bson) -> Option<Self> {
+        id::from_bson(value)
+    }
+}
+
+/// A page of entries with cursors for neighbouring pages.
+///
+/// Routes returning pages document them with a `ToSchema` impl for their page type, built with
+/// [`Page::schema_of`].
+#[derive(Debug, Clone, Serialize)]
+pub struct Page<R> {
+    pub items: Vec<R>,
+    /// Cursor for the next page, passed as `from` query parameter
+    pub next: Option<String>,
+    /// Cursor for the previous page, passed as `before` query parameter
+    pub prev: Option<String>,
+    /// Number of entries on all pages, if requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+}
+
+impl<R> Page<R> {
+    pub fn map<O>(self, f: impl FnMut(R) -> O) -> Page<O> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next: self.next,
+            prev: self.prev,
+            total: self.total,
+        }
+    }
+
+    pub fn with_total(mut self, total: u64) -> Self {
+        self.total = Some(total);
+        self
+    }
+
+    /// OpenAPI schema of pages with items described by the `item` schema.
+    pub fn schema_of(item: &str) -> RefOr<Schema> {
+        let cursor = |description: &str| {
+            ObjectBuilder::new()
+                .schema_type(SchemaType::String)
+                .nullable(true)
+                .description(Some(description))
+        };
+
+        ObjectBuilder::new()
+            .property(
+                "items",
+                ArrayBuilder::new().items(Ref::from_schema_name(item)),
+            )
+            .required("items")
+            .property(
+                "next",
+                cursor("Cursor for the next page, passed as `from` query parameter"),
+            )
+            .property(
+                "prev",
+                cursor("Cursor for the previous page, passed as `before` query parameter"),
+            )
+            .property(
+                "total",
+                ObjectBuilder::new()
+                    .schema_type(SchemaType::Integer)
+                    .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64)))
+                    .nullable(true)
+                    .description(Some("Number of entries on all pages, if requested")),
+            )
+            .into()
+    }
+}
+
+impl<R: Serialize> Page<R> {
+    /// Creates a `Link` header value for neighbouring pages of the requested URI.
+    fn link_header(&self, request: &Request<'_>) -> Option<String> {
+        let uri = request.uri();
+        let query: Vec<&str> = uri
+            .query()
+            .map(|q| {
+                q.raw_segments()
+                    .map(RawStr::as_str)
+                    .filter(|it| !it.starts_with("from=") && !it.starts_with("before="))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let link = |param: &str, cursor: &str, rel: &str| {
+            let mut segments = query.clone();
+            let cursor = format!("{}={}", param, RawStr::new(cursor).percent_encode());
+            segments.push(&cursor);
+            format!("<{}?{}>; rel=\"{}\"", uri.path(), segments.join("&"), rel)
+        };
+
+        let mut links = vec![];
+        if let Some(next) = &self.next {
+            links.push(link("from", next, "next"));
+        }
+        if let Some(prev) = &self.prev {
+            links.push(link("before", prev, "prev"));
+        }
+
+        match links.is_empty() {
+            true => None,
+            false => Some(links.join(", ")),
+        }
+    }
+}
+
+impl<'r, R: Serialize> Responder<'r, 'static> for Page<R> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = serde_json::to_string(&self).map_err(|_| Status::InternalServerError)?;
+
+        let mut response = Response::build();
+        response
+            .header(ContentType::JSON)
+            .sized_body(body.len(), IoCursor::new(body));
+        if let Some(links) = self.link_header(request) {
+            response.raw_header(LINK.as_str(), links);
+        }
+        response.ok()
+    }
+}
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
-pub struct PageState<'r, T: FromForm<'r> + Serialize, Ctx: PagingContext = NoContext> {
+pub struct PageState<'r, T: FromForm<'r> + PageKey, Ctx: PagingContext = NoContext> {
     pub length: u8,
+    /// Page starts after this key
     pub from: Option<T>,
+    /// Page ends before this key
+    pub before: Option<T>,
     context: Ctx,
     _phantom: PhantomData<&'r ()>,
 }
 
-impl<'r, T: FromForm<'r> + Serialize, Ctx: PagingContext> PageState<'r, T, Ctx> {
+impl<'r, T: FromForm<'r> + PageKey, Ctx: PagingContext> PageState<'r, T, Ctx> {
     pub fn page_over<'k>(
         self,
         collection: &'k str,
@@ -42,86 +240,145 @@ impl<'r, T: FromForm<'r> + Serialize, Ctx: PagingContext> PageState<'r, T, Ctx>
         PageState {
             length: self.length,
             from: self.from,
-            context: HasContext { collection, key },
+            before: self.before,
+            context: HasContext {
+                collection,
+                key,
+                filter: Document::new(),
+                projection: None,
+                order: SortOrder::default(),
+            },
             _phantom: self._phantom,
         }
     }
 }
 
-impl<'r, 'k, T: FromForm<'r> + Serialize> PageState<'r, T, HasContext<'k>> {
-    pub async fn entries<R: DeserializeOwned>(
-        &'r self,
-        db: &'r Database,
-    ) -> Result<Vec<R>, Problem> {
-        let mut position: Cursor<R> = db
-            .collection(self.context.collection)
-            .find(
-                self.from.as_ref().map(|it| {
-                    let mut doc = Document::new();
-                    let val = bson::to_bson(it).expect("unable to serialize 'from' to bson");
-                    doc.insert(self.context.key, val);
-                    doc
-                }),
-                None,
-            )
+impl<'r, 'k, T: FromForm<'r> + PageKey> PageState<'r, T, HasContext<'k>> {
+    /// Only page over documents matching `filter`.
+    pub fn filter(mut self, filter: Document) -> Self {
+        self.context.filter = filter;
+        self
+    }
+
+    /// Only fetch fields included in `projection`. Page key is always included.
+    pub fn projection(mut self, mut projection: Document) -> Self {
+        projection.insert(self.context.key, 1);
+        self.context.projection = Some(projection);
+        self
+    }
+
+    pub fn order(mut self, order: SortOrder) -> Self {
+        self.context.order = order;
+        self
+    }
+
+    /// Number of documents matching the filter, on all pages.
+    pub async fn total(&self, db: &Store) -> Result<u64, Problem> {
+        db.count_documents(self.context.collection, self.context.filter.clone())
             .await
-            .map_err(|e| Problem::from(e))?;
-
-        let mut result = Vec::with_capacity(self.length as usize);
-        for _ in 0..self.length {
-            let raw = position.current();
-            match Document::try_from(raw) {
-                Ok(entry) => {
-                    let entry_identifier = entry.get(self.context.key).map(|it| it.to_string());
-
-                    match from_document(entry) {
-                        Ok(value) => result.push(value),
-                        Err(err) => {
-                            tracing::warn!(
-                                "Can't read document as {} in '{}' collection ({}: {:?}); {}",
-                                std::any::type_name::<T>(),
-                                self.context.collection,
-                                self.context.key,
-                                entry_identifier,
-                                err
-                            );
-                        }
-                    }
-                }
+    }
+
+    pub async fn entries<R: DeserializeOwned>(&self, db: &Store) -> Result<Page<R>, Problem> {
+        let key = self.context.key;
+        let backwards = self.from.is_none() && self.before.is_some();
+
+        // Backward pages are read in reverse order from the cursor and flipped afterwards
+        let order = match backwards {
+            true => self.context.order.reverse(),
+            false => self.context.order,
+        };
+        let cursor = match backwards {
+            true => self.before.as_ref(),
+            false => self.from.as_ref(),
+        };
+
+        let mut filter = self.context.filter.clone();
+        if let Some(cursor) = cursor {
+            let condition = doc! { order.after_operator(): cursor.to_bson() };
+            filter = match filter.is_empty() {
+                true => doc! { key: condition },
+                false => doc! { "$and": [filter, { key: condition }] },
+            };
+        }
+
+        let mut sort = Document::new();
+        sort.insert(key, order.direction());
+
+        // One extra entry shows whether there's more after this page
+        let options = FindOptions::builder()
+            .sort(sort)
+            .limit(self.length as i64 + 1)
+            .projection(self.context.projection.clone())
+            .build();
+
+        let mut documents = db
+            .find_documents(self.context.collection, filter, options)
+            .await?;
+
+        let has_more = documents.len() > self.length as usize;
+        documents.truncate(self.length as usize);
+        if backwards {
+            documents.reverse();
+        }
+
+        let first_key = documents.first().and_then(|it| it.get(key)).cloned();
+        let last_key = documents.last().and_then(|it| it.get(key)).cloned();
+        let cursor_string = |value: Option<Bson>| {
+            value
+                .as_ref()
+                .and_then(T::from_bson)
+                .map(|it| it.to_string())
+        };
+
+        let (next, prev) = match backwards {
+            true => (
+                cursor_string(last_key).or_else(|| self.before.as_ref().map(|it| it.to_string())),
+                has_more.then(|| cursor_string(first_key)).flatten(),
+            ),
+            false => (
+                has_more.then(|| cursor_string(last_key)).flatten(),
+                self.from
+                    .as_ref()
+                    .and_then(|from| cursor_string(first_key).or_else(|| Some(from.to_string()))),
+            ),
+        };
+
+        let mut items = Vec::with_capacity(documents.len());
+        for entry in documents {
+            let entry_identifier = entry.get(key).map(|it| it.to_string());
+            match from_document(entry) {
+                Ok(value) => items.push(value),
                 Err(err) => {
-                    let kv = raw
-                        .get(self.context.key)
-                        .ok()
-                        .flatten()
-                        .map(|it| it.to_raw_bson());
-                    tracing::error!(
-                        "Can't read document in '{}' collection ({}: {:?}); {}",
+                    tracing::warn!(
+                        "Can't read document as {} in '{}' collection ({}: {:?}); {}",
+                        std::any::type_name::<R>(),
                         self.context.collection,
-                        self.context.key,
-                        kv,
+                        key,
+                        entry_identifier,
                         err
                     );
                 }
             }
-
-            match position.advance().await {
-                Ok(true) => {}
-                Ok(false) => break,
-                Err(e) => return Err(Problem::from(e)),
-            }
         }
 
-        Ok(result)
+        Ok(Page {
+            items,
+            next,
+            prev,
+            total: None,
+        })
     }
 }
 
 const DEFAULT_LENGTH: u8 = 20;
+const MAX_LENGTH: u8 = 100;
 
-impl<'r, T: FromForm<'r> + Serialize> Default for PageState<'r, T, NoContext> {
+impl<'r, T: FromForm<'r> + PageKey> Default for PageState<'r, T, NoContext> {
     fn default() -> Self {
         PageState {
             length: DEFAULT_LENGTH,
             from: None,
+            before: None,
             context: NoContext,
             _phantom: PhantomData,
         }
@@ -129,13 +386,13 @@ impl<'r, T: FromForm<'r> + Serialize> Default for PageState<'r, T, NoContext> {
 }
 
 #[rocket::async_trait]
-impl<'r, T: FromForm<'r> + Serialize> FromRequest<'r> for PageState<'r, T, NoContext> {
+impl<'r, T: FromForm<'r> + PageKey> FromRequest<'r> for PageState<'r, T, NoContext> {
     type Error = rocket::form::Errors<'r>;
 
     async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         let length: u8 = match request.query_value("length") {
-            Some(Ok(it)) => it,
-            None => DEFAULT_LENGTH,
+            Some(Ok(0)) | None => DEFAULT_LENGTH,
+            Some(Ok(it)) => it.min(MAX_LENGTH),
             Some(Err(e)) => return Outcome::Failure((Status::BadRequest, e)),
         };
 
@@ -145,11 +402,133 @@ impl<'r, T: FromForm<'r> + Serialize> FromRequest<'r> for PageState<'r, T, NoCon
             Some(Err(e)) => return Outcome::Failure((Status::BadRequest, e)),
         };
 
+        let before: Option<T> = match request.query_value("before") {
+            Some(Ok(it)) => Some(it),
+            None => None,
+            Some(Err(e)) => return Outcome::Failure((Status::BadRequest, e)),
+        };
+
         Outcome::Success(PageState {
             length,
             from,
+            before,
             context: NoContext,
             _phantom: PhantomData,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Settings;
+    use mongodb::{Client, Database};
+
+    async fn test_collection(name: &str, keys: &[&str]) -> Database {
+        let settings = Settings::default();
+        let db = Client::with_uri_str(settings.database.uri.as_str())
+            .await
+            .expect("unable to init MongoDB client")
+            .database(settings.database.name.as_str());
+
+        let collection = db.collection::<Document>(name);
+        collection
+            .drop(None)
+            .await
+            .expect("unable to drop collection");
+        collection
+            .insert_many(keys.iter().map(|it| doc! { "key": *it }), None)
+            .await
+            .expect("unable to insert test documents");
+
+        db
+    }
+
+    fn state(length: u8, from: Option<&str>, before: Option<&str>) -> PageState<'static, String> {
+        PageState {
+            length,
+            from: from.map(str::to_string),
+            before: before.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn keys(page: &Page<Document>) -> Vec<&str> {
+        page.items
+            .iter()
+            .map(|it| it.get_str("key").unwrap())
+            .collect()
+    }
+
+    #[rocket::async_test]
+    async fn paging_forward_and_backward() {
+        let collection = "test.paging_forward_and_backward";
+        let db = test_collection(collection, &["e", "b", "d", "a", "c"]).await;
+        let store = Store::from(db.clone());
+
+        let first: Page<Document> = state(2, None, None)
+            .page_over(collection, "key")
+            .entries(&store)
+            .await
+            .unwrap();
+        assert_eq!(keys(&first), ["a", "b"]);
+        assert_eq!(first.next.as_deref(), Some("b"));
+        assert_eq!(first.prev, None);
+
+        let second: Page<Document> = state(2, first.next.as_deref(), None)
+            .page_over(collection, "key")
+            .entries(&store)
+            .await
+            .unwrap();
+        assert_eq!(keys(&second), ["c", "d"]);
+        assert_eq!(second.next.as_deref(), Some("d"));
+        assert_eq!(second.prev.as_deref(), Some("c"));
+
+        let last: Page<Document> = state(2, second.next.as_deref(), None)
+            .page_over(collection, "key")
+            .entries(&store)
+            .await
+            .unwrap();
+        assert_eq!(keys(&last), ["e"]);
+        assert_eq!(last.next, None);
+
+        let back: Page<Document> = state(2, None, second.prev.as_deref())
+            .page_over(collection, "key")
+            .entries(&store)
+            .await
+            .unwrap();
+        assert_eq!(keys(&back), ["a", "b"]);
+        assert_eq!(back.prev, None);
+        assert_eq!(back.next.as_deref(), Some("b"));
+
+        db.collection::<Document>(collection).drop(None).await.ok();
+    }
+
+    #[rocket::async_test]
+    async fn paging_descending_with_filter() {
+        let collection = "test.paging_descending_with_filter";
+        let db = test_collection(collection, &["a", "b", "c", "d", "e"]).await;
+        let store = Store::from(db.clone());
+
+        let page = state(2, None, None)
+            .page_over(collection, "key")
+            .filter(doc! { "key": { "$ne": "d" } })
+            .order(SortOrder::Desc);
+        assert_eq!(page.total(&store).await.unwrap(), 4);
+
+        let first: Page<Document> = page.entries(&store).await.unwrap();
+        assert_eq!(keys(&first), ["e", "c"]);
+
+        let second: Page<Document> = state(2, first.next.as_deref(), None)
+            .page_over(collection, "key")
+            .filter(doc! { "key": { "$ne": "d" } })
+            .order(SortOrder::Desc)
+            .entries(&store)
+            .await
+            .unwrap();
+        assert_eq!(keys(&second), ["b", "a"]);
+        assert_eq!(second.next, None);
+
+        db.collection::<Document>(collection).drop(None).await.ok();
+    }
+}
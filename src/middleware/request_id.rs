@@ -0,0 +1,137 @@
+//! Request ids.
+//!
+//! [`RequestIds`] fairing reads the `X-Request-Id` header of each request, or generates an id if
+//! the header is missing or invalid, and returns it in the response header. Routes wrapped with
+//! [`traced`] run in a span carrying the id, and problems use it as their instance URI.
+
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::route::{Handler, Outcome};
+use rocket::{Data, Request, Response, Route};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Longest accepted request id, longer ones are replaced.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Id of the current request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(String);
+
+/// Request id in request local cache, missing if [`RequestIds`] isn't attached.
+struct CachedRequestId(Option<RequestId>);
+
+impl RequestId {
+    /// Id provided by the client, if it's short and only uses URI safe characters.
+    fn from_header(value: &str) -> Option<RequestId> {
+        let valid = !value.is_empty()
+            && value.len() <= MAX_REQUEST_ID_LENGTH
+            && value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c));
+
+        match valid {
+            true => Some(RequestId(value.to_string())),
+            false => None,
+        }
+    }
+
+    fn generate() -> RequestId {
+        RequestId(Uuid::new_v4().to_string())
+    }
+
+    /// Id of `request`, if it was assigned one.
+    pub fn of<'r>(request: &'r Request<'_>) -> Option<&'r RequestId> {
+        request.local_cache(|| CachedRequestId(None)).0.as_ref()
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// URI identifying the request in problem responses.
+    pub fn instance_uri(&self) -> String {
+        format!("urn:knowmark:request:{}", self.0)
+    }
+}
+
+impl std::fmt::Display for RequestId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Assigns ids to requests.
+pub struct RequestIds;
+
+#[rocket::async_trait]
+impl Fairing for RequestIds {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request ids",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        let id = request
+            .headers()
+            .get_one(REQUEST_ID_HEADER)
+            .and_then(RequestId::from_header)
+            .unwrap_or_else(RequestId::generate);
+        request.local_cache(|| CachedRequestId(Some(id)));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        if let Some(id) = RequestId::of(request) {
+            response.set_header(Header::new(REQUEST_ID_HEADER, id.to_string()));
+        }
+    }
+}
+
+/// Route handler running the wrapped one in a span with the request id.
+#[derive(Clone)]
+struct Traced(Box<dyn Handler>);
+
+#[rocket::async_trait]
+impl Handler for Traced {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        let id = RequestId::of(request).map(RequestId::as_str);
+        let span = tracing::info_span!(
+            "request",
+            request_id = id.unwrap_or_default(),
+            method = %request.method(),
+            path = %request.uri().path(),
+        );
+        self.0.handle(request, data).instrument(span).await
+    }
+}
+
+/// Wraps handlers of `routes` so they run in a span with the request id.
+pub fn traced(routes: Vec<Route>) -> Vec<Route> {
+    routes
+        .into_iter()
+        .map(|mut route| {
+            route.handler = Box::new(Traced(route.handler));
+            route
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_safe_request_ids_are_accepted() {
+        assert_eq!(
+            RequestId::from_header("3f2c9a1e-lb.1:42").map(|it| it.to_string()),
+            Some("3f2c9a1e-lb.1:42".to_string())
+        );
+        assert_eq!(RequestId::from_header(""), None);
+        assert_eq!(RequestId::from_header("id\nforged log line"), None);
+        assert_eq!(RequestId::from_header(&"a".repeat(129)), None);
+    }
+}
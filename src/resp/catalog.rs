@@ -0,0 +1,129 @@
+use rocket::http::Status;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+macro_rules! problem_types {
+    ($($(#[$meta:meta])* $name:ident => ($uri:literal, $status:ident, $title:literal)),* $(,)?) => {
+        /// Catalog of problems returned by the API.
+        ///
+        /// Each type has a stable URI returned as problem `type`, clients should check it instead
+        /// of the (localized) title.
+        #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize, ToSchema)]
+        pub enum ProblemType {
+            $(
+                $(#[$meta])*
+                #[serde(rename = $uri)]
+                $name,
+            )*
+        }
+
+        impl ProblemType {
+            pub const ALL: &'static [ProblemType] = &[$(ProblemType::$name),*];
+
+            /// Stable URI identifying the problem type.
+            pub fn uri(self) -> &'static str {
+                match self {
+                    $(ProblemType::$name => $uri,)*
+                }
+            }
+
+            /// Status problems of this type are returned with.
+            pub fn status(self) -> Status {
+                match self {
+                    $(ProblemType::$name => Status::$status,)*
+                }
+            }
+
+            /// English title, translated when the response is localized.
+            pub fn title(self) -> &'static str {
+                match self {
+                    $(ProblemType::$name => $title,)*
+                }
+            }
+        }
+    };
+}
+
+problem_types! {
+    /// Part of the request couldn't be parsed
+    RequestParse => ("/problems/request/parse", BadRequest, "There was a problem parsing part of the request."),
+    /// Query or path parameter has an invalid value
+    RequestBadParameter => ("/problems/request/bad-parameter", BadRequest, "Invalid request parameter."),
+    /// Administrative change is missing a reason
+    RequestMissingReason => ("/problems/request/missing-reason", BadRequest, "Missing reason."),
+    /// Requested resource or route doesn't exist
+    RequestNotFound => ("/problems/request/not-found", NotFound, "Requested resource doesn't exist."),
+    /// Request is well formed, but its data is invalid
+    RequestUnprocessable => ("/problems/request/unprocessable", UnprocessableEntity, "Request data couldn't be processed."),
+    /// Submitted data has invalid fields, listed in `errors`
+    RequestInvalidFields => ("/problems/request/invalid-fields", BadRequest, "Submitted data has invalid fields."),
+    /// Request is forbidden for the current user
+    RequestForbidden => ("/problems/request/forbidden", Forbidden, "Request is forbidden."),
+
+    /// Request couldn't be authorized
+    AuthUnauthorized => ("/problems/auth/unauthorized", Unauthorized, "Unable to authorize user."),
+    /// Request has no authentication token
+    AuthMissingToken => ("/problems/auth/missing-token", Unauthorized, "Missing authentication token."),
+    /// Authentication token is malformed or has a bad signature
+    AuthInvalidToken => ("/problems/auth/invalid-token", Unauthorized, "Invalid authentication token."),
+    /// Authentication token expired, user has to log in again
+    AuthExpiredToken => ("/problems/auth/expired-token", Unauthorized, "Expired authentication token."),
+    /// User role doesn't allow the request
+    AuthInsufficientRole => ("/problems/auth/insufficient-role", Unauthorized, "Insufficient permissions."),
+    /// Resource belongs to another user
+    AuthNotOwner => ("/problems/auth/not-owner", Unauthorized, "Resource not owned by user."),
+    /// Provided password doesn't match the stored one
+    AuthBadPassword => ("/problems/auth/bad-password", Unauthorized, "Password doesn't match."),
+    /// Login credentials don't match any user
+    AuthBadLogin => ("/problems/auth/bad-login", Unauthorized, "Bad login credentials."),
+    /// Account is temporarily suspended
+    AuthAccountSuspended => ("/problems/auth/account-suspended", Forbidden, "Account suspended."),
+    /// Account is banned
+    AuthAccountBanned => ("/problems/auth/account-banned", Forbidden, "Account banned."),
+    /// Request requires a verified email address
+    AuthEmailNotVerified => ("/problems/auth/email-not-verified", Forbidden, "Email not verified."),
+
+    UserBadEmail => ("/problems/user/bad-email", BadRequest, "Bad email."),
+    UserBadUsername => ("/problems/user/bad-username", BadRequest, "Bad username."),
+    /// Password reset or verification token is invalid, expired or used
+    UserBadToken => ("/problems/user/bad-token", BadRequest, "Bad token."),
+    UserNotFound => ("/problems/user/not-found", NotFound, "User doesn't exist."),
+    /// Admins can't change their own role or status
+    UserSelfChange => ("/problems/user/self-change", BadRequest, "Admins can't change their own role or status."),
+    /// Stored password hash can't be read, password has to be reset
+    UserPasswordUnreadable => ("/problems/user/password-unreadable", InternalServerError, "Unable to check password."),
+
+    QuizNotFound => ("/problems/quiz/not-found", NotFound, "Quiz doesn't exist."),
+
+    /// Unexpected server error
+    ServerInternal => ("/problems/server/internal", InternalServerError, "Internal server error."),
+    /// Server JWT keys can't be used
+    ServerKeys => ("/problems/server/keys", InternalServerError, "Server authentication keys are invalid."),
+    ServerMail => ("/problems/server/mail", InternalServerError, "Unable to send mail."),
+    ServerDatabase => ("/problems/server/database", InternalServerError, "MongoDB failed while processing request."),
+    ServerDatabaseAccess => ("/problems/server/database-access", InternalServerError, "Server was unable to access MongoDB."),
+    ServerDatabaseRequest => ("/problems/server/database-request", InternalServerError, "MongoDB was unable to process bad server request."),
+    ServerBson => ("/problems/server/bson", InternalServerError, "An error occurred while processing BSON data."),
+    ServerJson => ("/problems/server/json", InternalServerError, "An error occurred while processing JSON data."),
+    ServerIo => ("/problems/server/io", InternalServerError, "Server IO error."),
+    /// Request needs MongoDB, but the server uses another data store
+    ServerUnsupported => ("/problems/server/unsupported", NotImplemented, "Request isn't supported by the server data store."),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProblemType;
+    use std::collections::HashSet;
+
+    #[test]
+    fn problem_type_uris_are_unique_and_serialized() {
+        let mut uris = HashSet::new();
+        for kind in ProblemType::ALL {
+            assert!(uris.insert(kind.uri()), "duplicate URI: {}", kind.uri());
+            assert_eq!(
+                serde_json::to_value(kind).unwrap(),
+                serde_json::Value::from(kind.uri())
+            );
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use rocket::http::Status;
+use rocket::outcome::Outcome;
+use rocket::Request;
+
+use crate::resp::catalog::ProblemType;
+use crate::resp::problem::Problem;
+
+/// Problem a request guard failed with.
+///
+/// Rocket doesn't pass guard errors to catchers, so guards store them in request local cache
+/// where [`default_catcher`] can find them.
+struct GuardProblem(Option<Problem>);
+
+/// Fails a request or data guard with `problem`, which is then returned by the catcher.
+pub fn guard_failure<S, F>(
+    req: &Request<'_>,
+    problem: Problem,
+) -> Outcome<S, (Status, Problem), F> {
+    req.local_cache(|| GuardProblem(Some(problem.clone())));
+    Outcome::Failure((problem.status, problem))
+}
+
+/// Returns RFC 7807 problems for errors that weren't handled by routes.
+#[catch(default)]
+pub fn default_catcher(status: Status, req: &Request<'_>) -> Problem {
+    if let Some(problem) = &req.local_cache(|| GuardProblem(None)).0 {
+        if problem.status == status {
+            return problem.clone();
+        }
+    }
+
+    let kind = match status {
+        Status::BadRequest => ProblemType::RequestParse,
+        Status::Unauthorized => ProblemType::AuthUnauthorized,
+        Status::Forbidden => ProblemType::RequestForbidden,
+        Status::NotFound => ProblemType::RequestNotFound,
+        Status::UnprocessableEntity => ProblemType::RequestUnprocessable,
+        Status::InternalServerError => ProblemType::ServerInternal,
+        // Status code describes the problem well enough
+        _ => {
+            return Problem::new_untyped(status, status.reason().unwrap_or("Unknown problem."));
+        }
+    };
+
+    Problem::typed(kind)
+}
@@ -1,16 +1,21 @@
 use chrono::{DateTime, Duration, Utc};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
-use rocket::http::{Cookie, CookieJar, Status};
+use rocket::http::{Cookie, CookieJar};
 use rocket::request::{self, FromRequest, Request};
 use rocket::time::OffsetDateTime;
 use serde::{Deserialize, Serialize};
 
 use super::util::date_time_as_unix_seconds;
+use crate::data::store::Store;
+use crate::data::user::db::{problem as user_problem, UserRepository};
 use crate::data::user::User;
+use crate::resp::catalog::ProblemType;
+use crate::resp::catcher::guard_failure;
 use crate::resp::problem::Problem;
 use crate::role::Role;
 use crate::security::Security;
-use rocket::outcome::Outcome::{Failure, Success};
+use crate::settings::CookieSettings;
+use rocket::outcome::Outcome::Success;
 use uuid::Uuid;
 
 pub static AUTH_COOKIE_NAME: &'static str = "jwt_auth";
@@ -41,30 +46,51 @@ impl UserRoleToken {
         private_key: impl AsRef<[u8]>,
     ) -> Result<String, jsonwebtoken::errors::Error> {
         let header = Header::new(Algorithm::PS256);
-        let key = EncodingKey::from_rsa_pem(private_key.as_ref())
-            .expect("user_auth private key isn't valid. Unable to encode JWT.");
+        let key = EncodingKey::from_rsa_pem(private_key.as_ref())?;
 
-        Ok(encode(&header, &self, &key)?)
+        encode(&header, &self, &key)
     }
 
     pub fn cookie(
         &self,
         private_key: impl AsRef<[u8]>,
+        settings: &CookieSettings,
     ) -> Result<Cookie<'static>, jsonwebtoken::errors::Error> {
-        Ok(
-            // FIXME: Undo LAX Same-Site and domain once client is served by backend
-            Cookie::build(AUTH_COOKIE_NAME, self.encode_jwt(private_key)?)
-                .expires(OffsetDateTime::from_unix_timestamp(self.exp.timestamp()).ok())
-                //.path("/")
-                .same_site(rocket::http::SameSite::None)
-                .secure(false)
-                .finish(),
-        )
+        let mut cookie = Cookie::build(AUTH_COOKIE_NAME, self.encode_jwt(private_key)?)
+            .expires(OffsetDateTime::from_unix_timestamp(self.exp.timestamp()).ok())
+            .path(settings.path.clone())
+            .same_site(settings.same_site.into())
+            .secure(settings.secure)
+            .http_only(true)
+            .finish();
+        if let Some(domain) = &settings.domain {
+            cookie.set_domain(domain.clone());
+        }
+
+        Ok(cookie)
+    }
+}
+
+/// Cookie that removes the auth cookie, it must have the same path and domain.
+pub fn removal_cookie(settings: &CookieSettings) -> Cookie<'static> {
+    let mut cookie = Cookie::build(AUTH_COOKIE_NAME, "")
+        .path(settings.path.clone())
+        .finish();
+    if let Some(domain) = &settings.domain {
+        cookie.set_domain(domain.clone());
     }
+    cookie
 }
 
 pub fn auth_problem(detail: impl ToString) -> Problem {
-    Problem::new_untyped(Status::Unauthorized, "Unable to authorize user.")
+    Problem::typed(ProblemType::AuthUnauthorized)
+        .detail(detail)
+        .clone()
+}
+
+/// Problem for users whose role doesn't allow a request.
+pub fn role_problem(detail: impl ToString) -> Problem {
+    Problem::typed(ProblemType::AuthInsufficientRole)
         .detail(detail)
         .clone()
 }
@@ -77,25 +103,35 @@ pub fn extract_claims(
     let token = match auth_cookie {
         Some(jwt) => jwt.value().to_owned(),
         None => {
-            return Err(auth_problem("No JWT auth cookie."));
+            return Err(Problem::typed(ProblemType::AuthMissingToken)
+                .detail("No JWT auth cookie.")
+                .clone());
         }
     };
     tracing::debug!("extracted jwt auth from cookie");
 
-    match decode::<UserRoleToken>(
-        &token,
-        &DecodingKey::from_rsa_pem(public_key.as_ref())
-            .expect("user_auth public key isn't valid. Unable to decode JWT."),
-        &Validation::new(Algorithm::PS256),
-    )
-    .map(|data| data.claims)
+    let key = DecodingKey::from_rsa_pem(public_key.as_ref()).map_err(|e| {
+        tracing::error!(
+            "user_auth public key isn't valid, unable to decode JWT: {}",
+            e
+        );
+        Problem::from(e)
+    })?;
+
+    match decode::<UserRoleToken>(&token, &key, &Validation::new(Algorithm::PS256))
+        .map(|data| data.claims)
     {
         Ok(it) => {
             tracing::debug!("decoded user roles token for user: {}", it.user);
 
             Ok(it)
         }
-        Err(_) => Err(auth_problem("JWT cookie was malformed.")),
+        Err(e) if matches!(e.kind(), jsonwebtoken::errors::ErrorKind::ExpiredSignature) => {
+            Err(Problem::from(e))
+        }
+        Err(_) => Err(Problem::typed(ProblemType::AuthInvalidToken)
+            .detail("JWT cookie was malformed.")
+            .clone()),
     }
 }
 
@@ -104,16 +140,39 @@ impl<'r> FromRequest<'r> for UserRoleToken {
     type Error = Problem;
 
     async fn from_request(req: &'r Request<'_>) -> request::Outcome<Self, Self::Error> {
-        let security: &Security = req.rocket().state().unwrap();
+        let security: &Security = match req.rocket().state() {
+            Some(it) => it,
+            None => {
+                tracing::error!("Security isn't managed, unable to check JWT");
+                return guard_failure(req, Problem::typed(ProblemType::ServerKeys));
+            }
+        };
 
         tracing::trace!("extracting user roles token from request cookies");
-        let claims: UserRoleToken = match extract_claims(req.cookies(), &security.jwt_keys.public) {
-            Ok(it) => it,
-            Err(e) => {
-                tracing::debug!("unable to extract claims from cookies");
-                return Failure((Status::Unauthorized, e));
+        let mut claims: UserRoleToken =
+            match extract_claims(req.cookies(), &security.jwt_keys.public) {
+                Ok(it) => it,
+                Err(e) => {
+                    tracing::debug!("unable to extract claims from cookies");
+                    return guard_failure(req, e);
+                }
+            };
+
+        // Tokens outlive changes to the account, so current account state is checked as well
+        if let Some(db) = req.rocket().state::<Store>() {
+            let user = match db.get_user(claims.user).await {
+                Ok(Some(it)) => it,
+                Ok(None) => return guard_failure(req, auth_problem("User doesn't exist anymore.")),
+                Err(e) => return guard_failure(req, e),
+            };
+
+            if !user.status.is_active() {
+                tracing::debug!("refused token of inactive user: {}", user.id);
+                return guard_failure(req, user_problem::account_inactive(&user.status));
             }
-        };
+
+            claims.role = user.user_role;
+        }
 
         return Success(claims);
     }
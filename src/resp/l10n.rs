@@ -0,0 +1,454 @@
+//! Localization of response messages.
+//!
+//! Messages are written in English in code and translated by looking them up in per-language
+//! tables. Messages with values use `{name}` placeholders which are substituted after
+//! translation, so translations can move them around.
+
+use std::borrow::Cow;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Language {
+    English,
+    Croatian,
+}
+
+impl Default for Language {
+    fn default() -> Self {
+        Language::English
+    }
+}
+
+impl Language {
+    pub const ALL: &'static [Language] = &[Language::English, Language::Croatian];
+
+    /// BCP 47 language tag.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Language::English => "en",
+            Language::Croatian => "hr",
+        }
+    }
+
+    /// Finds a supported language for a BCP 47 tag, ignoring region and script subtags.
+    pub fn from_tag(tag: &str) -> Option<Language> {
+        let primary = tag.split(|c| c == '-' || c == '_').next()?.trim();
+        Language::ALL
+            .iter()
+            .copied()
+            .find(|it| it.tag().eq_ignore_ascii_case(primary))
+    }
+
+    /// Picks the most preferred supported language from an `Accept-Language` header value.
+    pub fn negotiate(accept_language: Option<&str>) -> Language {
+        let header = match accept_language {
+            Some(it) => it,
+            None => return Language::default(),
+        };
+
+        let mut best: Option<(Language, f32)> = None;
+        for entry in header.split(',') {
+            let mut parts = entry.split(';');
+            let tag = parts.next().unwrap_or_default().trim();
+            let quality = parts
+                .find_map(|it| it.trim().strip_prefix("q="))
+                .and_then(|it| it.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            let language = match tag {
+                "*" => Language::default(),
+                _ => match Language::from_tag(tag) {
+                    Some(it) => it,
+                    None => continue,
+                },
+            };
+
+            // Earlier entries win ties
+            if quality > 0.0 && best.map(|(_, q)| quality > q).unwrap_or(true) {
+                best = Some((language, quality));
+            }
+        }
+
+        best.map(|(it, _)| it).unwrap_or_default()
+    }
+
+    fn translations(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => &[],
+            Language::Croatian => CROATIAN,
+        }
+    }
+
+    /// Translates English `message`, messages without translation are returned as they are.
+    pub fn translate(self, message: &str) -> &str {
+        self.translations()
+            .iter()
+            .find(|(english, _)| *english == message)
+            .map(|(_, translated)| *translated)
+            .unwrap_or(message)
+    }
+
+    /// Translates `template` and substitutes its `{name}` placeholders with `args`.
+    pub fn format<'a>(self, template: &'a str, args: &[(String, String)]) -> Cow<'a, str> {
+        let translated = self.translate(template);
+        if args.is_empty() {
+            return Cow::Borrowed(translated);
+        }
+
+        let mut result = translated.to_string();
+        for (name, value) in args {
+            result = result.replace(&format!("{{{}}}", name), value);
+        }
+        Cow::Owned(result)
+    }
+}
+
+static CROATIAN: &[(&str, &str)] = &[
+    // Problem titles
+    (
+        "There was a problem parsing part of the request.",
+        "Dio zahtjeva nije moguće pročitati.",
+    ),
+    (
+        "Invalid request parameter.",
+        "Neispravan parametar zahtjeva.",
+    ),
+    ("Missing reason.", "Nedostaje razlog."),
+    (
+        "Requested resource doesn't exist.",
+        "Traženi resurs ne postoji.",
+    ),
+    (
+        "Request data couldn't be processed.",
+        "Podatke zahtjeva nije moguće obraditi.",
+    ),
+    (
+        "Submitted data has invalid fields.",
+        "Poslani podaci sadrže neispravna polja.",
+    ),
+    ("Request is forbidden.", "Zahtjev je zabranjen."),
+    (
+        "Unable to authorize user.",
+        "Korisnika nije moguće autorizirati.",
+    ),
+    (
+        "Missing authentication token.",
+        "Nedostaje token za autentifikaciju.",
+    ),
+    (
+        "Invalid authentication token.",
+        "Neispravan token za autentifikaciju.",
+    ),
+    (
+        "Expired authentication token.",
+        "Token za autentifikaciju je istekao.",
+    ),
+    ("Insufficient permissions.", "Nedovoljne ovlasti."),
+    (
+        "Resource not owned by user.",
+        "Resurs nije u vlasništvu korisnika.",
+    ),
+    ("Password doesn't match.", "Lozinka se ne podudara."),
+    ("Bad login credentials.", "Neispravni podaci za prijavu."),
+    ("Account suspended.", "Račun je suspendiran."),
+    ("Account banned.", "Račun je zabranjen."),
+    ("Email not verified.", "Adresa e-pošte nije potvrđena."),
+    ("Bad email.", "Neispravna adresa e-pošte."),
+    ("Bad username.", "Neispravno korisničko ime."),
+    ("Bad token.", "Neispravan token."),
+    ("User doesn't exist.", "Korisnik ne postoji."),
+    (
+        "Admins can't change their own role or status.",
+        "Administratori ne mogu mijenjati vlastitu ulogu ili status.",
+    ),
+    (
+        "Unable to check password.",
+        "Lozinku nije moguće provjeriti.",
+    ),
+    ("Quiz doesn't exist.", "Kviz ne postoji."),
+    ("Internal server error.", "Interna pogreška poslužitelja."),
+    (
+        "Server authentication keys are invalid.",
+        "Ključevi za autentifikaciju na poslužitelju su neispravni.",
+    ),
+    ("Unable to send mail.", "Slanje e-pošte nije uspjelo."),
+    (
+        "MongoDB failed while processing request.",
+        "MongoDB nije uspio obraditi zahtjev.",
+    ),
+    (
+        "Server was unable to access MongoDB.",
+        "Poslužitelj ne može pristupiti MongoDB bazi.",
+    ),
+    (
+        "MongoDB was unable to process bad server request.",
+        "MongoDB nije mogao obraditi neispravan zahtjev poslužitelja.",
+    ),
+    (
+        "An error occurred while processing BSON data.",
+        "Došlo je do pogreške pri obradi BSON podataka.",
+    ),
+    (
+        "An error occurred while processing JSON data.",
+        "Došlo je do pogreške pri obradi JSON podataka.",
+    ),
+    ("Server IO error.", "Ulazno-izlazna pogreška poslužitelja."),
+    (
+        "Request isn't supported by the server data store.",
+        "Spremište podataka poslužitelja ne podržava zahtjev.",
+    ),
+    // Problem details
+    ("No JWT auth cookie.", "Nedostaje kolačić s JWT tokenom."),
+    (
+        "JWT cookie was malformed.",
+        "Kolačić s JWT tokenom je neispravan.",
+    ),
+    ("User doesn't exist anymore.", "Korisnik više ne postoji."),
+    ("Permission level too low.", "Razina ovlasti je preniska."),
+    (
+        "Quiz not owned by user.",
+        "Kviz nije u vlasništvu korisnika.",
+    ),
+    (
+        "Only admins can manage users.",
+        "Samo administratori mogu upravljati korisnicima.",
+    ),
+    (
+        "Only admins can delete other users.",
+        "Samo administratori mogu brisati druge korisnike.",
+    ),
+    (
+        "Only admins can create classes for other users.",
+        "Samo administratori mogu stvarati razrede za druge korisnike.",
+    ),
+    (
+        "Only members can list users.",
+        "Samo članovi mogu pregledavati popis korisnika.",
+    ),
+    (
+        "Only members can view other users.",
+        "Samo članovi mogu pregledavati druge korisnike.",
+    ),
+    (
+        "Current password doesn't match.",
+        "Trenutna lozinka se ne podudara.",
+    ),
+    (
+        "Bad email or password.",
+        "Neispravna adresa e-pošte ili lozinka.",
+    ),
+    (
+        "Bad username or password.",
+        "Neispravno korisničko ime ili lozinka.",
+    ),
+    (
+        "Verify your email address before participating in quizzes.",
+        "Potvrdite adresu e-pošte prije sudjelovanja u kvizovima.",
+    ),
+    (
+        "Email already registered.",
+        "Adresa e-pošte je već registrirana.",
+    ),
+    ("Username already used.", "Korisničko ime je već zauzeto."),
+    (
+        "Username looks too similar to an already used one.",
+        "Korisničko ime previše nalikuje već zauzetom.",
+    ),
+    (
+        "Token is invalid, expired or was already used.",
+        "Token je neispravan, istekao ili je već iskorišten.",
+    ),
+    (
+        "Stored password is unreadable. Request a password reset to set a new one.",
+        "Spremljenu lozinku nije moguće pročitati. Zatražite ponovno postavljanje lozinke.",
+    ),
+    (
+        "Missing search terms.",
+        "Nedostaju pojmovi za pretraživanje.",
+    ),
+    (
+        "'{parameter}' must be an RFC 3339 date, e.g. 2023-01-31T12:00:00Z",
+        "'{parameter}' mora biti datum u RFC 3339 obliku, npr. 2023-01-31T12:00:00Z",
+    ),
+    (
+        "Changes to user accounts must state a reason.",
+        "Promjene korisničkih računa moraju navesti razlog.",
+    ),
+    (
+        "An IO error occurred. Submitted data might not be properly stored.",
+        "Došlo je do ulazno-izlazne pogreške. Poslani podaci možda nisu ispravno spremljeni.",
+    ),
+    (
+        "A write error occurred. Submitted data might not be properly stored.",
+        "Došlo je do pogreške pri pisanju. Poslani podaci možda nisu ispravno spremljeni.",
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resp::catalog::ProblemType;
+
+    #[test]
+    fn negotiates_preferred_supported_language() {
+        assert_eq!(Language::negotiate(None), Language::English);
+        assert_eq!(
+            Language::negotiate(Some("hr-HR,hr;q=0.9,en;q=0.8")),
+            Language::Croatian
+        );
+        assert_eq!(
+            Language::negotiate(Some("de;q=1.0,en;q=0.5,hr;q=0.7")),
+            Language::Croatian
+        );
+        assert_eq!(Language::negotiate(Some("de, fr")), Language::English);
+        assert_eq!(Language::negotiate(Some("hr;q=0")), Language::English);
+    }
+
+    #[test]
+    fn problem_titles_are_translated() {
+        for kind in ProblemType::ALL {
+            assert_ne!(
+                Language::Croatian.translate(kind.title()),
+                kind.title(),
+                "missing translation for {:?}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn formats_translated_templates() {
+        let args = [("parameter".to_string(), "created_after".to_string())];
+        assert_eq!(
+            Language::Croatian.format(
+                "'{parameter}' must be an RFC 3339 date, e.g. 2023-01-31T12:00:00Z",
+                &args
+            ),
+            "'created_after' mora biti datum u RFC 3339 obliku, npr. 2023-01-31T12:00:00Z"
+        );
+    }
+
+    /// Names of `{name}` placeholders in `template`, sorted.
+    fn placeholders(template: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = template
+            .split('{')
+            .skip(1)
+            .filter_map(|it| it.split_once('}').map(|(name, _)| name))
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn translations_keep_placeholders() {
+        for (english, translated) in CROATIAN {
+            assert_eq!(
+                placeholders(english),
+                placeholders(translated),
+                "placeholders of {:?} changed in translation",
+                english
+            );
+        }
+    }
+
+    /// Calls whose string literal arguments end up as problem details.
+    const DETAIL_CALLS: &[&str] = &[
+        ".detail(",
+        "auth_problem(",
+        "role_problem(",
+        "bad_email(",
+        "bad_username(",
+    ];
+
+    /// Code of `source` before its test module.
+    fn non_test_code(source: &str) -> &str {
+        let lines: Vec<&str> = source.lines().collect();
+        let end = lines.windows(2).position(|it| {
+            it[0].starts_with("#[cfg(")
+                && it[0].contains("test")
+                && it[1].trim_start().starts_with("mod ")
+        });
+        match end {
+            Some(end) => {
+                let offset: usize = lines[..end].iter().map(|it| it.len() + 1).sum();
+                &source[..offset.min(source.len())]
+            }
+            None => source,
+        }
+    }
+
+    /// String literals among arguments of the call starting at the beginning of `code`.
+    fn call_literals(code: &str) -> Vec<String> {
+        let mut literals = vec![];
+        let mut depth = 0;
+        let mut chars = code.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '(' => depth += 1,
+                ')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                '"' => {
+                    let mut literal = String::new();
+                    while let Some(c) = chars.next() {
+                        match c {
+                            '"' => break,
+                            '\\' => match chars.next() {
+                                Some('n') => literal.push('\n'),
+                                Some(escaped) => literal.push(escaped),
+                                None => break,
+                            },
+                            _ => literal.push(c),
+                        }
+                    }
+                    literals.push(literal);
+                }
+                _ => {}
+            }
+        }
+        literals
+    }
+
+    fn collect_sources(dir: &std::path::Path, sources: &mut Vec<std::path::PathBuf>) {
+        for entry in std::fs::read_dir(dir).expect("unable to read source directory") {
+            let path = entry.expect("unable to read source directory").path();
+            if path.is_dir() {
+                collect_sources(&path, sources);
+            } else if path.extension().map_or(false, |it| it == "rs") {
+                sources.push(path);
+            }
+        }
+    }
+
+    #[test]
+    fn problem_details_are_translated() {
+        let mut sources = vec![];
+        collect_sources(
+            &std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut sources,
+        );
+
+        let mut missing = vec![];
+        for path in sources {
+            let source = std::fs::read_to_string(&path).expect("unable to read source file");
+            let code = non_test_code(&source);
+            for call in DETAIL_CALLS {
+                for (start, _) in code.match_indices(call) {
+                    let args = &code[start + call.len() - 1..];
+                    for detail in call_literals(args) {
+                        if Language::Croatian.translate(&detail) == detail {
+                            missing.push(format!("{}: {:?}", path.display(), detail));
+                        }
+                    }
+                }
+            }
+        }
+        assert!(
+            missing.is_empty(),
+            "missing translations:\n{}",
+            missing.join("\n")
+        );
+    }
+}
@@ -1,3 +1,7 @@
+pub mod catalog;
+pub mod catcher;
 pub mod jwt;
+pub mod l10n;
 pub mod problem;
 pub mod util;
+pub mod validation;
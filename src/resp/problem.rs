@@ -11,15 +11,23 @@ use serde_json::{Map, Value};
 use std::fmt::{Display, Formatter};
 use utoipa::ToSchema;
 
+use crate::middleware::request_id::RequestId;
+use crate::resp::catalog::ProblemType;
+use crate::resp::l10n::Language;
+
 /// Implements [RFC7807](https://tools.ietf.org/html/rfc7807).
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 pub struct Problem {
     #[serde(skip)]
     pub status: Status,
+    /// URI of a [`ProblemType`] or `about:blank`
     pub type_uri: String,
     pub title: String,
 
     pub detail: Option<String>,
+    /// Values of `{name}` placeholders in `detail`
+    #[serde(skip)]
+    pub detail_args: Vec<(String, String)>,
     pub instance_uri: Option<String>,
 
     pub body: Map<String, Value>,
@@ -32,6 +40,7 @@ impl Default for Problem {
             type_uri: "about:blank".to_string(),
             title: "Problem".to_string(),
             detail: None,
+            detail_args: Vec::new(),
             instance_uri: None,
             body: Map::new(),
         }
@@ -48,7 +57,11 @@ impl Problem {
         }
     }
 
-    // TODO: Add problem type URIs
+    /// Creates a problem of a type from the catalog.
+    pub fn typed(kind: ProblemType) -> Problem {
+        Problem::new(kind.status(), kind.uri(), kind.title())
+    }
+
     pub fn new_untyped(status: Status, title: impl ToString) -> Problem {
         Problem {
             status,
@@ -63,6 +76,16 @@ impl Problem {
         self
     }
 
+    /// Sets value of a `{name}` placeholder in detail, substituted after detail is translated.
+    pub fn arg(&mut self, name: impl ToString, value: impl ToString) -> &mut Problem {
+        self.detail_args.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn is(&self, kind: ProblemType) -> bool {
+        self.type_uri == kind.uri()
+    }
+
     pub fn instance_uri(&mut self, value: String) -> &mut Problem {
         self.instance_uri = Some(value);
         self
@@ -113,28 +136,35 @@ impl Display for Problem {
 impl std::error::Error for Problem {}
 
 impl<'r> Responder<'r, 'static> for Problem {
-    fn respond_to(self, _: &'r Request<'_>) -> response::Result<'static> {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let language = Language::negotiate(request.headers().get_one("Accept-Language"));
         let mut body = self.body.clone();
 
         // Following are required by rfc7807
         body.insert(String::from("type"), serde_json::Value::from(self.type_uri));
-        body.insert(String::from("title"), serde_json::Value::from(self.title));
+        body.insert(
+            String::from("title"),
+            serde_json::Value::from(language.translate(&self.title)),
+        );
 
         // Optional parameters as specified by rfc7807
-        if self.detail.is_some() {
+        if let Some(detail) = &self.detail {
             body.insert(
                 String::from("detail"),
-                serde_json::Value::from(self.detail.unwrap()),
+                serde_json::Value::from(language.format(detail, &self.detail_args).as_ref()),
             );
         }
         body.insert(
             String::from("status"),
             serde_json::Value::from(self.status.code),
         );
-        if self.instance_uri.is_some() {
+        let instance_uri = self
+            .instance_uri
+            .or_else(|| RequestId::of(request).map(RequestId::instance_uri));
+        if let Some(instance_uri) = instance_uri {
             body.insert(
                 String::from("instance"),
-                serde_json::Value::from(self.instance_uri.unwrap()),
+                serde_json::Value::from(instance_uri),
             );
         }
 
@@ -144,22 +174,19 @@ impl<'r> Responder<'r, 'static> for Problem {
         Response::build()
             .status(self.status)
             .header(ContentType::new("application", "problem+json"))
-            .raw_header(CONTENT_LANGUAGE.as_str(), "en")
+            .raw_header(CONTENT_LANGUAGE.as_str(), language.tag())
             .sized_body(body_string.len(), Cursor::new(body_string))
             .ok()
     }
 }
 
 pub mod problems {
+    use crate::resp::catalog::ProblemType;
     use crate::resp::problem::Problem;
-    use rocket::http::Status;
 
     #[inline]
     pub fn parse_problem() -> Problem {
-        Problem::new_untyped(
-            Status::BadRequest,
-            "There was a problem parsing part of the request.",
-        )
+        Problem::typed(ProblemType::RequestParse)
     }
 }
 
@@ -169,38 +196,19 @@ impl From<mongodb::error::Error> for Problem {
         use mongodb::error::ErrorKind;
 
         fn mongodb_problem() -> Problem {
-            Problem::new_untyped(
-                Status::InternalServerError,
-                "MongoDB failed while processing request.",
-            )
+            Problem::typed(ProblemType::ServerDatabase)
         }
 
         fn access_problem() -> Problem {
-            Problem::new_untyped(
-                Status::InternalServerError,
-                "Server was unable to access MongoDB.",
-            )
+            Problem::typed(ProblemType::ServerDatabaseAccess)
         }
 
         fn bad_db_request() -> Problem {
-            Problem::new_untyped(
-                Status::InternalServerError,
-                "MongoDB was unable to process bad server request.",
-            )
+            Problem::typed(ProblemType::ServerDatabaseRequest)
         }
 
         fn bson_problem() -> Problem {
-            Problem::new_untyped(
-                Status::InternalServerError,
-                "There was a problem with handling MongoDB bson.",
-            )
-        }
-
-        fn timeout_problem() -> Problem {
-            Problem::new_untyped(
-                Status::InternalServerError,
-                "A timeout occurred while accessing MongoDB.",
-            )
+            Problem::typed(ProblemType::ServerBson)
         }
 
         match e.kind.as_ref() {
@@ -232,19 +240,19 @@ impl From<mongodb::error::Error> for Problem {
 
 impl From<bson::de::Error> for Problem {
     fn from(_: bson::de::Error) -> Self {
-        Problem::new_untyped(
-            Status::InternalServerError,
-            "An error occurred while processing BSON data.",
-        )
+        Problem::typed(ProblemType::ServerBson)
+    }
+}
+
+impl From<bson::ser::Error> for Problem {
+    fn from(_: bson::ser::Error) -> Self {
+        Problem::typed(ProblemType::ServerBson)
     }
 }
 
 impl From<serde_json::Error> for Problem {
     fn from(_: serde_json::Error) -> Self {
-        Problem::new_untyped(
-            Status::InternalServerError,
-            "An error occurred while processing JSON data.",
-        )
+        Problem::typed(ProblemType::ServerJson)
     }
 }
 
@@ -253,16 +261,18 @@ impl From<jsonwebtoken::errors::Error> for Problem {
         use jsonwebtoken::errors::ErrorKind;
 
         match e.into_kind() {
-            ErrorKind::ExpiredSignature => {
-                Problem::new_untyped(Status::Unauthorized, "Expired JWT signature.")
-            }
-            _ => Problem::new_untyped(Status::Unauthorized, "Error while handling JWT."),
+            ErrorKind::ExpiredSignature => Problem::typed(ProblemType::AuthExpiredToken),
+            ErrorKind::InvalidRsaKey(_)
+            | ErrorKind::InvalidEcdsaKey
+            | ErrorKind::InvalidKeyFormat
+            | ErrorKind::RsaFailedSigning => Problem::typed(ProblemType::ServerKeys),
+            _ => Problem::typed(ProblemType::AuthInvalidToken),
         }
     }
 }
 
 impl From<std::io::Error> for Problem {
     fn from(_: std::io::Error) -> Self {
-        Problem::new_untyped(Status::InternalServerError, "Server IO error")
+        Problem::typed(ProblemType::ServerIo)
     }
 }
@@ -0,0 +1,382 @@
+//! Validation of submitted data.
+//!
+//! Input types implement [`Validate`] and report every invalid field to a [`Validator`], which
+//! turns them into a single problem listing all of them:
+//!
+//! ```json
+//! {"errors": [{"field": "username", "code": "too_short", "limit": 5}]}
+//! ```
+//!
+//! [`ValidForm`] and [`ValidJson`] data guards parse and validate request bodies. Bodies that
+//! can't be parsed are reported in the same way.
+
+use std::ops::Deref;
+
+use rocket::data::{self, Data, FromData};
+use rocket::form::{self, Form, FromForm};
+use rocket::outcome::Outcome;
+use rocket::serde::json::{self, Json};
+use rocket::{Orbit, Request, Rocket};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use crate::resp::catalog::ProblemType;
+use crate::resp::catcher::guard_failure;
+use crate::resp::problem::Problem;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldErrorCode {
+    /// Field is missing or empty
+    Required,
+    TooShort,
+    TooLong,
+    /// List has more items than allowed
+    TooMany,
+    /// Value doesn't have the expected format, e.g. it's not an email address
+    BadFormat,
+    /// Value is outside the allowed range
+    OutOfRange,
+    /// Value can be mistaken for a different one, e.g. a username mixing scripts
+    Confusable,
+    /// Password is too easy to guess, `limit` is the required strength score
+    TooWeak,
+    /// Password is among commonly used ones
+    TooCommon,
+    /// Field isn't expected
+    Unknown,
+    /// Field was submitted more than once
+    Duplicate,
+    /// Value refers to an entry that doesn't exist
+    NotFound,
+    /// Value couldn't be parsed
+    Invalid,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct FieldError {
+    /// Name of the field, e.g. `tags[2]`; empty if the error concerns the whole body
+    pub field: String,
+    pub code: FieldErrorCode,
+    /// Length, count or range bound that was violated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<i64>,
+    /// Parser message for values that couldn't be parsed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl FieldError {
+    pub fn new(field: impl ToString, code: FieldErrorCode) -> FieldError {
+        FieldError {
+            field: field.to_string(),
+            code,
+            limit: None,
+            message: None,
+        }
+    }
+
+    pub fn limit(mut self, limit: i64) -> FieldError {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn message(mut self, message: impl ToString) -> FieldError {
+        self.message = Some(message.to_string());
+        self
+    }
+}
+
+/// Problem listing all invalid fields.
+pub fn invalid_fields(errors: Vec<FieldError>) -> Problem {
+    Problem::typed(ProblemType::RequestInvalidFields)
+        .insert("errors", errors)
+        .to_owned()
+}
+
+/// Collects errors of invalid fields.
+#[derive(Debug, Default)]
+pub struct Validator<'a> {
+    errors: Vec<FieldError>,
+    rocket: Option<&'a Rocket<Orbit>>,
+}
+
+impl<'a> Validator<'a> {
+    pub fn new() -> Validator<'a> {
+        Validator::default()
+    }
+
+    /// Validator with access to managed state of `rocket`, e.g. configured limits.
+    pub fn with_state(rocket: &'a Rocket<Orbit>) -> Validator<'a> {
+        Validator {
+            errors: Vec::new(),
+            rocket: Some(rocket),
+        }
+    }
+
+    /// Managed state, if it's available.
+    pub fn state<T: Send + Sync + 'static>(&self) -> Option<&'a T> {
+        self.rocket.and_then(|it| it.state::<T>())
+    }
+
+    pub fn push(&mut self, error: FieldError) -> &mut Self {
+        self.errors.push(error);
+        self
+    }
+
+    /// Adds an error with `code` for `field` unless `valid`.
+    pub fn check(&mut self, valid: bool, field: impl ToString, code: FieldErrorCode) -> &mut Self {
+        if !valid {
+            self.push(FieldError::new(field, code));
+        }
+        self
+    }
+
+    /// Checks that `length` of `field` is between `min` and `max`, inclusive.
+    ///
+    /// Length is measured by the caller, so fields can be limited in characters or bytes.
+    pub fn length(
+        &mut self,
+        field: impl ToString,
+        length: usize,
+        min: usize,
+        max: usize,
+    ) -> &mut Self {
+        if length == 0 && min > 0 {
+            self.push(FieldError::new(field, FieldErrorCode::Required));
+        } else if length < min {
+            self.push(FieldError::new(field, FieldErrorCode::TooShort).limit(min as i64));
+        } else if length > max {
+            self.push(FieldError::new(field, FieldErrorCode::TooLong).limit(max as i64));
+        }
+        self
+    }
+
+    /// Checks that list `field` doesn't have more than `max` items.
+    pub fn count(&mut self, field: impl ToString, count: usize, max: usize) -> &mut Self {
+        if count > max {
+            self.push(FieldError::new(field, FieldErrorCode::TooMany).limit(max as i64));
+        }
+        self
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn errors(&self) -> &[FieldError] {
+        &self.errors
+    }
+
+    /// Fails with a problem listing all collected errors, if there are any.
+    pub fn finish(self) -> Result<(), Problem> {
+        match self.errors.is_empty() {
+            true => Ok(()),
+            false => Err(invalid_fields(self.errors)),
+        }
+    }
+}
+
+/// Submitted data that can be checked for invalid fields.
+pub trait Validate {
+    /// Reports all invalid fields to `validator`.
+    fn check(&self, validator: &mut Validator<'_>);
+
+    fn validate(&self) -> Result<(), Problem> {
+        self.validate_with(Validator::new())
+    }
+
+    fn validate_with(&self, mut validator: Validator<'_>) -> Result<(), Problem> {
+        self.check(&mut validator);
+        validator.finish()
+    }
+}
+
+impl From<&form::Error<'_>> for FieldError {
+    fn from(error: &form::Error<'_>) -> Self {
+        use rocket::form::error::ErrorKind;
+
+        let field = error
+            .name
+            .as_ref()
+            .map(|it| it.to_string())
+            .unwrap_or_default();
+
+        match &error.kind {
+            ErrorKind::Missing => FieldError::new(field, FieldErrorCode::Required),
+            ErrorKind::Unexpected | ErrorKind::Unknown => {
+                FieldError::new(field, FieldErrorCode::Unknown)
+            }
+            ErrorKind::Duplicate => FieldError::new(field, FieldErrorCode::Duplicate),
+            ErrorKind::InvalidLength {
+                min: Some(min),
+                max: None,
+            } => FieldError::new(field, FieldErrorCode::TooShort).limit(*min as i64),
+            ErrorKind::InvalidLength {
+                min: None,
+                max: Some(max),
+            } => FieldError::new(field, FieldErrorCode::TooLong).limit(*max as i64),
+            ErrorKind::OutOfRange { .. } => {
+                FieldError::new(field, FieldErrorCode::OutOfRange).message(&error.kind)
+            }
+            kind => FieldError::new(field, FieldErrorCode::Invalid).message(kind),
+        }
+    }
+}
+
+impl From<form::Errors<'_>> for Problem {
+    fn from(errors: form::Errors<'_>) -> Self {
+        invalid_fields(errors.iter().map(FieldError::from).collect())
+    }
+}
+
+/// Extracts the field name from serde messages like "missing field `name` at line 1 column 2".
+fn serde_field<'a>(message: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = message.strip_prefix(prefix)?.strip_prefix(" `")?;
+    rest.split('`').next()
+}
+
+impl From<json::Error<'_>> for Problem {
+    fn from(error: json::Error<'_>) -> Self {
+        let error = match error {
+            json::Error::Io(_) => return Problem::typed(ProblemType::RequestParse),
+            json::Error::Parse(_, error) => error,
+        };
+
+        // serde_json doesn't report paths, so fields of nested values are named without their parents
+        let message = error.to_string();
+        let field_error = if let Some(field) = serde_field(&message, "missing field") {
+            FieldError::new(field, FieldErrorCode::Required)
+        } else if let Some(field) = serde_field(&message, "unknown field") {
+            FieldError::new(field, FieldErrorCode::Unknown)
+        } else if let Some(field) = serde_field(&message, "duplicate field") {
+            FieldError::new(field, FieldErrorCode::Duplicate)
+        } else {
+            FieldError::new("", FieldErrorCode::Invalid).message(&message)
+        };
+
+        invalid_fields(vec![field_error])
+    }
+}
+
+/// Form data guard which fails with a [`Problem`] listing invalid fields.
+#[derive(Debug)]
+pub struct ValidForm<T>(pub T);
+
+impl<T> ValidForm<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidForm<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: FromForm<'r> + Validate> FromData<'r> for ValidForm<T> {
+    type Error = Problem;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let value = match Form::<T>::from_data(req, data).await {
+            Outcome::Success(form) => form.into_inner(),
+            Outcome::Failure((_, errors)) => return guard_failure(req, Problem::from(errors)),
+            Outcome::Forward(data) => return Outcome::Forward(data),
+        };
+
+        match value.validate_with(Validator::with_state(req.rocket())) {
+            Ok(()) => Outcome::Success(ValidForm(value)),
+            Err(problem) => guard_failure(req, problem),
+        }
+    }
+}
+
+/// JSON data guard which fails with a [`Problem`] listing invalid fields.
+#[derive(Debug)]
+pub struct ValidJson<T>(pub T);
+
+impl<T> ValidJson<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ValidJson<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+#[rocket::async_trait]
+impl<'r, T: Deserialize<'r> + Validate + Send> FromData<'r> for ValidJson<T> {
+    type Error = Problem;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let value = match Json::<T>::from_data(req, data).await {
+            Outcome::Success(json) => json.into_inner(),
+            Outcome::Failure((_, error)) => return guard_failure(req, Problem::from(error)),
+            Outcome::Forward(data) => return Outcome::Forward(data),
+        };
+
+        match value.validate_with(Validator::with_state(req.rocket())) {
+            Ok(()) => Outcome::Success(ValidJson(value)),
+            Err(problem) => guard_failure(req, problem),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, FromForm)]
+    struct Signup {
+        username: String,
+        password: String,
+    }
+
+    impl Validate for Signup {
+        fn check(&self, validator: &mut Validator<'_>) {
+            validator
+                .length("username", self.username.len(), 5, 32)
+                .length("password", self.password.len(), 8, 1024);
+        }
+    }
+
+    #[test]
+    fn validator_lists_every_invalid_field() {
+        let signup = Signup {
+            username: "abc".to_string(),
+            password: String::new(),
+        };
+        let problem = signup.validate().unwrap_err();
+
+        assert!(problem.is(ProblemType::RequestInvalidFields));
+        assert_eq!(
+            problem.body.get("errors").cloned().unwrap(),
+            serde_json::json!([
+                { "field": "username", "code": "too_short", "limit": 5 },
+                { "field": "password", "code": "required" },
+            ])
+        );
+    }
+
+    #[test]
+    fn form_errors_become_field_errors() {
+        let errors = Form::<Signup>::parse("username=someone").unwrap_err();
+        let problem = Problem::from(errors);
+        let errors: Vec<FieldError> =
+            serde_json::from_value(problem.body.get("errors").cloned().unwrap()).unwrap();
+
+        assert_eq!(
+            errors,
+            [FieldError::new("password", FieldErrorCode::Required)]
+        );
+    }
+}
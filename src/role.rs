@@ -1,7 +1,23 @@
+use std::convert::TryFrom;
+
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize, ToSchema)]
+use crate::error::BackendError;
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Serialize,
+    Deserialize,
+    ToSchema,
+    FromFormField,
+)]
 pub enum Role {
     #[schema(rename = "None")]
     None,
@@ -17,9 +33,14 @@ impl Role {
     const ALL: &[Role] = &[Role::None, Role::Normal, Role::Author, Role::Admin];
 }
 
-impl From<u8> for Role {
-    fn from(value: u8) -> Self {
-        Role::ALL[value as usize]
+impl TryFrom<u8> for Role {
+    type Error = BackendError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Role::ALL
+            .get(value as usize)
+            .copied()
+            .ok_or(BackendError::InvalidRole(value))
     }
 }
 
@@ -47,8 +68,41 @@ impl std::fmt::Display for Role {
     }
 }
 
+impl std::str::FromStr for Role {
+    type Err = BackendError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Role::ALL
+            .iter()
+            .copied()
+            .find(|it| it.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| BackendError::UnknownRole(s.to_string()))
+    }
+}
+
 impl std::convert::Into<String> for Role {
     fn into(self) -> String {
         self.to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn roles_are_parsed_by_name() {
+        assert_eq!(Role::from_str("author").unwrap(), Role::Author);
+        assert_eq!(Role::from_str(" Admin ").unwrap(), Role::Admin);
+        assert_eq!(Role::from_str("NONE").unwrap(), Role::None);
+        for role in Role::ALL {
+            assert_eq!(Role::from_str(&role.to_string()).unwrap(), *role);
+        }
+        assert!(matches!(
+            Role::from_str("owner"),
+            Err(BackendError::UnknownRole(it)) if it == "owner"
+        ));
+    }
+}
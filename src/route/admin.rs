@@ -0,0 +1,178 @@
+use rocket::serde::json::Json;
+use rocket::State;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::data::store::Store;
+use crate::data::user::audit::{AuditAction, AuditEntry, UserAuditDbExt};
+use crate::data::user::db::{problem as user_problem, UserRepository};
+use crate::data::user::{AccountStatus, UserResponse};
+use crate::resp::catalog::ProblemType;
+use crate::resp::jwt::{role_problem, UserRoleToken};
+use crate::resp::problem::Problem;
+use crate::role::Role;
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct RoleChangeData {
+    pub role: Role,
+    /// Why the role was changed
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+pub struct StatusChangeData {
+    pub status: AccountStatus,
+    /// Why the status was changed
+    pub reason: String,
+}
+
+fn require_admin(auth: &UserRoleToken, target: Uuid) -> Result<(), Problem> {
+    if auth.role < Role::Admin {
+        return Err(role_problem("Only admins can manage users."));
+    }
+
+    // Prevents admins from locking themselves out
+    if auth.user == target {
+        return Err(Problem::typed(ProblemType::UserSelfChange));
+    }
+
+    Ok(())
+}
+
+fn require_reason(reason: &str) -> Result<(), Problem> {
+    if reason.trim().is_empty() {
+        return Err(Problem::typed(ProblemType::RequestMissingReason)
+            .detail("Changes to user accounts must state a reason.")
+            .to_owned());
+    }
+    Ok(())
+}
+
+/// Change role of a user
+#[utoipa::path(
+    params(
+        ("id", description = "user ID")
+    ),
+    request_body = RoleChangeData,
+    responses(
+        (status = 400, description = "Missing reason or changing own role", body = Problem),
+        (status = 401, description = "Missing/expired token or insufficient privileges", body = Problem),
+        (status = 404, description = "Querried user doesn't exist", body = Problem),
+        (status = 200, description = "Updated user information", body = UserResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[put("/user/<id>/role", format = "application/json", data = "<change>")]
+#[tracing::instrument]
+pub async fn admin_user_role(
+    id: Uuid,
+    change: Json<RoleChangeData>,
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<UserResponse>, Problem> {
+    require_admin(&auth, id)?;
+    require_reason(&change.reason)?;
+
+    let mut user = db
+        .get_user(id)
+        .await?
+        .ok_or_else(|| user_problem::not_found(id))?;
+
+    if user.user_role != change.role {
+        db.update_role(id, change.role).await?;
+        db.record_audit(&AuditEntry::new(
+            id,
+            auth.user,
+            AuditAction::RoleChanged {
+                from: user.user_role,
+                to: change.role,
+            },
+            &change.reason,
+        ))
+        .await?;
+
+        user.user_role = change.role;
+    }
+
+    Ok(Json(UserResponse::from(user)))
+}
+
+/// Suspend, ban or restore a user
+#[utoipa::path(
+    params(
+        ("id", description = "user ID")
+    ),
+    request_body = StatusChangeData,
+    responses(
+        (status = 400, description = "Missing reason or changing own status", body = Problem),
+        (status = 401, description = "Missing/expired token or insufficient privileges", body = Problem),
+        (status = 404, description = "Querried user doesn't exist", body = Problem),
+        (status = 200, description = "Updated user information", body = UserResponse),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[put("/user/<id>/status", format = "application/json", data = "<change>")]
+#[tracing::instrument]
+pub async fn admin_user_status(
+    id: Uuid,
+    change: Json<StatusChangeData>,
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<UserResponse>, Problem> {
+    require_admin(&auth, id)?;
+    require_reason(&change.reason)?;
+
+    let mut user = db
+        .get_user(id)
+        .await?
+        .ok_or_else(|| user_problem::not_found(id))?;
+
+    if user.status != change.status {
+        db.update_status(id, &change.status).await?;
+        db.record_audit(&AuditEntry::new(
+            id,
+            auth.user,
+            AuditAction::StatusChanged {
+                from: user.status.clone(),
+                to: change.status.clone(),
+            },
+            &change.reason,
+        ))
+        .await?;
+
+        user.status = change.status.clone();
+    }
+
+    Ok(Json(UserResponse::from(user)))
+}
+
+/// List administrative changes made to a user
+#[utoipa::path(
+    params(
+        ("id", description = "user ID")
+    ),
+    responses(
+        (status = 401, description = "Missing/expired token or insufficient privileges", body = Problem),
+        (status = 200, description = "Changes made to the user, newest first", body = Vec<AuditEntry>),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[get("/user/<id>/audit")]
+#[tracing::instrument]
+pub async fn admin_user_audit(
+    id: Uuid,
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<Vec<AuditEntry>>, Problem> {
+    if auth.role < Role::Admin {
+        return Err(role_problem("Only admins can manage users."));
+    }
+
+    db.user_audit(id).await.map(Json)
+}
@@ -1,24 +1,143 @@
-use crate::data::class::db::ClassCreateData;
-use crate::resp::jwt::auth_problem;
+use crate::data::class::db::{ClassCreateData, ClassRepository};
+use crate::data::class::Class;
+use crate::data::store::Store;
+use crate::data::user::db::UserRepository;
+use crate::data::user::profile::normalize_tags;
+use crate::resp::jwt::role_problem;
 use crate::resp::jwt::UserRoleToken;
 use crate::resp::problem::Problem;
+use crate::resp::validation::{invalid_fields, FieldError, FieldErrorCode, ValidJson};
 use crate::route::Role;
-use mongodb::Database;
-use rocket::http::CookieJar;
 use rocket::serde::json::Json;
 use rocket::State;
 
-#[utoipa::path(request_body = ClassCreateData)]
+/// Create a class
+///
+/// Owner of the class is its first teacher. Only admins can create classes owned by other users,
+/// who must exist.
+#[utoipa::path(
+    request_body = ClassCreateData,
+    responses(
+        (status = 400, description = "Class has invalid fields or owner doesn't exist", body = Problem),
+        (status = 401, description = "Missing/expired token or insufficient privileges", body = Problem),
+        (status = 200, description = "Created class", body = Class),
+    )
+)]
 #[post("/class", format = "application/json", data = "<class>")]
 #[tracing::instrument]
-pub fn class_create<'a>(
-    class: Json<ClassCreateData>,
+pub async fn class_create(
+    class: ValidJson<ClassCreateData>,
     auth: UserRoleToken,
-    cookies: &'a CookieJar<'_>,
-    db: &State<Database>,
-) -> Result<(), Problem> {
+    db: &State<Store>,
+) -> Result<Json<Class>, Problem> {
     if auth.role < Role::Author {
-        return Err(auth_problem("Permission level too low."));
+        return Err(role_problem("Permission level too low."));
+    }
+    let data = class.into_inner();
+
+    let owner = data.owner.unwrap_or(auth.user);
+    if owner != auth.user {
+        if auth.role < Role::Admin {
+            return Err(role_problem(
+                "Only admins can create classes for other users.",
+            ));
+        }
+        if db.get_user(owner).await?.is_none() {
+            return Err(invalid_fields(vec![FieldError::new(
+                "owner",
+                FieldErrorCode::NotFound,
+            )]));
+        }
+    }
+
+    let class = Class::new(data.name.trim(), normalize_tags(&data.tags), owner);
+    db.create_class(&class).await?;
+
+    Ok(Json(class))
+}
+
+#[cfg(all(test, feature = "generate-security"))]
+mod tests {
+    use rocket::http::{ContentType, Cookie, Status};
+    use rocket::local::asynchronous::Client;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::resp::catalog::ProblemType;
+    use crate::route::testing::{create_user, test_client};
+
+    async fn create(client: &Client, cookie: &Cookie<'static>, body: Value) -> (Status, Value) {
+        let response = client
+            .post("/api/v1/class")
+            .header(ContentType::JSON)
+            .cookie(cookie.clone())
+            .body(body.to_string())
+            .dispatch()
+            .await;
+        let status = response.status();
+        (
+            status,
+            response.into_json().await.expect("invalid response json"),
+        )
+    }
+
+    fn teachers(class: &Value) -> Vec<&str> {
+        class["participants"]
+            .as_array()
+            .expect("class without participants")
+            .iter()
+            .filter(|it| it["class_role"] == "Teacher")
+            .map(|it| it["user_id"].as_str().expect("participant without id"))
+            .collect()
+    }
+
+    #[rocket::async_test]
+    async fn v1_class_create_is_limited_to_authors() {
+        let client = test_client().await;
+        let (_, normal) = create_user(&client, "v1_class_normal", Role::Normal).await;
+        let (author, author_cookie) = create_user(&client, "v1_class_author", Role::Author).await;
+
+        let (status, problem) = create(&client, &normal, json!({ "name": "Biology" })).await;
+        assert_eq!(status, Status::Unauthorized);
+        assert_eq!(problem["type"], ProblemType::AuthInsufficientRole.uri());
+
+        let (status, class) = create(
+            &client,
+            &author_cookie,
+            json!({ "name": " Biology ", "tags": ["Cells"] }),
+        )
+        .await;
+        assert_eq!(status, Status::Ok);
+        assert_eq!(class["name"], "Biology");
+        assert_eq!(class["tags"], json!(["cells"]));
+        let author_id = author.id.to_string();
+        assert_eq!(teachers(&class), [author_id.as_str()]);
+    }
+
+    #[rocket::async_test]
+    async fn v1_class_create_for_other_owner_needs_admin() {
+        let client = test_client().await;
+        let (owner, _) = create_user(&client, "v1_class_owner", Role::Author).await;
+        let (_, author) = create_user(&client, "v1_class_other_author", Role::Author).await;
+        let (_, admin) = create_user(&client, "v1_class_admin", Role::Admin).await;
+        let body = json!({ "name": "Physics", "owner": owner.id });
+
+        let (status, problem) = create(&client, &author, body.clone()).await;
+        assert_eq!(status, Status::Unauthorized);
+        assert_eq!(problem["type"], ProblemType::AuthInsufficientRole.uri());
+
+        let (status, class) = create(&client, &admin, body).await;
+        assert_eq!(status, Status::Ok);
+        let owner_id = owner.id.to_string();
+        assert_eq!(teachers(&class), [owner_id.as_str()]);
+
+        let body = json!({ "name": "Physics", "owner": Uuid::new_v4() });
+        let (status, problem) = create(&client, &admin, body).await;
+        assert_eq!(status, Status::BadRequest);
+        assert_eq!(
+            problem["errors"],
+            json!([{ "field": "owner", "code": "not_found" }])
+        );
     }
-    Ok(())
 }
@@ -0,0 +1,171 @@
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use rocket::State;
+use utoipa::ToSchema;
+
+use crate::data::store::Store;
+use crate::security::Security;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Readiness {
+    /// Whether the backend can serve requests
+    pub ready: bool,
+    /// Whether the database responded
+    pub database: bool,
+    /// Whether JWT signing keys are loaded and usable
+    pub keys: bool,
+    /// Versions of database migrations that weren't applied yet
+    pub pending_migrations: Vec<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct BuildInfo {
+    /// Backend crate version
+    pub version: String,
+    /// Commit the backend was built from, "unknown" if it wasn't built from a git checkout
+    pub git_hash: String,
+    /// Enabled cargo features
+    pub features: Vec<String>,
+}
+
+impl BuildInfo {
+    pub fn current() -> BuildInfo {
+        let features = [
+            ("tls", cfg!(feature = "tls")),
+            ("validation-regex", cfg!(feature = "validation-regex")),
+            ("generate-security", cfg!(feature = "generate-security")),
+            ("smtp", cfg!(feature = "smtp")),
+        ];
+
+        BuildInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_hash: env!("KNOWMARK_GIT_HASH").to_string(),
+            features: features
+                .iter()
+                .filter(|(_, enabled)| *enabled)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Check the backend is running
+#[utoipa::path(
+    responses(
+        (status = 204, description = "Backend is running"),
+    )
+)]
+#[get("/health")]
+pub fn health_live() -> Status {
+    Status::NoContent
+}
+
+/// Check the backend can serve requests
+///
+/// Pings the database, checks JWT keys and lists pending database migrations.
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Backend is ready", body = Readiness),
+        (status = 503, description = "Database isn't reachable, keys aren't usable or migrations are pending", body = Readiness),
+    )
+)]
+#[get("/health/ready")]
+#[tracing::instrument(skip(db, security))]
+pub async fn health_ready(
+    db: &State<Store>,
+    security: &State<Security>,
+) -> (Status, Json<Readiness>) {
+    let database = match db.ping().await {
+        Ok(()) => true,
+        Err(problem) => {
+            tracing::warn!("Database isn't reachable: {:?}", problem);
+            false
+        }
+    };
+
+    let keys = match security.jwt_keys.check() {
+        Ok(()) => true,
+        Err(err) => {
+            tracing::warn!("JWT keys aren't usable: {}", err);
+            false
+        }
+    };
+
+    let pending_migrations = match database {
+        true => match db.pending_migrations().await {
+            Ok(it) => it,
+            Err(problem) => {
+                tracing::warn!("Unable to list pending migrations: {:?}", problem);
+                Vec::new()
+            }
+        },
+        false => Vec::new(),
+    };
+
+    let ready = database && keys && pending_migrations.is_empty();
+    let status = match ready {
+        true => Status::Ok,
+        false => Status::ServiceUnavailable,
+    };
+
+    (
+        status,
+        Json(Readiness {
+            ready,
+            database,
+            keys,
+            pending_migrations,
+        }),
+    )
+}
+
+/// Build information
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Version, commit and enabled features of the backend", body = BuildInfo),
+    )
+)]
+#[get("/version")]
+pub fn version() -> Json<BuildInfo> {
+    Json(BuildInfo::current())
+}
+
+#[cfg(all(test, feature = "generate-security"))]
+mod tests {
+    use rocket::local::asynchronous::Client;
+
+    use super::*;
+    use crate::security::KeySet;
+    use crate::settings::Settings;
+    use crate::BackendBuilder;
+
+    #[rocket::async_test]
+    async fn memory_backend_is_ready() {
+        let backend =
+            BackendBuilder::new(Settings::default(), Security::ephemeral(), Store::memory())
+                .build()
+                .expect("unable to build test backend");
+        let client = Client::tracked(backend).await.expect("invalid backend");
+
+        let response = client.get("/api/v1/health").dispatch().await;
+        assert_eq!(response.status(), Status::NoContent);
+
+        let response = client.get("/api/v1/health/ready").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let readiness: Readiness = response.into_json().await.expect("invalid response json");
+        assert!(readiness.ready && readiness.database && readiness.keys);
+
+        let response = client.get("/api/v1/version").dispatch().await;
+        let info: BuildInfo = response.into_json().await.expect("invalid response json");
+        assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    }
+
+    #[test]
+    fn broken_keys_are_detected() {
+        let keys = KeySet {
+            public: b"not a key".to_vec(),
+            private: b"not a key".to_vec(),
+        };
+        assert!(keys.check().is_err());
+    }
+}
@@ -0,0 +1,12 @@
+use rocket::http::ContentType;
+use rocket::State;
+
+use crate::middleware::metrics::Metrics;
+
+/// Metrics in Prometheus text format.
+///
+/// Mounted on `/`, or served on a separate port when `metrics.port` is set.
+#[get("/metrics")]
+pub fn metrics_get(metrics: &State<Metrics>) -> (ContentType, String) {
+    (ContentType::Plain, metrics.render())
+}
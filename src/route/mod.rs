@@ -2,22 +2,40 @@ use std::collections::BTreeMap;
 
 use rocket::{response::Redirect, Build, Rocket, Route};
 
+pub mod admin;
 pub mod class;
 pub mod files;
+pub mod health;
+pub mod metrics;
+pub mod profile;
 pub mod quiz;
+pub mod search;
+pub mod tag;
 pub mod users;
 
+#[cfg(all(test, feature = "generate-security"))]
+mod testing;
+
+use admin::*;
 use class::*;
 use files::*;
+use health::*;
+use profile::*;
 use quiz::*;
+use search::*;
+use tag::*;
 use users::*;
 
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 use crate::{
-    data::{class as cd, class::db as cdbd, quiz as qd, user::db as udbd, user::UserResponse},
-    resp::{jwt::doc::JWTAuth, problem::Problem},
+    data::{
+        class as cd, class::db as cdbd, quiz as qd, user::audit as uad, user::db as udbd,
+        user::profile as upd, user::settings as usd, user::AccountStatus, user::UserResponse,
+    },
+    middleware::request_id::traced,
+    resp::{catcher::default_catcher, jwt::doc::JWTAuth, problem::Problem},
     role::Role,
 };
 
@@ -29,13 +47,33 @@ use crate::{
         user_create,
         user_delete,
         user_logout,
+        user_username_change,
+        user_email_change,
+        user_password_change,
+        user_password_reset_request,
+        user_password_reset,
+        user_verify_email,
+        user_verify_email_resend,
         login_submit,
+        profile_get,
+        profile_update,
+        settings_get,
+        settings_update,
+        admin_user_role,
+        admin_user_status,
+        admin_user_audit,
         class_create,
         quiz_list,
+        quiz_recommended,
         quiz_create,
         quiz_info,
         quiz_delete,
-        quiz_submit_answers
+        quiz_submit_answers,
+        search_all,
+        tag_list,
+        health_live,
+        health_ready,
+        version
     ),
     components(schemas(
         Role,
@@ -48,13 +86,44 @@ use crate::{
         qd::AnswerValidation,
         qd::AnswerChoice,
         qd::QuizParticipant,
+        cd::Class,
         cd::ClassRole,
+        cd::ClassParticipant,
         cdbd::ClassCreateData,
         cdbd::AddUserData,
         QuizListResponse,
         UserResponse,
+        crate::data::user::UserSummary,
+        UserPage,
+        QuizPage,
         udbd::UserLoginData,
         udbd::UserSignupData<'_>,
+        udbd::UsernameChangeData,
+        udbd::EmailChangeData,
+        udbd::EmailVerificationData,
+        udbd::PasswordChangeData,
+        udbd::PasswordResetRequestData,
+        udbd::PasswordResetData,
+        upd::Profile,
+        upd::BirthDate,
+        upd::Tag,
+        upd::Interests,
+        usd::UserSettings,
+        usd::NotificationSettings,
+        AccountStatus,
+        uad::AuditEntry,
+        uad::AuditAction,
+        RoleChangeData,
+        StatusChangeData,
+        crate::data::search::SearchResults,
+        crate::data::search::SearchHit,
+        crate::data::search::Snippet,
+        crate::data::tag::TagUsage,
+        Readiness,
+        BuildInfo,
+        crate::resp::catalog::ProblemType,
+        crate::resp::validation::FieldError,
+        crate::resp::validation::FieldErrorCode,
         Problem
     )),
     modifiers(&JWTAuth, &V1_PREFIX)
@@ -92,18 +161,40 @@ pub fn api_v1() -> Vec<Route> {
         user_create,
         user_delete,
         user_logout,
+        user_username_change,
+        user_email_change,
+        user_password_change,
+        user_password_reset_request,
+        user_password_reset,
+        user_verify_email,
+        user_verify_email_resend,
         login_submit,
+        profile_get,
+        profile_update,
+        settings_get,
+        settings_update,
+        admin_user_role,
+        admin_user_status,
+        admin_user_audit,
         class_create,
         quiz_list,
+        quiz_recommended,
         quiz_create,
         quiz_info,
         quiz_delete,
-        quiz_submit_answers
+        quiz_submit_answers,
+        search_all,
+        tag_list,
+        health_live,
+        health_ready,
+        version
     ]
 }
 
 pub fn mount_api(rocket: Rocket<Build>) -> Rocket<Build> {
-    let mut r = rocket.mount("/api/v1", api_v1());
+    let mut r = rocket
+        .mount("/api/v1", traced(api_v1()))
+        .register("/", catchers![default_catcher]);
 
     #[cfg(debug_assertions)]
     {
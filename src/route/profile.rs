@@ -0,0 +1,117 @@
+use rocket::serde::json::Json;
+use rocket::State;
+use uuid::Uuid;
+
+use crate::data::store::Store;
+use crate::data::user::db::{problem as user_problem, UserRepository};
+use crate::data::user::profile::Profile;
+use crate::data::user::settings::{UserSettings, UserSettingsDbExt};
+use crate::resp::jwt::{role_problem, UserRoleToken};
+use crate::resp::problem::Problem;
+use crate::resp::validation::ValidJson;
+use crate::role::Role;
+
+/// Get user profile
+#[utoipa::path(
+    params(
+        ("id", description = "user ID")
+    ),
+    responses(
+        (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 200, description = "Profile of the user", body = Profile),
+        (status = 404, description = "Querried user doesn't exist", body = Problem),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[get("/user/<id>/profile")]
+#[tracing::instrument]
+pub async fn profile_get(
+    id: Uuid,
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<Profile>, Problem> {
+    if auth.role < Role::Normal {
+        return Err(role_problem("Only members can view other users."));
+    }
+
+    let user = db
+        .get_user(id)
+        .await?
+        .ok_or_else(|| user_problem::not_found(id))?;
+
+    Ok(Json(user.profile))
+}
+
+/// Update profile of the current user
+#[utoipa::path(
+    request_body = Profile,
+    responses(
+        (status = 400, description = "Invalid profile data", body = Problem),
+        (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 200, description = "Updated profile", body = Profile),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[put("/user/profile", format = "application/json", data = "<profile>")]
+#[tracing::instrument(skip(profile))]
+pub async fn profile_update(
+    profile: ValidJson<Profile>,
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<Profile>, Problem> {
+    let profile = profile.into_inner();
+
+    if !db.update_profile(auth.user, &profile).await? {
+        return Err(user_problem::not_found(auth.user));
+    }
+
+    Ok(Json(profile))
+}
+
+/// Get settings of the current user
+#[utoipa::path(
+    responses(
+        (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 200, description = "User settings", body = UserSettings),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[get("/user/settings")]
+#[tracing::instrument]
+pub async fn settings_get(
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<UserSettings>, Problem> {
+    db.get_user_settings(auth.user).await.map(Json)
+}
+
+/// Update settings of the current user
+#[utoipa::path(
+    request_body = UserSettings,
+    responses(
+        (status = 400, description = "Locale isn't supported", body = Problem),
+        (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 200, description = "Updated user settings", body = UserSettings),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[put("/user/settings", format = "application/json", data = "<settings>")]
+#[tracing::instrument]
+pub async fn settings_update(
+    settings: ValidJson<UserSettings>,
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<UserSettings>, Problem> {
+    let settings = settings.into_inner();
+    db.save_user_settings(auth.user, &settings).await?;
+
+    Ok(Json(settings))
+}
@@ -1,44 +1,85 @@
 use std::time::Duration;
 
+use crate::data::quiz::attempt::{AttemptRepository, QuizAttempt};
+use crate::data::quiz::db::QuizRepository;
 use crate::data::quiz::{Quiz, QuizAnswers, ValidationResult, QUIZ_COLLECTION_NAME};
-use crate::resp::jwt::{auth_problem, UserRoleToken};
+use crate::data::store::Store;
+use crate::data::tag::TagDbExt;
+use crate::data::user::db::{problem as user_problem, UserRepository};
+use crate::data::user::profile::{normalize_tags, Tag};
+use crate::data::{date, id};
+use crate::middleware::metrics::Metrics;
+use crate::middleware::paging::{Page, PageState};
+use crate::resp::catalog::ProblemType;
+use crate::resp::jwt::{role_problem, UserRoleToken};
 use crate::resp::problem::Problem;
+use crate::resp::validation::ValidJson;
 use crate::role::Role;
-use bson::spec::BinarySubtype;
-use bson::{doc, from_bson, Bson, Document};
+use crate::settings::Settings;
+use crate::util::escape_regex;
+use bson::{doc, Document};
 use chrono::{DateTime, Utc};
-use mongodb::Database;
-use rocket::futures::StreamExt;
-use rocket::http::Status;
 use rocket::serde::json::Json;
 use rocket::State;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use utoipa::openapi::{RefOr, Schema};
 use utoipa::ToSchema;
 use uuid::Uuid;
 
 // pub static PART_COLLECTION_NAME: &'static str = "parts";
 // pub static PARTICIPANT_COLLECTION_NAME: &'static str = "participants";
 
-#[derive(Debug, Serialize, ToSchema)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct QuizListResponse {
+    #[serde(alias = "_id", with = "crate::data::id::binary")]
     pub id: Uuid,
     pub name: String,
     #[serde(default)]
     pub desc: String,
-    #[serde(
-        default = "Uuid::new_v4",
-        with = "bson::serde_helpers::uuid_1_as_binary"
-    )] // TODO: Remove default
+    #[serde(default = "Uuid::new_v4", with = "crate::data::id::binary")] // TODO: Remove default
     pub author: Uuid,
-    #[serde(default = "Utc::now")]
+    #[serde(default = "Utc::now", with = "crate::data::date::rfc3339")]
     pub created: DateTime<Utc>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
 
+    #[serde(default)]
     pub time_limit: Option<Duration>,
 
+    #[serde(default, with = "crate::data::date::rfc3339_option")]
     pub open_on: Option<DateTime<Utc>>,
+    #[serde(default, with = "crate::data::date::rfc3339_option")]
     pub close_on: Option<DateTime<Utc>>,
 }
 
+impl QuizListResponse {
+    /// Quiz document fields needed to build a list entry.
+    pub fn projection() -> Document {
+        doc! {
+            "name": 1,
+            "desc": 1,
+            "author": 1,
+            "created": 1,
+            "tags": 1,
+            "time_limit": 1,
+            "open_on": 1,
+            "close_on": 1,
+        }
+    }
+}
+
+/// Page of quiz list entries.
+pub type QuizPage = Page<QuizListResponse>;
+
+impl<'s> ToSchema<'s> for QuizPage {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        (
+            "QuizPage",
+            Page::<QuizListResponse>::schema_of("QuizListResponse"),
+        )
+    }
+}
+
 impl From<Quiz> for QuizListResponse {
     fn from(value: Quiz) -> Self {
         Self {
@@ -47,6 +88,7 @@ impl From<Quiz> for QuizListResponse {
             desc: value.desc,
             author: value.author,
             created: value.created,
+            tags: value.tags,
             time_limit: value.time_limit,
             open_on: value.open_on,
             close_on: value.close_on,
@@ -54,73 +96,197 @@ impl From<Quiz> for QuizListResponse {
     }
 }
 
-// TODO: Quiz list paging
+/// Whether a quiz accepts answers at the time of listing.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, FromFormField)]
+pub enum QuizStatus {
+    /// Quiz is past `open_on` and before `close_on`
+    Open,
+    /// Quiz is past `close_on`
+    Closed,
+    /// Quiz is before `open_on`
+    Upcoming,
+}
+
+impl QuizStatus {
+    fn filter(self, now: DateTime<Utc>) -> Document {
+        let now = date::to_bson(&now);
+        match self {
+            QuizStatus::Open => doc! {
+                "$and": [
+                    { "$or": [{ "open_on": null }, { "open_on": { "$lte": now.clone() } }] },
+                    { "$or": [{ "close_on": null }, { "close_on": { "$gt": now } }] },
+                ]
+            },
+            QuizStatus::Closed => doc! { "close_on": { "$lte": now } },
+            QuizStatus::Upcoming => doc! { "open_on": { "$gt": now } },
+        }
+    }
+}
+
+fn parse_date(name: &str, value: &str) -> Result<DateTime<Utc>, Problem> {
+    date::parse(value).ok_or_else(|| {
+        Problem::typed(ProblemType::RequestBadParameter)
+            .detail("'{parameter}' must be an RFC 3339 date, e.g. 2023-01-31T12:00:00Z")
+            .arg("parameter", name)
+            .insert_str("parameter", name)
+            .to_owned()
+    })
+}
+
+#[derive(Debug, Clone, FromForm)]
+pub struct QuizListQuery {
+    pub author: Option<Uuid>,
+    pub status: Option<QuizStatus>,
+    pub tag: Option<String>,
+    pub created_after: Option<String>,
+    pub created_before: Option<String>,
+    pub search: Option<String>,
+}
+
+impl QuizListQuery {
+    fn filter(&self) -> Result<Document, Problem> {
+        let mut conditions = vec![];
+
+        if let Some(author) = self.author {
+            conditions.push(id::eq("author", author));
+        }
+        if let Some(status) = self.status {
+            conditions.push(status.filter(Utc::now()));
+        }
+        if let Some(tag) = self.tag.as_deref() {
+            conditions.push(doc! { "tags": Tag::from(tag).normalized().as_ref() });
+        }
+
+        let mut created = Document::new();
+        if let Some(after) = self.created_after.as_deref() {
+            created.insert("$gte", date::to_bson(&parse_date("created_after", after)?));
+        }
+        if let Some(before) = self.created_before.as_deref() {
+            created.insert("$lt", date::to_bson(&parse_date("created_before", before)?));
+        }
+        if !created.is_empty() {
+            conditions.push(doc! { "created": created });
+        }
+
+        if let Some(search) = self.search.as_deref().map(str::trim) {
+            if !search.is_empty() {
+                let pattern = doc! { "$regex": escape_regex(search), "$options": "i" };
+                conditions.push(doc! {
+                    "$or": [{ "name": pattern.clone() }, { "desc": pattern }]
+                });
+            }
+        }
+
+        Ok(match conditions.len() {
+            0 => Document::new(),
+            1 => conditions.remove(0),
+            _ => doc! { "$and": conditions },
+        })
+    }
+}
 
-/// List all quiz documents
+/// List quizzes
+///
+/// Only public quizzes and quizzes authored by the caller are listed. Quizzes are listed in order
+/// of their ids, which is unrelated to when they were created.
 #[utoipa::path(
+    params(
+        ("author" = Option<Uuid>, Query, description = "Only list quizzes created by user"),
+        ("status" = Option<String>, Query, description = "Only list 'open', 'closed' or 'upcoming' quizzes"),
+        ("tag" = Option<String>, Query, description = "Only list quizzes with tag"),
+        ("created_after" = Option<String>, Query, description = "Only list quizzes created at or after RFC 3339 date"),
+        ("created_before" = Option<String>, Query, description = "Only list quizzes created before RFC 3339 date"),
+        ("search" = Option<String>, Query, description = "Text contained in quiz name or description"),
+        ("length" = Option<u8>, Query, description = "Page length"),
+        ("from" = Option<String>, Query, description = "Cursor of the next page"),
+        ("before" = Option<String>, Query, description = "Cursor of the previous page"),
+    ),
     responses(
-        (status = 200, description = "List of quizes", body = Vec<QuizListResponse>),
+        (status = 400, description = "Invalid filter value", body = Problem),
+        (status = 200, description = "Page of quizzes", body = QuizPage),
     )
 )]
-#[get("/quiz")]
-#[tracing::instrument]
+#[get("/quiz?<query..>")]
 pub async fn quiz_list(
-    db: &State<Database>,
-    user: Option<UserRoleToken>,
-) -> Result<Json<Vec<QuizListResponse>>, Problem> {
-    let mut documents = db
-        .collection(QUIZ_COLLECTION_NAME)
-        .find(None, None)
+    query: QuizListQuery,
+    auth: Option<UserRoleToken>,
+    paging: PageState<'_, Uuid>,
+    db: &State<Store>,
+) -> Result<Page<QuizListResponse>, Problem> {
+    let visible = Quiz::visible_to(auth.map(|it| it.user));
+    let filter = match query.filter()? {
+        filter if filter.is_empty() => visible,
+        filter => doc! { "$and": [filter, visible] },
+    };
+
+    paging
+        .page_over(QUIZ_COLLECTION_NAME, "_id")
+        .filter(filter)
+        .projection(QuizListResponse::projection())
+        .entries(db)
         .await
-        .expect("unable to list quizzes");
+}
 
-    let mut quizzes: Vec<QuizListResponse> = vec![];
+const RECOMMENDATION_COUNT: i64 = 20;
 
-    while let Some(user_result) = documents.next().await {
-        let quiz_document = Bson::Document(user_result.unwrap());
-        match from_bson::<Quiz>(quiz_document) {
-            Ok(quiz) => quizzes.push(quiz.into()),
-            Err(_) => {
-                warn!("Unable to deserialize Quiz document.")
-            }
-        }
-    }
+/// Recommend quizzes based on interests of the current user
+///
+/// Lists public quizzes tagged with the interests from user profile, quizzes matching more
+/// interests are listed first.
+#[utoipa::path(
+    responses(
+        (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 200, description = "Recommended quizzes", body = Vec<QuizListResponse>),
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[get("/quiz/recommended")]
+#[tracing::instrument]
+pub async fn quiz_recommended(
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<Vec<QuizListResponse>>, Problem> {
+    let user = db
+        .get_user(auth.user)
+        .await?
+        .ok_or_else(|| user_problem::not_found(auth.user))?;
+    let interests = normalize_tags(user.profile.interests.tags());
 
-    Ok(Json(quizzes))
+    db.recommended_quizzes(
+        auth.user,
+        &interests,
+        QuizListResponse::projection(),
+        RECOMMENDATION_COUNT,
+    )
+    .await
+    .map(Json)
 }
 
 /// Create a quiz
-#[utoipa::path(request_body = Quiz)]
+#[utoipa::path(
+    request_body = Quiz,
+    responses(
+        (status = 400, description = "Quiz has invalid fields", body = Problem),
+        (status = 401, description = "Missing/expired token or insufficient privileges", body = Problem),
+        (status = 200, description = "Quiz created"),
+    )
+)]
 #[post("/quiz", format = "application/json", data = "<quiz>")]
 #[tracing::instrument]
 pub async fn quiz_create(
-    quiz: Json<Quiz>,
+    quiz: ValidJson<Quiz>,
     auth: UserRoleToken,
-    db: &State<Database>,
+    db: &State<Store>,
 ) -> Result<(), Problem> {
     if auth.role < Role::Author {
-        return Err(auth_problem("Permission level too low."));
+        return Err(role_problem("Permission level too low."));
     }
+    let mut quiz = quiz.into_inner();
+    quiz.normalize_tags();
 
-    db.collection(QUIZ_COLLECTION_NAME)
-        .insert_one(
-            bson::to_document(&quiz.0).expect("Unable to serialize Quiz struct into BSON"),
-            None,
-        )
-        .await
-        .map_err(|e| Problem::from(e))?;
-
-    Ok(())
-}
-
-#[inline]
-pub fn quiz_id_filter(id: Uuid) -> Document {
-    doc! {
-        "_id": Bson::Binary(bson::Binary {
-            subtype: BinarySubtype::Uuid,
-            bytes: id.as_bytes().to_vec(),
-        })
-    }
+    db.create_quiz(&quiz).await
 }
 
 /// Get quiz information
@@ -136,19 +302,8 @@ pub fn quiz_id_filter(id: Uuid) -> Document {
 )]
 #[get("/quiz/<id>")]
 #[tracing::instrument]
-pub async fn quiz_info(id: Uuid, db: &State<Database>) -> Result<Option<Json<Quiz>>, Problem> {
-    let quiz_document = db
-        .collection(QUIZ_COLLECTION_NAME)
-        .find_one(quiz_id_filter(id), None)
-        .await
-        .expect("Unable to query by id");
-
-    let quiz: Option<Quiz> = match quiz_document {
-        Some(doc) => Some(from_bson(Bson::Document(doc)).map_err(|e| Problem::from(e))?),
-        None => None,
-    };
-
-    Ok(quiz.map(|u| Json(u)))
+pub async fn quiz_info(id: Uuid, db: &State<Store>) -> Result<Option<Json<Quiz>>, Problem> {
+    db.get_quiz(id).await.map(|it| it.map(Json))
 }
 
 /// Delete a quiz
@@ -170,68 +325,309 @@ pub async fn quiz_info(id: Uuid, db: &State<Database>) -> Result<Option<Json<Qui
 pub async fn quiz_delete(
     id: Uuid,
     auth: UserRoleToken,
-    db: &State<Database>,
+    db: &State<Store>,
 ) -> Result<Option<String>, Problem> {
-    let quiz_document = db
-        .collection(QUIZ_COLLECTION_NAME)
-        .find_one(quiz_id_filter(id), None)
-        .await
-        .expect("Unable to query by id");
-
-    let quiz: Quiz = match quiz_document {
-        Some(doc) => from_bson(Bson::Document(doc)).map_err(|e| Problem::from(e))?,
+    let quiz = match db.get_quiz(id).await? {
+        Some(it) => it,
         None => return Ok(None),
     };
 
     if auth.role < Role::Admin && quiz.author != auth.user {
-        return Err(auth_problem("Quiz not owned by user."));
+        return Err(Problem::typed(ProblemType::AuthNotOwner)
+            .detail("Quiz not owned by user.")
+            .to_owned());
     }
 
-    db.collection::<Quiz>(QUIZ_COLLECTION_NAME)
-        .delete_one(quiz_id_filter(id), None)
-        .await
-        .map_err(|e| Problem::from(e))?;
+    db.delete_quiz(id).await?;
 
     Ok(Some(id.to_string()))
 }
 
 /// Submit quiz answers
+///
+/// Answers are graded and the result is recorded as an attempt of the current user.
 #[utoipa::path(
     request_body(content = QuizAnswers, content_type="application/json"),
     responses(
         (status = 200, description = "Validation results", body = ValidationResult),
         (status = 400, description = "Provided invalid answers", body = Problem),
+        (status = 404, description = "Quiz for provided answers doesn't exist", body = Problem),
         (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 403, description = "Email verification is required and user email isn't verified", body = Problem),
     ),
     security(
         ("jwt" = [])
     )
 )]
 #[post("/quiz/<id>", format = "application/json", data = "<answers>")]
-#[tracing::instrument]
+#[tracing::instrument(skip(answers))]
 pub async fn quiz_submit_answers(
     id: Uuid,
     answers: Json<QuizAnswers>,
     auth: UserRoleToken,
-    db: &State<Database>,
+    db: &State<Store>,
+    settings: &State<Settings>,
+    metrics: &State<Metrics>,
 ) -> Result<Json<ValidationResult>, Problem> {
+    let _submission = metrics.submission_started();
     let answer = answers.0;
 
-    let quiz_document = db
-        .collection(QUIZ_COLLECTION_NAME)
-        .find_one(quiz_id_filter(id), None)
-        .await
-        .expect("Unable to query by id");
+    if settings.auth.require_email_verification {
+        let user = db
+            .get_user(auth.user)
+            .await?
+            .ok_or_else(|| user_problem::not_found(auth.user))?;
 
-    let quiz: Quiz = match quiz_document {
-        Some(doc) => from_bson(Bson::Document(doc)).map_err(|e| Problem::from(e))?,
-        None => {
-            return Err(Problem::new_untyped(
-                Status::BadRequest,
-                "Quiz for provided answers doesn't exist.",
-            ))
+        if !user.email_verified {
+            return Err(user_problem::email_not_verified());
         }
-    };
+    }
+
+    let quiz = db.get_quiz(id).await?.ok_or_else(|| {
+        Problem::typed(ProblemType::QuizNotFound)
+            .insert("id", id.to_string())
+            .to_owned()
+    })?;
+
+    let result = metrics.time_grading(|| answer.validate(&quiz));
+    db.record_attempt(&QuizAttempt::new(quiz.id, auth.user, result.clone()))
+        .await?;
 
-    Ok(Json(answer.validate(&quiz)))
+    Ok(Json(result))
+}
+
+#[cfg(all(test, feature = "generate-security"))]
+mod tests {
+    use rocket::http::Status;
+    use serde_json::Value;
+
+    use super::*;
+    use crate::data::user::profile::{Interests, Profile};
+    use crate::route::testing::{create_user, test_client, test_quiz};
+
+    fn names(list: &Value) -> Vec<&str> {
+        list.as_array()
+            .expect("not a list")
+            .iter()
+            .map(|it| it["name"].as_str().expect("quiz without name"))
+            .collect()
+    }
+
+    #[rocket::async_test]
+    async fn v1_quiz_list_pages_through_quizzes() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let author = Uuid::new_v4();
+        for name in ["first", "second", "third"] {
+            db.create_quiz(&test_quiz(name, author, &[]))
+                .await
+                .expect("unable to create test quiz");
+        }
+
+        let response = client.get("/api/v1/quiz?length=2").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let first: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(names(&first["items"]).len(), 2);
+        let next = first["next"].as_str().expect("missing next page cursor");
+
+        let response = client
+            .get(format!("/api/v1/quiz?length=2&from={}", next))
+            .dispatch()
+            .await;
+        let second: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(names(&second["items"]).len(), 1);
+        assert!(second["next"].is_null());
+
+        let mut all = names(&first["items"]);
+        all.extend(names(&second["items"]));
+        all.sort_unstable();
+        assert_eq!(all, ["first", "second", "third"]);
+    }
+
+    /// Sorted names of quizzes listed with `query`.
+    async fn listed(client: &rocket::local::asynchronous::Client, query: &str) -> Vec<String> {
+        let response = client
+            .get(format!("/api/v1/quiz?{}", query))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok, "listing with '{}'", query);
+        let page: Value = response.into_json().await.expect("invalid response json");
+        let mut result: Vec<String> = names(&page["items"])
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        result.sort_unstable();
+        result
+    }
+
+    #[rocket::async_test]
+    async fn v1_quiz_list_filters_quizzes() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let (author, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let now = Utc::now();
+        let date = |value: &str| date::parse(value).unwrap();
+
+        let mut open = test_quiz("open", author, &["biology"]);
+        open.open_on = Some(now - chrono::Duration::days(1));
+        open.close_on = Some(now + chrono::Duration::days(1));
+        open.created = date("2023-01-10T08:00:00Z");
+        let mut closed = test_quiz("closed", author, &["physics"]);
+        closed.close_on = Some(now - chrono::Duration::hours(1));
+        closed.created = date("2023-01-31T12:00:00Z");
+        let mut upcoming = test_quiz("upcoming", other, &["biology", "cells"]);
+        upcoming.open_on = Some(now + chrono::Duration::hours(1));
+        upcoming.created = date("2023-02-01T00:00:00.5Z");
+        let mut always = test_quiz("always", other, &[]);
+        always.created = date("2023-03-01T00:00:00+02:00");
+        for quiz in [&open, &closed, &upcoming, &always] {
+            db.create_quiz(quiz)
+                .await
+                .expect("unable to create test quiz");
+        }
+
+        assert_eq!(listed(&client, "status=open").await, ["always", "open"]);
+        assert_eq!(listed(&client, "status=closed").await, ["closed"]);
+        assert_eq!(listed(&client, "status=upcoming").await, ["upcoming"]);
+        assert_eq!(
+            listed(&client, "tag=%20Biology").await,
+            ["open", "upcoming"]
+        );
+        assert_eq!(
+            listed(&client, &format!("author={}", other)).await,
+            ["always", "upcoming"]
+        );
+        assert_eq!(
+            listed(&client, &format!("author={}&tag=cells", other)).await,
+            ["upcoming"]
+        );
+        assert_eq!(
+            listed(
+                &client,
+                "created_after=2023-01-31T12:00:00Z&created_before=2023-02-28T23:00:00%2B01:00"
+            )
+            .await,
+            ["closed", "upcoming"]
+        );
+        assert_eq!(
+            listed(&client, "created_before=2023-01-31T12:00:00.001Z").await,
+            ["closed", "open"]
+        );
+
+        let response = client
+            .get("/api/v1/quiz?created_after=yesterday")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[rocket::async_test]
+    async fn v1_quiz_list_hides_private_quizzes() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let (author, author_cookie) =
+            create_user(&client, "v1_quiz_list_author", Role::Normal).await;
+        let (_, other_cookie) = create_user(&client, "v1_quiz_list_other", Role::Normal).await;
+
+        let mut private = test_quiz("private", author.id, &["secret"]);
+        private.public = false;
+        for quiz in [&private, &test_quiz("public", author.id, &["secret"])] {
+            db.create_quiz(quiz)
+                .await
+                .expect("unable to create test quiz");
+        }
+
+        assert_eq!(listed(&client, "tag=secret").await, ["public"]);
+        assert!(listed(&client, "search=private").await.is_empty());
+
+        for (cookie, expected) in [
+            (other_cookie, vec!["public"]),
+            (author_cookie, vec!["private", "public"]),
+        ] {
+            let response = client
+                .get("/api/v1/quiz?tag=secret")
+                .cookie(cookie)
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok);
+            let page: Value = response.into_json().await.expect("invalid response json");
+            let mut listed = names(&page["items"]);
+            listed.sort_unstable();
+            assert_eq!(listed, expected);
+        }
+    }
+
+    #[rocket::async_test]
+    async fn v1_quiz_recommended_matches_interests() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let (user, cookie) = create_user(&client, "v1_quiz_recommended", Role::Normal).await;
+        let profile = Profile {
+            interests: Interests::from(vec![Tag::from("Biology")]),
+            ..Default::default()
+        };
+        db.update_profile(user.id, &profile)
+            .await
+            .expect("unable to update test profile");
+
+        let other = Uuid::new_v4();
+        for quiz in [
+            test_quiz("cells", other, &["biology"]),
+            test_quiz("own", user.id, &["biology"]),
+            test_quiz("forces", other, &["physics"]),
+        ] {
+            db.create_quiz(&quiz)
+                .await
+                .expect("unable to create test quiz");
+        }
+
+        let response = client
+            .get("/api/v1/quiz/recommended")
+            .cookie(cookie)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let quizzes: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(names(&quizzes), ["cells"]);
+    }
+
+    #[rocket::async_test]
+    async fn v1_quiz_recommended_prefers_more_interests_then_newer() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let (user, cookie) = create_user(&client, "v1_quiz_recommended_order", Role::Normal).await;
+        let interests = vec![Tag::from("biology"), Tag::from("cells")];
+        let profile = Profile {
+            interests: Interests::from(interests),
+            ..Default::default()
+        };
+        db.update_profile(user.id, &profile)
+            .await
+            .expect("unable to update test profile");
+
+        let other = Uuid::new_v4();
+        let now = Utc::now();
+        let mut older_both = test_quiz("older both", other, &["cells", "biology"]);
+        older_both.created = now - chrono::Duration::days(3);
+        let mut newer_both = test_quiz("newer both", other, &["biology", "cells", "physics"]);
+        newer_both.created = now - chrono::Duration::days(2);
+        let mut newest_one = test_quiz("newest one", other, &["biology"]);
+        newest_one.created = now - chrono::Duration::days(1);
+        let mut private = test_quiz("private both", other, &["biology", "cells"]);
+        private.public = false;
+        for quiz in [&newest_one, &older_both, &private, &newer_both] {
+            db.create_quiz(quiz)
+                .await
+                .expect("unable to create test quiz");
+        }
+
+        let response = client
+            .get("/api/v1/quiz/recommended")
+            .cookie(cookie)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let quizzes: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(names(&quizzes), ["newer both", "older both", "newest one"]);
+    }
 }
@@ -0,0 +1,183 @@
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::data::search::{SearchDbExt, SearchResults};
+use crate::data::store::Store;
+use crate::resp::catalog::ProblemType;
+use crate::resp::jwt::UserRoleToken;
+use crate::resp::problem::Problem;
+use crate::role::Role;
+
+const DEFAULT_LIMIT: u8 = 10;
+const MAX_LIMIT: u8 = 50;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, FromFormField)]
+pub enum SearchKind {
+    Quiz,
+    Class,
+    User,
+}
+
+#[derive(Debug, Clone, FromForm)]
+pub struct SearchQuery {
+    pub q: String,
+    pub kind: Option<SearchKind>,
+    pub limit: Option<u8>,
+}
+
+/// Search quizzes, classes and users
+///
+/// Results are ranked by relevance and contain snippets of matched text. Only public quizzes and
+/// quizzes authored by the caller are searched, classes are limited to the ones the caller
+/// participates in and users can only be searched by members.
+#[utoipa::path(
+    params(
+        ("q" = String, Query, description = "Search terms"),
+        ("kind" = Option<String>, Query, description = "Only search 'quiz', 'class' or 'user' entries"),
+        ("limit" = Option<u8>, Query, description = "Maximum number of results of each kind"),
+    ),
+    responses(
+        (status = 400, description = "Missing search terms", body = Problem),
+        (status = 200, description = "Matching entries, most relevant first", body = SearchResults),
+    )
+)]
+#[get("/search?<query..>")]
+#[tracing::instrument]
+pub async fn search_all(
+    query: SearchQuery,
+    auth: Option<UserRoleToken>,
+    db: &State<Store>,
+) -> Result<Json<SearchResults>, Problem> {
+    let terms = query.q.trim();
+    if terms.is_empty() {
+        return Err(Problem::typed(ProblemType::RequestBadParameter)
+            .detail("Missing search terms.")
+            .insert_str("parameter", "q")
+            .to_owned());
+    }
+
+    let limit = match query.limit {
+        Some(0) | None => DEFAULT_LIMIT,
+        Some(it) => it.min(MAX_LIMIT),
+    } as i64;
+    let includes = |kind: SearchKind| query.kind.map(|it| it == kind).unwrap_or(true);
+
+    let mut results = SearchResults::default();
+    if includes(SearchKind::Quiz) {
+        let viewer = auth.as_ref().map(|it| it.user);
+        results.quizzes = db.search_quizzes(terms, viewer, limit).await?;
+    }
+    if let Some(auth) = &auth {
+        if includes(SearchKind::Class) {
+            results.classes = db.search_classes(terms, auth.user, limit).await?;
+        }
+        if includes(SearchKind::User) && auth.role >= Role::Normal {
+            results.users = db.search_users(terms, limit).await?;
+        }
+    }
+
+    Ok(Json(results))
+}
+
+#[cfg(all(test, feature = "generate-security"))]
+mod tests {
+    use rocket::http::Status;
+    use serde_json::Value;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::data::class::db::ClassRepository;
+    use crate::data::class::Class;
+    use crate::data::quiz::db::QuizRepository;
+    use crate::route::testing::{create_user, test_client, test_quiz};
+
+    fn titles(hits: &Value) -> Vec<&str> {
+        hits.as_array()
+            .expect("not a list of hits")
+            .iter()
+            .map(|it| it["title"].as_str().expect("hit without title"))
+            .collect()
+    }
+
+    #[rocket::async_test]
+    async fn v1_search_finds_quizzes_classes_and_users() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let (user, cookie) = create_user(&client, "v1_search_photo", Role::Normal).await;
+
+        let mut quiz = test_quiz("Photosynthesis basics", Uuid::new_v4(), &[]);
+        quiz.desc = "How plants turn light into energy.".to_string();
+        db.create_quiz(&quiz)
+            .await
+            .expect("unable to create test quiz");
+        db.create_quiz(&test_quiz("Forces", Uuid::new_v4(), &[]))
+            .await
+            .expect("unable to create test quiz");
+        db.create_class(&Class::new("Photography club", vec![], user.id))
+            .await
+            .expect("unable to create test class");
+
+        let response = client
+            .get("/api/v1/search?q=PHOTO")
+            .cookie(cookie)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok);
+        let results: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(titles(&results["quizzes"]), ["Photosynthesis basics"]);
+        assert_eq!(titles(&results["classes"]), ["Photography club"]);
+        assert_eq!(titles(&results["users"]), ["v1_search_photo"]);
+        assert_eq!(
+            results["quizzes"][0]["snippets"][0]["highlights"],
+            serde_json::json!([[0, 5]])
+        );
+
+        let response = client.get("/api/v1/search?q=%20").dispatch().await;
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[rocket::async_test]
+    async fn v1_search_respects_visibility() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let (author, author_cookie) = create_user(&client, "v1_search_author", Role::Author).await;
+        let (_, other_cookie) = create_user(&client, "v1_search_other", Role::Normal).await;
+
+        let mut private = test_quiz("Private genetics", author.id, &[]);
+        private.public = false;
+        db.create_quiz(&private)
+            .await
+            .expect("unable to create test quiz");
+        db.create_quiz(&test_quiz("Public genetics", Uuid::new_v4(), &[]))
+            .await
+            .expect("unable to create test quiz");
+        db.create_class(&Class::new("Genetics class", vec![], author.id))
+            .await
+            .expect("unable to create test class");
+
+        let response = client
+            .get("/api/v1/search?q=genetics")
+            .cookie(author_cookie)
+            .dispatch()
+            .await;
+        let results: Value = response.into_json().await.expect("invalid response json");
+        let mut quizzes = titles(&results["quizzes"]);
+        quizzes.sort_unstable();
+        assert_eq!(quizzes, ["Private genetics", "Public genetics"]);
+        assert_eq!(titles(&results["classes"]), ["Genetics class"]);
+
+        let response = client
+            .get("/api/v1/search?q=genetics")
+            .cookie(other_cookie)
+            .dispatch()
+            .await;
+        let results: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(titles(&results["quizzes"]), ["Public genetics"]);
+        assert!(titles(&results["classes"]).is_empty());
+
+        let response = client.get("/api/v1/search?q=v1_search").dispatch().await;
+        let results: Value = response.into_json().await.expect("invalid response json");
+        assert!(titles(&results["users"]).is_empty());
+        assert!(titles(&results["classes"]).is_empty());
+    }
+}
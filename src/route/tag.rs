@@ -0,0 +1,116 @@
+use rocket::serde::json::Json;
+use rocket::State;
+
+use crate::data::store::Store;
+use crate::data::tag::{TagDbExt, TagUsage};
+use crate::resp::jwt::UserRoleToken;
+use crate::resp::problem::Problem;
+
+/// List used tags
+///
+/// Classes are private to their participants, so only classes of the caller are counted.
+#[utoipa::path(
+    responses(
+        (status = 200, description = "Tags with number of public quizzes and caller's classes using them, most used first", body = Vec<TagUsage>),
+    )
+)]
+#[get("/tag")]
+#[tracing::instrument]
+pub async fn tag_list(
+    auth: Option<UserRoleToken>,
+    db: &State<Store>,
+) -> Result<Json<Vec<TagUsage>>, Problem> {
+    db.tag_usage(auth.map(|it| it.user)).await.map(Json)
+}
+
+#[cfg(all(test, feature = "generate-security"))]
+mod tests {
+    use rocket::http::Status;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::data::class::db::ClassRepository;
+    use crate::data::class::Class;
+    use crate::data::quiz::db::QuizRepository;
+    use crate::data::user::profile::Tag;
+    use crate::role::Role;
+    use crate::route::testing::{create_user, test_client, test_quiz};
+
+    #[rocket::async_test]
+    async fn v1_tag_list_counts_quiz_tags() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let author = Uuid::new_v4();
+        for tags in [&["biology", "cells"][..], &["biology"], &["physics"]] {
+            db.create_quiz(&test_quiz("tagged", author, tags))
+                .await
+                .expect("unable to create test quiz");
+        }
+
+        let response = client.get("/api/v1/tag").dispatch().await;
+        assert_eq!(response.status(), Status::Ok);
+        let usage: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(
+            usage[0],
+            json!({ "tag": "biology", "quizzes": 2, "classes": 0 })
+        );
+        let mut rest: Vec<&str> = usage.as_array().unwrap()[1..]
+            .iter()
+            .map(|it| it["tag"].as_str().unwrap())
+            .collect();
+        rest.sort_unstable();
+        assert_eq!(rest, ["cells", "physics"]);
+    }
+
+    #[rocket::async_test]
+    async fn v1_tag_list_hides_private_quizzes_and_other_classes() {
+        let client = test_client().await;
+        let db: &Store = client.rocket().state().unwrap();
+        let (teacher, teacher_cookie) = create_user(&client, "v1_tag_teacher", Role::Author).await;
+        let (_, other_cookie) = create_user(&client, "v1_tag_other", Role::Normal).await;
+
+        let mut private = test_quiz("private", teacher.id, &["secret", "biology"]);
+        private.public = false;
+        db.create_quiz(&private)
+            .await
+            .expect("unable to create test quiz");
+        db.create_quiz(&test_quiz("public", teacher.id, &["biology"]))
+            .await
+            .expect("unable to create test quiz");
+        let tags = vec![Tag::from("biology"), Tag::from("lab")];
+        db.create_class(&Class::new("Biology lab", tags, teacher.id))
+            .await
+            .expect("unable to create test class");
+
+        let response = client
+            .get("/api/v1/tag")
+            .cookie(teacher_cookie)
+            .dispatch()
+            .await;
+        let usage: Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(
+            usage,
+            json!([
+                { "tag": "biology", "quizzes": 1, "classes": 1 },
+                { "tag": "lab", "quizzes": 0, "classes": 1 },
+            ])
+        );
+
+        for request in [
+            client.get("/api/v1/tag").cookie(other_cookie),
+            client.get("/api/v1/tag"),
+        ] {
+            let usage: Value = request
+                .dispatch()
+                .await
+                .into_json()
+                .await
+                .expect("invalid response json");
+            assert_eq!(
+                usage,
+                json!([{ "tag": "biology", "quizzes": 1, "classes": 0 }])
+            );
+        }
+    }
+}
@@ -0,0 +1,81 @@
+//! Helpers shared by route tests.
+
+use std::borrow::Cow;
+use std::sync::OnceLock;
+
+use chrono::Utc;
+use rocket::http::Cookie;
+use rocket::local::asynchronous::Client;
+use uuid::Uuid;
+
+use crate::data::quiz::Quiz;
+use crate::data::store::Store;
+use crate::data::user::db::{UserRepository, UserSignupData};
+use crate::data::user::profile::Tag;
+use crate::data::user::User;
+use crate::resp::jwt::UserRoleToken;
+use crate::role::Role;
+use crate::security::Security;
+use crate::settings::{CookieSettings, Settings};
+use crate::BackendBuilder;
+
+/// Keys are shared between tests as generating them is slow.
+pub fn test_security() -> Security {
+    static SECURITY: OnceLock<Security> = OnceLock::new();
+    SECURITY.get_or_init(Security::ephemeral).clone()
+}
+
+/// Client of a backend with default settings and an empty in-memory store.
+pub async fn test_client() -> Client {
+    let backend = BackendBuilder::new(Settings::default(), test_security(), Store::memory())
+        .build()
+        .expect("unable to build test backend");
+    Client::tracked(backend).await.expect("invalid backend")
+}
+
+/// Creates a user with `role` and returns it with its auth cookie.
+pub async fn create_user(client: &Client, username: &str, role: Role) -> (User, Cookie<'static>) {
+    let db: &Store = client.rocket().state().unwrap();
+    let security: &Security = client.rocket().state().unwrap();
+
+    let signup = UserSignupData {
+        email: Cow::Owned(format!("{}@example.com", username)),
+        username: Cow::Borrowed(username),
+        password: Cow::Borrowed("T3st#password"),
+    };
+    let (_, mut user) = db
+        .create_user(signup, &security.salt, &[])
+        .await
+        .expect("unable to create test user");
+    db.update_role(user.id, role)
+        .await
+        .expect("unable to set test user role");
+    user.user_role = role;
+
+    let cookie = UserRoleToken::new(&user)
+        .cookie(&security.jwt_keys.private, &CookieSettings::default())
+        .expect("unable to encode UserRoleToken cookie");
+    (user, cookie)
+}
+
+/// Public quiz without parts, created now.
+pub fn test_quiz(name: &str, author: Uuid, tags: &[&str]) -> Quiz {
+    Quiz {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        desc: String::new(),
+        author,
+        created: Utc::now(),
+        parts: vec![],
+        tags: tags.iter().map(Tag::from).collect(),
+        time_limit: None,
+        expect_focus: false,
+        show_answer: false,
+        show_results: true,
+        public: true,
+        open_on: None,
+        close_on: None,
+        begin_buffer: None,
+        participants: vec![],
+    }
+}
@@ -1,54 +1,124 @@
-use bson::doc;
-use mongodb::Database;
-use rocket::form::Form;
-use rocket::http::private::cookie::CookieBuilder;
-use rocket::http::{Cookie, CookieJar, Status};
+use bson::{doc, Document};
+use rocket::http::CookieJar;
 use rocket::serde::json::Json;
 use rocket::State;
 use utoipa::openapi::path::Parameter;
+use utoipa::openapi::{RefOr, Schema};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::data::store::Store;
+use crate::data::user::db::PasswordChangeData;
 use crate::data::user::db::{problem as user_problem, USER_COLLECTION_NAME};
-use crate::data::user::db::{CreateUserDbExt, UserLoginData, UserSignupData};
-use crate::data::user::{PasswordHash, User, UserResponse};
-use crate::middleware::paging::PageState;
-use crate::resp::jwt::{UserRoleToken, AUTH_COOKIE_NAME};
+use crate::data::user::db::{EmailChangeData, EmailVerificationData, UsernameChangeData};
+use crate::data::user::db::{PasswordResetData, PasswordResetRequestData};
+use crate::data::user::db::{UserLoginData, UserRepository, UserSignupData};
+use crate::data::user::policy::normalize_password;
+use crate::data::user::token::{email_verification_ttl, password_reset_ttl};
+use crate::data::user::token::{TokenPurpose, UserTokenDbExt};
+use crate::data::user::{PasswordHash, User, UserResponse, UserSummary};
+use crate::error::BackendError;
+use crate::mail::template::MailTemplate;
+use crate::mail::Mailer;
+use crate::middleware::metrics::Metrics;
+use crate::middleware::paging::{Page, PageState, SortOrder};
+use crate::resp::catalog::ProblemType;
+use crate::resp::jwt::{removal_cookie, role_problem, UserRoleToken};
 use crate::resp::problem::Problem;
+use crate::resp::validation::ValidForm;
 use crate::role::Role;
 use crate::security::Security;
 use crate::settings::Settings;
+use crate::util::escape_regex;
+
+/// Page of user summaries.
+pub type UserPage = Page<UserSummary>;
+
+impl<'s> ToSchema<'s> for UserPage {
+    fn schema() -> (&'s str, RefOr<Schema>) {
+        ("UserPage", Page::<UserSummary>::schema_of("UserSummary"))
+    }
+}
+
+#[derive(Debug, Clone, FromForm)]
+pub struct UserListQuery {
+    pub role: Option<Role>,
+    pub search: Option<String>,
+    pub order: Option<SortOrder>,
+}
 
 /// Get list of users
+///
+/// Email addresses and account status are only listed for admins.
 #[utoipa::path(
+    params(
+        ("role" = Option<Role>, Query, description = "Only list users with role"),
+        ("search" = Option<String>, Query, description = "Text contained in username, or email for admins"),
+        ("order" = Option<String>, Query, description = "Username sort order: 'asc' or 'desc'"),
+        ("length" = Option<u8>, Query, description = "Page length"),
+        ("from" = Option<String>, Query, description = "Cursor of the next page"),
+        ("before" = Option<String>, Query, description = "Cursor of the previous page"),
+    ),
     responses(
         (status = 401, description = "Missing/expired token", body = Problem),
-        (status = 200, description = "List of users", body = Vec<UserResponse>)
+        (status = 200, description = "Page of users, with total number of matching users", body = UserPage)
     ),
     security(
         ("jwt" = [])
     )
 )]
-#[get("/user")]
+#[get("/user?<query..>")]
 pub async fn user_list(
-    paging: PageState<'_, Uuid>,
+    query: UserListQuery,
+    paging: PageState<'_, String>,
     auth: UserRoleToken,
-    db: &State<Database>,
-) -> Result<Json<Vec<UserResponse>>, Problem> {
-    if auth.role > Role::Normal {
-        return Err(Problem::new_untyped(
-            Status::Unauthorized,
-            "can't list users",
-        ));
+    db: &State<Store>,
+) -> Result<Page<UserSummary>, Problem> {
+    if auth.role < Role::Normal {
+        return Err(role_problem("Only members can list users."));
+    }
+    let is_admin = auth.role >= Role::Admin;
+
+    let mut filter = Document::new();
+    if let Some(role) = query.role {
+        filter.insert(
+            "user_role",
+            bson::to_bson(&role).map_err(BackendError::from)?,
+        );
+    }
+    if let Some(search) = query.search.as_deref().map(str::trim) {
+        if !search.is_empty() {
+            let pattern = doc! { "$regex": escape_regex(search), "$options": "i" };
+            if is_admin {
+                filter.insert(
+                    "$or",
+                    vec![
+                        doc! { "username": pattern.clone() },
+                        doc! { "email": pattern },
+                    ],
+                );
+            } else {
+                filter.insert("username", pattern);
+            }
+        }
     }
 
-    let users = paging
-        .page_over(USER_COLLECTION_NAME, "_id")
+    let page = paging
+        .page_over(USER_COLLECTION_NAME, "username")
+        .filter(filter)
+        .order(query.order.unwrap_or_default());
+
+    let total = page.total(db).await?;
+    let users = page
         .entries(db)
         .await?
-        .into_iter()
-        .map(|it: User| it.into())
-        .collect();
-    Ok(Json(users))
+        .map(|it: User| match is_admin {
+            true => UserSummary::full(it),
+            false => UserSummary::public(it),
+        })
+        .with_total(total);
+
+    Ok(users)
 }
 
 /// Get information about the user
@@ -70,13 +140,10 @@ pub async fn user_list(
 pub async fn user_get(
     id: Uuid,
     auth: UserRoleToken,
-    db: &State<Database>,
+    db: &State<Store>,
 ) -> Result<Option<UserResponse>, Problem> {
     if auth.role < Role::Normal {
-        return Err(Problem::new_untyped(
-            Status::Unauthorized,
-            "only members can view other users",
-        ));
+        return Err(role_problem("Only members can view other users."));
     }
 
     db.get_user(id).await.map(|ok| ok.map(UserResponse::from))
@@ -86,29 +153,121 @@ pub async fn user_get(
 #[utoipa::path(
     request_body(content = UserSignupData<'_>, content_type="application/x-www-form-urlencoded"),
     responses(
+        (status = 400, description = "Invalid signup data, or email or username already used", body = Problem),
         (status = 401, description = "Missing/expired token", body = Problem),
         (status = 200, description = "User created", body = UserResponse)
     )
 )]
 #[post("/user", data = "<create_user>")]
-#[tracing::instrument]
+#[tracing::instrument(skip(cookies))]
 pub async fn user_create<'a>(
-    create_user: Form<UserSignupData<'_>>,
+    create_user: ValidForm<UserSignupData<'_>>,
     cookies: &'a CookieJar<'_>,
-    db: &State<Database>,
+    db: &State<Store>,
     c: &State<Settings>,
     security: &State<Security>,
+    mailer: &State<Mailer>,
 ) -> Result<Json<UserResponse>, Problem> {
-    create_user.validate()?;
-
     let (token, user) = db
-        .create_user(create_user.into_inner(), &security.salt, &c.admin_usernames)
+        .create_user(
+            create_user.into_inner(),
+            &security.salt,
+            &c.auth.admin_usernames,
+        )
         .await?;
-    cookies.add(token.cookie(&security.jwt_keys.private)?);
+    cookies.add(token.cookie(&security.jwt_keys.private, &c.auth.cookie)?);
+
+    // Account is usable without a verified email, user can request another mail later.
+    if let Err(err) = send_verification_mail(db, mailer, &user).await {
+        tracing::warn!("Unable to send verification mail to new user: {}", err);
+    }
 
     Ok(Json(UserResponse::from(user)))
 }
 
+async fn send_verification_mail(db: &Store, mailer: &Mailer, user: &User) -> Result<(), Problem> {
+    let token = db
+        .create_user_token(
+            user.id,
+            TokenPurpose::EmailVerification,
+            Some(user.email.clone()),
+            email_verification_ttl(),
+        )
+        .await?;
+
+    let link = mailer.link(format!("/verify-email?token={}", token));
+    mailer
+        .send_template(
+            &user.email,
+            MailTemplate::EmailVerification {
+                username: &user.username,
+                link: &link,
+                expires_in: email_verification_ttl(),
+            },
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Verify user email with a token sent by email
+#[utoipa::path(
+    request_body(content = EmailVerificationData, content_type="application/x-www-form-urlencoded"),
+    responses(
+        (status = 400, description = "Invalid or expired token", body = Problem),
+        (status = 200, description = "Email verified")
+    )
+)]
+#[post("/user/verify", data = "<verification>")]
+#[tracing::instrument]
+pub async fn user_verify_email(
+    verification: ValidForm<EmailVerificationData>,
+    db: &State<Store>,
+) -> Result<(), Problem> {
+    let token = db
+        .consume_user_token(&verification.token, TokenPurpose::EmailVerification)
+        .await?
+        .ok_or_else(user_problem::bad_token)?;
+
+    let email = token.email.ok_or_else(user_problem::bad_token)?;
+
+    // Token for an email that was changed since can't verify the new one
+    if !db.set_email_verified(token.user, email).await? {
+        return Err(user_problem::bad_token());
+    }
+
+    Ok(())
+}
+
+/// Send another verification mail to the current user
+#[utoipa::path(
+    responses(
+        (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 200, description = "Verification mail sent or email already verified")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[post("/user/verify/resend")]
+#[tracing::instrument]
+pub async fn user_verify_email_resend(
+    auth: UserRoleToken,
+    db: &State<Store>,
+    mailer: &State<Mailer>,
+) -> Result<(), Problem> {
+    let user = db
+        .get_user(auth.user)
+        .await?
+        .ok_or_else(|| user_problem::not_found(auth.user))?;
+
+    if user.email_verified {
+        return Ok(());
+    }
+
+    send_verification_mail(db, mailer, &user).await
+}
+
 // Logout current user
 #[utoipa::path(
     responses(
@@ -116,31 +275,32 @@ pub async fn user_create<'a>(
     )
 )]
 #[post("/user/logout")]
-#[tracing::instrument]
-pub async fn user_logout<'a>(cookies: &'a CookieJar<'_>) {
-    cookies.remove(Cookie::named(AUTH_COOKIE_NAME));
+#[tracing::instrument(skip(cookies))]
+pub async fn user_logout<'a>(cookies: &'a CookieJar<'_>, c: &State<Settings>) {
+    cookies.remove(removal_cookie(&c.auth.cookie));
 }
 
 /// Login via a login form
 #[utoipa::path(
     request_body(content = UserLoginData, content_type="application/x-www-form-urlencoded"),
     responses(
+        (status = 400, description = "Missing or malformed login information", body = Problem),
         (status = 401, description = "Bad login infomation", body = Problem),
         (status = 200, description = "Login user info and cookies", body = UserResponse)
     )
 )]
 #[post("/login", data = "<login_user>")]
-#[tracing::instrument]
+#[tracing::instrument(skip(cookies))]
 pub async fn login_submit<'a>(
-    login_user: Form<UserLoginData>,
+    login_user: ValidForm<UserLoginData>,
     cookies: &'a CookieJar<'_>,
-    db: &State<Database>,
+    db: &State<Store>,
+    c: &State<Settings>,
     security: &State<Security>,
+    metrics: &State<Metrics>,
 ) -> Result<UserResponse, Problem> {
     let is_email = login_user.is_email();
 
-    login_user.validate(is_email)?;
-
     // VULN: Prevent login_submit brute force attacks by checking login source
 
     let document = match is_email {
@@ -148,23 +308,248 @@ pub async fn login_submit<'a>(
         false => db.find_user_by_username(login_user.username.clone()).await,
     }?;
 
-    let user = document.ok_or_else(|| user_problem::bad_login(is_email))?;
+    let user = match document {
+        Some(user) if user.password_matches(&login_user.password, security.salt) => user,
+        _ => {
+            metrics.record_login(false);
+            return Err(user_problem::bad_login(is_email));
+        }
+    };
 
-    if user.pw_hash != PasswordHash::new(login_user.password.clone(), security.salt) {
-        return Err(user_problem::bad_login(is_email));
+    if !user.status.is_active() {
+        metrics.record_login(false);
+        return Err(user_problem::account_inactive(&user.status));
     }
 
     let urt = UserRoleToken::new(&user);
-    let cookie = urt.cookie(&security.jwt_keys.private)?;
+    let cookie = urt.cookie(&security.jwt_keys.private, &c.auth.cookie)?;
     cookies.add(cookie);
-    tracing::debug!(
-        "This cookie: {:?}",
-        cookies.get_pending(crate::resp::jwt::AUTH_COOKIE_NAME)
-    );
+    metrics.record_login(true);
 
     Ok(UserResponse::from(user))
 }
 
+/// Change username of the current user
+#[utoipa::path(
+    request_body(content = UsernameChangeData, content_type="application/x-www-form-urlencoded"),
+    responses(
+        (status = 400, description = "Username isn't valid or is already used", body = Problem),
+        (status = 401, description = "Missing/expired token", body = Problem),
+        (status = 200, description = "Updated user information", body = UserResponse)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[put("/user/username", data = "<change>")]
+#[tracing::instrument]
+pub async fn user_username_change(
+    change: ValidForm<UsernameChangeData>,
+    auth: UserRoleToken,
+    db: &State<Store>,
+) -> Result<Json<UserResponse>, Problem> {
+    if !db.update_username(auth.user, &change.username).await? {
+        return Err(user_problem::not_found(auth.user));
+    }
+
+    db.get_user(auth.user)
+        .await?
+        .map(|it| Json(UserResponse::from(it)))
+        .ok_or_else(|| user_problem::not_found(auth.user))
+}
+
+/// Change email of the current user
+///
+/// New email has to be verified again.
+#[utoipa::path(
+    request_body(content = EmailChangeData, content_type="application/x-www-form-urlencoded"),
+    responses(
+        (status = 400, description = "Email isn't valid or is already registered", body = Problem),
+        (status = 401, description = "Missing/expired token or wrong password", body = Problem),
+        (status = 200, description = "Updated user information", body = UserResponse)
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[put("/user/email", data = "<change>")]
+#[tracing::instrument]
+pub async fn user_email_change(
+    change: ValidForm<EmailChangeData>,
+    auth: UserRoleToken,
+    db: &State<Store>,
+    security: &State<Security>,
+    mailer: &State<Mailer>,
+) -> Result<Json<UserResponse>, Problem> {
+    let user = db
+        .get_user(auth.user)
+        .await?
+        .ok_or_else(|| user_problem::not_found(auth.user))?;
+
+    if !user.password_matches(&change.password, security.salt) {
+        return Err(Problem::typed(ProblemType::AuthBadPassword));
+    }
+
+    if user.email == change.email {
+        return Ok(Json(UserResponse::from(user)));
+    }
+
+    db.update_email(user.id, &change.email).await?;
+
+    let user = db
+        .get_user(auth.user)
+        .await?
+        .ok_or_else(|| user_problem::not_found(auth.user))?;
+
+    if let Err(err) = send_verification_mail(db, mailer, &user).await {
+        tracing::warn!(
+            "Unable to send verification mail for changed email: {}",
+            err
+        );
+    }
+
+    Ok(Json(UserResponse::from(user)))
+}
+
+/// Change password of the current user
+#[utoipa::path(
+    request_body(content = PasswordChangeData, content_type="application/x-www-form-urlencoded"),
+    responses(
+        (status = 400, description = "New password isn't valid", body = Problem),
+        (status = 401, description = "Missing/expired token or wrong current password", body = Problem),
+        (status = 200, description = "Password changed")
+    ),
+    security(
+        ("jwt" = [])
+    )
+)]
+#[put("/user/password", data = "<change>")]
+#[tracing::instrument]
+pub async fn user_password_change(
+    change: ValidForm<PasswordChangeData>,
+    auth: UserRoleToken,
+    db: &State<Store>,
+    security: &State<Security>,
+) -> Result<(), Problem> {
+    let user = db
+        .get_user(auth.user)
+        .await?
+        .ok_or_else(|| user_problem::not_found(auth.user))?;
+
+    if !user.password_matches(&change.current_password, security.salt) {
+        return Err(Problem::typed(ProblemType::AuthBadPassword)
+            .detail("Current password doesn't match.")
+            .to_owned());
+    }
+
+    let password = normalize_password(&change.new_password);
+    db.update_password(user.id, PasswordHash::new(password, security.salt))
+        .await?;
+
+    Ok(())
+}
+
+/// Request a password reset token to be sent by email
+#[utoipa::path(
+    request_body(content = PasswordResetRequestData, content_type="application/x-www-form-urlencoded"),
+    responses(
+        (status = 200, description = "Reset token was sent if the account exists")
+    )
+)]
+#[post("/user/password/reset", data = "<request>")]
+#[tracing::instrument]
+pub async fn user_password_reset_request(
+    request: ValidForm<PasswordResetRequestData>,
+    db: &State<Store>,
+    mailer: &State<Mailer>,
+) -> Result<(), Problem> {
+    let identifier = request.identifier.trim();
+    let user = match identifier.contains('@') {
+        true => db.find_user_by_email(identifier).await,
+        false => db.find_user_by_username(identifier).await,
+    }?;
+
+    // Response is the same for unknown accounts so it can't be used to look up users.
+    let user = match user {
+        Some(it) => it,
+        None => {
+            tracing::debug!("Password reset requested for unknown user");
+            return Ok(());
+        }
+    };
+
+    // Failures are only logged, as an error response would reveal the account exists.
+    let token = match db
+        .create_user_token(
+            user.id,
+            TokenPurpose::PasswordReset,
+            None,
+            password_reset_ttl(),
+        )
+        .await
+    {
+        Ok(it) => it,
+        Err(problem) => {
+            tracing::error!("Unable to create password reset token: {:?}", problem);
+            return Ok(());
+        }
+    };
+
+    let link = mailer.link(format!("/reset-password?token={}", token));
+    let sent = mailer
+        .send_template(
+            &user.email,
+            MailTemplate::PasswordReset {
+                username: &user.username,
+                link: &link,
+                expires_in: password_reset_ttl(),
+            },
+        )
+        .await;
+    if let Err(err) = sent {
+        tracing::error!("Unable to send password reset mail: {}", err);
+    }
+
+    Ok(())
+}
+
+/// Set a new password using a password reset token
+#[utoipa::path(
+    request_body(content = PasswordResetData, content_type="application/x-www-form-urlencoded"),
+    responses(
+        (status = 400, description = "Invalid token or password", body = Problem),
+        (status = 200, description = "Password changed")
+    )
+)]
+#[post("/user/password/reset/confirm", data = "<reset>")]
+#[tracing::instrument]
+pub async fn user_password_reset(
+    reset: ValidForm<PasswordResetData>,
+    db: &State<Store>,
+    security: &State<Security>,
+) -> Result<(), Problem> {
+    // Password is validated by the guard, so a bad password doesn't use up the token.
+    let token = db
+        .consume_user_token(&reset.token, TokenPurpose::PasswordReset)
+        .await?
+        .ok_or_else(user_problem::bad_token)?;
+
+    if !db
+        .update_password(
+            token.user,
+            PasswordHash::new(normalize_password(&reset.password), security.salt),
+        )
+        .await?
+    {
+        return Err(user_problem::not_found(token.user));
+    }
+    // Other links sent before the reset would still allow changing the password.
+    db.delete_user_tokens(token.user, TokenPurpose::PasswordReset)
+        .await?;
+
+    Ok(())
+}
+
 /// Delete a user
 #[utoipa::path(
     params(
@@ -179,25 +564,23 @@ pub async fn login_submit<'a>(
     )
 )]
 #[delete("/user/<id>")]
-#[tracing::instrument]
+#[tracing::instrument(skip(cookies))]
 pub async fn user_delete<'a>(
     id: Uuid,
     auth: UserRoleToken,
     cookies: &'a CookieJar<'_>,
-    db: &State<Database>,
+    db: &State<Store>,
+    c: &State<Settings>,
 ) -> Result<String, Problem> {
     if auth.user != id && auth.role < Role::Admin {
-        return Err(Problem::new_untyped(
-            Status::Unauthorized,
-            "only admin can get a list of all users",
-        ));
+        return Err(role_problem("Only admins can delete other users."));
     }
 
     let removed = db.delete_user(id).await?;
 
     if let Some(removed) = removed {
         if auth.user == id {
-            cookies.remove(CookieBuilder::new(AUTH_COOKIE_NAME, "").finish())
+            cookies.remove(removal_cookie(&c.auth.cookie))
         }
         Ok(removed.id.to_string())
     } else {
@@ -209,26 +592,30 @@ pub async fn user_delete<'a>(
 //       TESTS
 ///////////////////////
 
-#[cfg(test)]
+#[cfg(all(test, feature = "generate-security"))]
 mod user_endpoints {
-    use std::{borrow::Cow, collections::HashMap, str::FromStr};
+    use std::{borrow::Cow, str::FromStr};
 
     use crate::{
+        data::store::Store,
         data::user::{
-            db::{CreateUserDbExt, UserSignupData},
-            User, UserResponse,
+            db::{UserRepository, UserSignupData},
+            token::{email_verification_ttl, password_reset_ttl, TokenPurpose, UserTokenDbExt},
+            PasswordHash, UserResponse,
         },
+        resp::catalog::ProblemType,
         resp::jwt::{HasAuthCookie, UserRoleToken},
         role::Role,
-        security::{self, Security},
+        route::testing::{create_user, test_client, test_security},
+        security::Security,
+        settings::{CookieSettings, MailBackend, Settings},
+        BackendBuilder,
     };
-    use mongodb::Database;
     use rocket::{
         http::{ContentType, Header, Status},
         local::asynchronous::Client,
         Rocket,
     };
-    use tracing::Level;
     use uuid::Uuid;
 
     fn example_signup_data(user: impl AsRef<str>) -> UserSignupData<'static> {
@@ -255,12 +642,13 @@ mod user_endpoints {
 
     fn login_form_body(user: impl AsRef<str>) -> String {
         let data = example_signup_data(user);
-        format!("identifier={}&password={}", data.username, data.password)
+        format!("username={}&password={}", data.username, data.password)
     }
 
+    /// Backend with default settings and an empty in-memory store.
     async fn test_backend() -> Rocket<rocket::Build> {
-        crate::create(Some(Level::TRACE))
-            .await
+        BackendBuilder::new(Settings::default(), test_security(), Store::memory())
+            .build()
             .expect("unable to build test backend")
     }
 
@@ -269,7 +657,7 @@ mod user_endpoints {
         let client = Client::tracked(test_backend().await)
             .await
             .expect("invalid backend");
-        let db: &Database = client.rocket().state().unwrap();
+        let db: &Store = client.rocket().state().unwrap();
         let security: &Security = client.rocket().state().unwrap();
 
         let response: rocket::local::asynchronous::LocalResponse = client
@@ -305,15 +693,16 @@ mod user_endpoints {
     }
 
     #[rocket::async_test]
-    async fn v1_user_create_can_login() {
+    async fn v1_user_create_doesnt_login_existing_user() {
         let client = Client::tracked(test_backend().await)
             .await
             .expect("invalid backend");
-        let db: &Database = client.rocket().state().unwrap();
+        let db: &Store = client.rocket().state().unwrap();
         let security: &Security = client.rocket().state().unwrap();
 
-        let user: UserSignupData = example_signup_data("v1_user_create_can_login");
-        db.create_user(user.clone(), &security.salt, &[])
+        let user: UserSignupData = example_signup_data("v1_user_create_doesnt_login");
+        let (_, created) = db
+            .create_user(user.clone(), &security.salt, &[])
             .await
             .expect("unable to create test user");
 
@@ -326,23 +715,49 @@ mod user_endpoints {
             .body(create_form_body(user.username))
             .dispatch()
             .await;
-        assert_eq!(response.status(), Status::Ok, "an ok response");
         assert_eq!(
-            response.content_type(),
-            Some(ContentType::JSON),
-            "not a application/json response"
+            response.status(),
+            Status::BadRequest,
+            "signup with existing email must fail"
         );
         assert!(
             response
                 .get_auth_cookie(&security.jwt_keys.public)
-                .is_some(),
-            "jwt_auth cookie wasn't present"
+                .is_none(),
+            "signup logged in an existing user"
         );
 
-        let response_data: UserResponse =
-            response.into_json().await.expect("invalid response json");
+        db.delete_user(created.id)
+            .await
+            .expect("unable to delete test user");
+    }
 
-        db.delete_user(response_data.id)
+    #[rocket::async_test]
+    async fn v1_user_create_rejects_username_differing_in_case() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let (_, created) = db
+            .create_user(
+                example_signup_data("v1_user_create_case"),
+                &security.salt,
+                &[],
+            )
+            .await
+            .expect("unable to create test user");
+
+        let mut duplicate = example_signup_data("V1_USER_CREATE_CASE");
+        duplicate.email = Cow::Borrowed("other_v1_user_create_case@example.com");
+        let result = db.create_user(duplicate, &security.salt, &[]).await;
+        assert!(
+            matches!(result, Err(ref problem) if problem.body.contains_key("username")),
+            "expected a bad username problem"
+        );
+
+        db.delete_user(created.id)
             .await
             .expect("unable to delete test user");
     }
@@ -352,7 +767,7 @@ mod user_endpoints {
         let client = Client::tracked(test_backend().await)
             .await
             .expect("invalid backend");
-        let db: &Database = client.rocket().state().unwrap();
+        let db: &Store = client.rocket().state().unwrap();
         let security: &Security = client.rocket().state().unwrap();
 
         let user = example_signup_data("v1_login_submit_works");
@@ -382,12 +797,11 @@ mod user_endpoints {
             "no jwt_auth cookie present"
         );
 
-        let user_info: HashMap<String, String> =
+        let user_info: serde_json::Value =
             response.into_json().await.expect("invalid response json");
-        let response_id = user_info
-            .get("id")
-            .map(|it| Uuid::from_str(it).ok())
-            .flatten()
+        let response_id = user_info["id"]
+            .as_str()
+            .and_then(|it| Uuid::from_str(it).ok())
             .expect("invalid response user UUID");
 
         db.delete_user(response_id)
@@ -400,15 +814,16 @@ mod user_endpoints {
         let client = Client::tracked(test_backend().await)
             .await
             .expect("invalid backend");
-        let db: &Database = client.rocket().state().unwrap();
+        let db: &Store = client.rocket().state().unwrap();
         let security: &Security = client.rocket().state().unwrap();
 
         let user = example_signup_data("v1_user_delete_doesnt_work_for_unauthorized_users");
-        db.create_user(user.clone(), &security.salt, &[])
+        let (_, user) = db
+            .create_user(user, &security.salt, &[])
             .await
             .expect("unable to create user");
 
-        let delete_uri = format!("/api/v1/user/{}", user.id());
+        let delete_uri = format!("/api/v1/user/{}", user.id);
 
         let response = client.delete(&delete_uri).dispatch().await;
         assert_eq!(
@@ -417,7 +832,7 @@ mod user_endpoints {
             "expected unauthorized response"
         );
 
-        db.delete_user(user.id())
+        db.delete_user(user.id)
             .await
             .expect("unable to delete test user");
     }
@@ -427,20 +842,20 @@ mod user_endpoints {
         let client = Client::tracked(test_backend().await)
             .await
             .expect("invalid backend");
-        let db: &Database = client.rocket().state().unwrap();
+        let db: &Store = client.rocket().state().unwrap();
         let security: &Security = client.rocket().state().unwrap();
 
         let user = example_signup_data("v1_user_delete_works_for_same_user");
-        db.create_user(user.clone(), &security.salt, &[])
+        let (urt, user) = db
+            .create_user(user, &security.salt, &[])
             .await
             .expect("unable to create user");
 
-        let urt = UserRoleToken::new(&user.to_user(&security.salt));
-        assert_eq!(urt.user, user.id());
+        assert_eq!(urt.user, user.id);
         let jwt_cookie = urt
-            .cookie(&security.jwt_keys.private)
+            .cookie(&security.jwt_keys.private, &CookieSettings::default())
             .expect("unable to encode UserRoleToken cookie");
-        let delete_uri = format!("/api/v1/user/{}", user.id());
+        let delete_uri = format!("/api/v1/user/{}", user.id);
 
         let response = client
             .delete(delete_uri)
@@ -456,7 +871,7 @@ mod user_endpoints {
             .map(|it| Uuid::parse_str(&it).ok())
             .flatten();
 
-        assert_eq!(Some(user.id()), response_id, "deleted unexpected user");
+        assert_eq!(Some(user.id), response_id, "deleted unexpected user");
     }
 
     #[rocket::async_test]
@@ -464,21 +879,25 @@ mod user_endpoints {
         let client = Client::tracked(test_backend().await)
             .await
             .expect("invalid backend");
-        let db: &Database = client.rocket().state().unwrap();
+        let db: &Store = client.rocket().state().unwrap();
         let security: &Security = client.rocket().state().unwrap();
 
         let user = example_signup_data("v1_user_delete_works_for_admin_user");
-        db.create_user(user.clone(), &security.salt, &[])
+        let (_, user) = db
+            .create_user(user, &security.salt, &[])
             .await
             .expect("unable to create user");
 
-        let mut admin = User::new("admin@example.com", "admin", "admin_pass", &security.salt);
-        admin.user_role = Role::Admin;
-        let urt = UserRoleToken::new(&admin);
+        let admin = example_signup_data("v1_user_delete_admin");
+        let (urt, admin) = db
+            .create_user(admin.clone(), &security.salt, &[admin.username.to_string()])
+            .await
+            .expect("unable to create admin user");
+        assert_eq!(admin.user_role, Role::Admin);
         let jwt_cookie = urt
-            .cookie(&security.jwt_keys.private)
+            .cookie(&security.jwt_keys.private, &CookieSettings::default())
             .expect("unable to encode admin UserRoleToken cookie");
-        let delete_uri = format!("/api/v1/user/{}", user.id());
+        let delete_uri = format!("/api/v1/user/{}", user.id);
 
         let response = client
             .delete(delete_uri)
@@ -494,6 +913,579 @@ mod user_endpoints {
             .map(|it| Uuid::parse_str(&it).ok())
             .flatten();
 
-        assert_eq!(Some(user.id()), response_id, "deleted unexpected user");
+        assert_eq!(Some(user.id), response_id, "deleted unexpected user");
+
+        db.delete_user(admin.id)
+            .await
+            .expect("unable to delete admin user");
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_password_change_works() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let user = example_signup_data("v1_user_password_change_works");
+        let (urt, created) = db
+            .create_user(user.clone(), &security.salt, &[])
+            .await
+            .expect("unable to create user");
+        let jwt_cookie = urt
+            .cookie(&security.jwt_keys.private, &CookieSettings::default())
+            .expect("unable to encode UserRoleToken cookie");
+
+        let response = client
+            .put("/api/v1/user/password")
+            .header(Header::new(
+                "Content-Type",
+                "application/x-www-form-urlencoded",
+            ))
+            .cookie(jwt_cookie.clone())
+            .body("current_password=wrong_password&new_password=new_password_123")
+            .dispatch()
+            .await;
+        assert_eq!(
+            response.status(),
+            Status::Unauthorized,
+            "changed password without current password"
+        );
+
+        let response = client
+            .put("/api/v1/user/password")
+            .header(Header::new(
+                "Content-Type",
+                "application/x-www-form-urlencoded",
+            ))
+            .cookie(jwt_cookie)
+            .body(format!(
+                "current_password={}&new_password=new_password_123",
+                user.password
+            ))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok, "an ok response");
+
+        let changed = db
+            .get_user(created.id)
+            .await
+            .expect("unable to query user")
+            .expect("user missing");
+        assert_eq!(
+            changed.pw_hash,
+            PasswordHash::new("new_password_123", &security.salt)
+        );
+
+        db.delete_user(created.id)
+            .await
+            .expect("unable to delete test user");
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_password_reset_token_is_single_use() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let user = example_signup_data("v1_user_password_reset_token_is_single_use");
+        let (_, created) = db
+            .create_user(user.clone(), &security.salt, &[])
+            .await
+            .expect("unable to create user");
+
+        let token = db
+            .create_user_token(
+                created.id,
+                TokenPurpose::PasswordReset,
+                None,
+                password_reset_ttl(),
+            )
+            .await
+            .expect("unable to create reset token");
+        let earlier_token = db
+            .create_user_token(
+                created.id,
+                TokenPurpose::PasswordReset,
+                None,
+                password_reset_ttl(),
+            )
+            .await
+            .expect("unable to create reset token");
+
+        for (token, expected) in [
+            (&token, Status::Ok),
+            (&token, Status::BadRequest),
+            (&earlier_token, Status::BadRequest),
+        ] {
+            let response = client
+                .post("/api/v1/user/password/reset/confirm")
+                .header(Header::new(
+                    "Content-Type",
+                    "application/x-www-form-urlencoded",
+                ))
+                .body(format!("token={}&password=reset_password_123", token))
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), expected);
+        }
+
+        let changed = db
+            .get_user(created.id)
+            .await
+            .expect("unable to query user")
+            .expect("user missing");
+        assert_eq!(
+            changed.pw_hash,
+            PasswordHash::new("reset_password_123", &security.salt)
+        );
+
+        db.delete_user(created.id)
+            .await
+            .expect("unable to delete test user");
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_password_reset_request_hides_mail_failures() {
+        // Mail can't be stored in a directory that is actually a file.
+        let mail_dir = std::env::temp_dir().join(format!("knowmark-mail-{}", Uuid::new_v4()));
+        std::fs::write(&mail_dir, "").expect("unable to create mail dir placeholder");
+        let mut settings = Settings::default();
+        settings.mail.backend = MailBackend::File;
+        settings.mail.dir = mail_dir.clone();
+        let backend = BackendBuilder::new(settings, test_security(), Store::memory())
+            .build()
+            .expect("unable to build test backend");
+        let client = Client::tracked(backend).await.expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let user = example_signup_data("v1_password_reset_mail_fails");
+        db.create_user(user.clone(), &security.salt, &[])
+            .await
+            .expect("unable to create user");
+
+        for identifier in [user.username.as_ref(), "v1_password_reset_unknown"] {
+            let response = client
+                .post("/api/v1/user/password/reset")
+                .header(Header::new(
+                    "Content-Type",
+                    "application/x-www-form-urlencoded",
+                ))
+                .body(format!("identifier={}", identifier))
+                .dispatch()
+                .await;
+            assert_eq!(response.status(), Status::Ok, "{} was revealed", identifier);
+        }
+
+        let _ = std::fs::remove_file(mail_dir);
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_verify_email_works() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let user = example_signup_data("v1_user_verify_email_works");
+        let (_, created) = db
+            .create_user(user.clone(), &security.salt, &[])
+            .await
+            .expect("unable to create user");
+        assert!(!created.email_verified, "new users must be unverified");
+
+        let token = db
+            .create_user_token(
+                created.id,
+                TokenPurpose::EmailVerification,
+                Some(created.email.clone()),
+                email_verification_ttl(),
+            )
+            .await
+            .expect("unable to create verification token");
+
+        let response = client
+            .post("/api/v1/user/verify")
+            .header(Header::new(
+                "Content-Type",
+                "application/x-www-form-urlencoded",
+            ))
+            .body(format!("token={}", token))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok, "an ok response");
+
+        let verified = db
+            .get_user(created.id)
+            .await
+            .expect("unable to query user")
+            .expect("user missing");
+        assert!(verified.email_verified, "email wasn't marked as verified");
+
+        db.delete_user(created.id)
+            .await
+            .expect("unable to delete test user");
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_username_change_rejects_used_username() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let (urt, first) = db
+            .create_user(
+                example_signup_data("v1_username_change_first"),
+                &security.salt,
+                &[],
+            )
+            .await
+            .expect("unable to create user");
+        let (_, second) = db
+            .create_user(
+                example_signup_data("v1_username_change_second"),
+                &security.salt,
+                &[],
+            )
+            .await
+            .expect("unable to create user");
+        let jwt_cookie = urt
+            .cookie(&security.jwt_keys.private, &CookieSettings::default())
+            .expect("unable to encode UserRoleToken cookie");
+
+        let response = client
+            .put("/api/v1/user/username")
+            .header(Header::new(
+                "Content-Type",
+                "application/x-www-form-urlencoded",
+            ))
+            .cookie(jwt_cookie.clone())
+            .body(format!("username={}", second.username))
+            .dispatch()
+            .await;
+        assert_eq!(
+            response.status(),
+            Status::BadRequest,
+            "took a used username"
+        );
+
+        let response = client
+            .put("/api/v1/user/username")
+            .header(Header::new(
+                "Content-Type",
+                "application/x-www-form-urlencoded",
+            ))
+            .cookie(jwt_cookie)
+            .body("username=v1_username_changed")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok, "an ok response");
+
+        let response_data: UserResponse =
+            response.into_json().await.expect("invalid response json");
+        assert_eq!(response_data.id, first.id, "user id changed with username");
+        assert_eq!(response_data.username, "v1_username_changed");
+
+        for id in [first.id, second.id] {
+            db.delete_user(id)
+                .await
+                .expect("unable to delete test user");
+        }
+    }
+
+    #[rocket::async_test]
+    async fn v1_suspended_user_token_is_refused() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+        let db: &Store = client.rocket().state().unwrap();
+        let security: &Security = client.rocket().state().unwrap();
+
+        let (user_urt, user) = db
+            .create_user(
+                example_signup_data("v1_suspended_user"),
+                &security.salt,
+                &[],
+            )
+            .await
+            .expect("unable to create user");
+        let admin = example_signup_data("v1_suspending_admin");
+        let (admin_urt, admin) = db
+            .create_user(admin.clone(), &security.salt, &[admin.username.to_string()])
+            .await
+            .expect("unable to create admin user");
+
+        let user_cookie = user_urt
+            .cookie(&security.jwt_keys.private, &CookieSettings::default())
+            .expect("unable to encode UserRoleToken cookie");
+        let admin_cookie = admin_urt
+            .cookie(&security.jwt_keys.private, &CookieSettings::default())
+            .expect("unable to encode admin UserRoleToken cookie");
+        let user_uri = format!("/api/v1/user/{}", user.id);
+
+        let response = client
+            .get(&user_uri)
+            .cookie(user_cookie.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok, "an ok response");
+
+        let response = client
+            .put(format!("{}/status", user_uri))
+            .header(ContentType::JSON)
+            .cookie(admin_cookie)
+            .body(r#"{"status":{"status":"Suspended"},"reason":"test suspension"}"#)
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok, "an ok response");
+
+        let response = client.get(&user_uri).cookie(user_cookie).dispatch().await;
+        assert_eq!(
+            response.status(),
+            Status::Forbidden,
+            "token of suspended user was accepted"
+        );
+
+        for id in [user.id, admin.id] {
+            db.delete_user(id)
+                .await
+                .expect("unable to delete test user");
+        }
+    }
+
+    #[rocket::async_test]
+    async fn v1_guard_failures_return_localized_problems() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+
+        let response = client
+            .get("/api/v1/user")
+            .header(Header::new("Accept-Language", "hr-HR, en;q=0.5"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(
+            response.content_type(),
+            Some(ContentType::new("application", "problem+json")),
+            "not a problem response"
+        );
+        assert_eq!(response.headers().get_one("Content-Language"), Some("hr"));
+
+        let body: serde_json::Value = response.into_json().await.expect("invalid problem json");
+        assert_eq!(body["type"], ProblemType::AuthMissingToken.uri());
+        assert_eq!(body["title"], "Nedostaje token za autentifikaciju.");
+
+        let response = client.get("/api/v1/missing").dispatch().await;
+        assert_eq!(response.status(), Status::NotFound);
+        let body: serde_json::Value = response.into_json().await.expect("invalid problem json");
+        assert_eq!(body["type"], ProblemType::RequestNotFound.uri());
+    }
+
+    #[rocket::async_test]
+    async fn v1_problems_carry_request_id() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+
+        let response = client
+            .get("/api/v1/user")
+            .header(Header::new("X-Request-Id", "lb-42"))
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Unauthorized);
+        assert_eq!(response.headers().get_one("X-Request-Id"), Some("lb-42"));
+        let body: serde_json::Value = response.into_json().await.expect("invalid problem json");
+        assert_eq!(body["instance"], "urn:knowmark:request:lb-42");
+
+        let response = client
+            .get("/api/v1/user")
+            .header(Header::new("X-Request-Id", "forged\tid"))
+            .dispatch()
+            .await;
+        let generated = response.headers().get_one("X-Request-Id");
+        assert!(generated.map_or(false, |it| Uuid::parse_str(it).is_ok()));
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_create_lists_invalid_fields() {
+        let client = Client::tracked(test_backend().await)
+            .await
+            .expect("invalid backend");
+
+        let response = client
+            .post("/api/v1/user")
+            .header(Header::new(
+                "Content-Type",
+                "application/x-www-form-urlencoded",
+            ))
+            .body("email=not-an-email&username=abc&password=")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let body: serde_json::Value = response.into_json().await.expect("invalid problem json");
+        assert_eq!(body["type"], ProblemType::RequestInvalidFields.uri());
+        assert_eq!(
+            body["errors"],
+            serde_json::json!([
+                { "field": "email", "code": "bad_format" },
+                { "field": "username", "code": "too_short", "limit": 5 },
+                { "field": "password", "code": "required" },
+            ])
+        );
+
+        let response = client
+            .post("/api/v1/user")
+            .header(Header::new(
+                "Content-Type",
+                "application/x-www-form-urlencoded",
+            ))
+            .body("username=abcdef")
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let body: serde_json::Value = response.into_json().await.expect("invalid problem json");
+        let fields: Vec<&str> = body["errors"]
+            .as_array()
+            .expect("missing errors")
+            .iter()
+            .filter_map(|it| it["field"].as_str())
+            .collect();
+        assert!(fields.contains(&"email") && fields.contains(&"password"));
+    }
+
+    fn usernames(page: &serde_json::Value) -> Vec<&str> {
+        page["items"]
+            .as_array()
+            .expect("missing page items")
+            .iter()
+            .map(|it| it["username"].as_str().expect("missing username"))
+            .collect()
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_list_pages_with_total() {
+        let client = test_client().await;
+        let (_, cookie) = create_user(&client, "v1_user_list_viewer", Role::Normal).await;
+        for name in ["v1_user_list_1", "v1_user_list_2", "v1_user_list_3"] {
+            create_user(&client, name, Role::Normal).await;
+        }
+
+        let response = client
+            .get("/api/v1/user?length=3")
+            .cookie(cookie.clone())
+            .dispatch()
+            .await;
+        assert_eq!(response.status(), Status::Ok, "an ok response");
+        let first: serde_json::Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(
+            usernames(&first),
+            ["v1_user_list_1", "v1_user_list_2", "v1_user_list_3"]
+        );
+        assert_eq!(first["total"], 4);
+        let next = first["next"].as_str().expect("missing next page cursor");
+
+        let response = client
+            .get(format!("/api/v1/user?length=3&from={}", next))
+            .cookie(cookie.clone())
+            .dispatch()
+            .await;
+        let second: serde_json::Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(usernames(&second), ["v1_user_list_viewer"]);
+        assert!(second["next"].is_null());
+        let prev = second["prev"]
+            .as_str()
+            .expect("missing previous page cursor");
+
+        let response = client
+            .get(format!("/api/v1/user?length=3&before={}", prev))
+            .cookie(cookie)
+            .dispatch()
+            .await;
+        let back: serde_json::Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(usernames(&back), usernames(&first));
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_list_hides_private_fields_from_members() {
+        let client = test_client().await;
+        let (_, member) = create_user(&client, "v1_user_list_member", Role::Normal).await;
+        let (_, admin) = create_user(&client, "v1_user_list_admin", Role::Admin).await;
+
+        let response = client.get("/api/v1/user").cookie(member).dispatch().await;
+        assert_eq!(response.status(), Status::Ok, "an ok response");
+        let page: serde_json::Value = response.into_json().await.expect("invalid response json");
+        for user in page["items"].as_array().expect("missing page items") {
+            assert!(user.get("email").is_none() && user.get("status").is_none());
+        }
+
+        let response = client.get("/api/v1/user").cookie(admin).dispatch().await;
+        let page: serde_json::Value = response.into_json().await.expect("invalid response json");
+        for user in page["items"].as_array().expect("missing page items") {
+            assert!(user["email"].is_string() && !user["status"].is_null());
+        }
+
+        let (_, none) = create_user(&client, "v1_user_list_none", Role::None).await;
+        let response = client.get("/api/v1/user").cookie(none).dispatch().await;
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[rocket::async_test]
+    async fn v1_user_list_filters_by_role_and_search() {
+        let client = test_client().await;
+        let (_, admin) = create_user(&client, "v1_user_list_admin", Role::Admin).await;
+        for name in ["author_anna", "author_bob", "author_cleo"] {
+            create_user(&client, name, Role::Author).await;
+        }
+        create_user(&client, "member_anna", Role::Normal).await;
+
+        let response = client
+            .get("/api/v1/user?role=Author&order=Desc")
+            .cookie(admin.clone())
+            .dispatch()
+            .await;
+        let page: serde_json::Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(
+            usernames(&page),
+            ["author_cleo", "author_bob", "author_anna"]
+        );
+        assert_eq!(page["total"], 3);
+
+        // Searched text is matched literally and with any letter case
+        let response = client
+            .get("/api/v1/user?search=ANNA&length=1")
+            .cookie(admin.clone())
+            .dispatch()
+            .await;
+        let first: serde_json::Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(usernames(&first), ["author_anna"]);
+        assert_eq!(first["total"], 2);
+        let next = first["next"].as_str().expect("missing next page cursor");
+
+        let response = client
+            .get(format!("/api/v1/user?search=ANNA&length=1&from={}", next))
+            .cookie(admin.clone())
+            .dispatch()
+            .await;
+        let second: serde_json::Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(usernames(&second), ["member_anna"]);
+        assert!(second["next"].is_null());
+
+        let response = client
+            .get("/api/v1/user?search=a.*a")
+            .cookie(admin)
+            .dispatch()
+            .await;
+        let page: serde_json::Value = response.into_json().await.expect("invalid response json");
+        assert_eq!(page["total"], 0);
     }
 }
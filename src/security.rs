@@ -1,6 +1,10 @@
 use std::convert::TryInto;
-use std::path::PathBuf;
-use std::{env, fs};
+use std::fs;
+use std::path::Path;
+
+use jsonwebtoken::{DecodingKey, EncodingKey};
+
+use crate::settings::SecuritySettings;
 
 const PASSWORD_SALT: &'static str = "password.salt";
 const USER_AUTH_PUBLIC: &'static str = "user_auth.pem.pub";
@@ -8,26 +12,112 @@ const USER_AUTH_PRIVATE: &'static str = "user_auth.pem";
 
 pub type Salt = [u8; 16];
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct KeySet {
     pub public: Vec<u8>,
     pub private: Vec<u8>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct Security {
     pub salt: Salt,
     pub jwt_keys: KeySet,
 }
 
-#[inline]
-fn security_dir() -> PathBuf {
-    PathBuf::from(env::var("SECURITY_DIR").unwrap_or("./security".to_string()))
+impl std::fmt::Debug for KeySet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "KeySet")
+    }
+}
+
+impl std::fmt::Debug for Security {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Security")
+    }
+}
+
+impl KeySet {
+    /// Checks the keys can be used for signing and verifying tokens.
+    pub fn check(&self) -> Result<(), jsonwebtoken::errors::Error> {
+        EncodingKey::from_rsa_pem(&self.private)?;
+        DecodingKey::from_rsa_pem(&self.public)?;
+        Ok(())
+    }
+
+    /// Generates a new JWT signing key pair with an RSA key of `bits` size, without storing it.
+    #[cfg(feature = "generate-security")]
+    pub fn random(bits: usize) -> KeySet {
+        use rsa::pkcs1::{EncodeRsaPrivateKey, LineEnding};
+        use rsa::pkcs8::EncodePublicKey;
+
+        let mut rng = rand::thread_rng();
+        let rsa_sk =
+            rsa::RsaPrivateKey::new(&mut rng, bits).expect("unable to generate a private RSA key");
+
+        tracing::info!("Creating PS256 private key...");
+        let private = rsa_sk
+            .to_pkcs1_pem(LineEnding::LF)
+            .expect("unable to generate PS256 private key")
+            .to_string()
+            .into_bytes();
+
+        tracing::info!("Creating PS256 public key...");
+        let public = rsa_sk
+            .to_public_key()
+            .to_public_key_der()
+            .expect("unable to generate PS256 public key")
+            .to_pem("JWT public key", LineEnding::LF)
+            .expect("unable to crate a valid UTF8 pem key")
+            .into_bytes();
+
+        KeySet { public, private }
+    }
+
+    /// Generates a new JWT signing key pair and writes it into `dir`, replacing existing keys.
+    ///
+    /// Tokens signed with replaced keys are no longer accepted.
+    #[cfg(feature = "generate-security")]
+    pub fn generate(dir: &Path) -> KeySet {
+        tracing::info!("Generating a private RSA key. This will take a few minutes...");
+        let keys = KeySet::random(4096);
+
+        fs::write(dir.join(USER_AUTH_PRIVATE), keys.private.as_slice())
+            .expect("unable to write user auth private key");
+        fs::write(dir.join(USER_AUTH_PUBLIC), keys.public.as_slice())
+            .expect("unable to write user auth public key");
+
+        tracing::info!("Done generating JWT keys.");
+
+        keys
+    }
 }
 
 impl Security {
+    /// Files security information is stored in, relative to its directory.
+    pub const FILES: &'static [&'static str] =
+        &[PASSWORD_SALT, USER_AUTH_PUBLIC, USER_AUTH_PRIVATE];
+
+    /// Random salt and JWT keys which aren't stored anywhere, for tests and embedded backends.
+    ///
+    /// Uses a smaller key than [`KeySet::generate`] as tokens signed with it don't outlive the
+    /// process.
+    #[cfg(feature = "generate-security")]
+    pub fn ephemeral() -> Security {
+        Security {
+            salt: rand::random(),
+            jwt_keys: KeySet::random(2048),
+        }
+    }
+
+    /// Loads security information from the default directory.
     pub fn load() -> Security {
-        let dir = security_dir();
+        Security::load_from(&SecuritySettings::default().dir)
+    }
+
+    /// Loads security information from `dir`, generating missing parts if `generate-security`
+    /// feature is enabled.
+    pub fn load_from(dir: &Path) -> Security {
+        let dir = dir.to_path_buf();
 
         if cfg!(feature = "generate-security") {
             fs::create_dir_all(dir.clone())
@@ -65,43 +155,10 @@ impl Security {
             }
             #[cfg(feature = "generate-security")]
             _ => {
-                use rsa::pkcs1::{EncodeRsaPrivateKey, LineEnding};
-                use rsa::pkcs8::EncodePublicKey;
-
                 tracing::info!(
                     "Unable to load private and/or public user auth key(s). Generating a new pair."
                 );
-
-                tracing::info!("Generating a private RSA key. This will take a few minutes...");
-                let mut rng = rand::thread_rng();
-                let rsa_sk = rsa::RsaPrivateKey::new(&mut rng, 4096)
-                    .expect("unable to generate a private RSA key");
-
-                tracing::info!("Creating PS256 private key...");
-                let private = rsa_sk
-                    .to_pkcs1_pem(LineEnding::LF)
-                    .expect("unable to generate PS256 private key")
-                    .to_string()
-                    .into_bytes();
-
-                fs::write(dir.join(USER_AUTH_PRIVATE), private.as_slice())
-                    .expect("unable to write user auth private key");
-
-                tracing::info!("Creating PS256 public key...");
-                let public = rsa_sk
-                    .to_public_key()
-                    .to_public_key_der()
-                    .expect("unable to generate PS256 public key")
-                    .to_pem("JWT public key", LineEnding::LF)
-                    .expect("unable to crate a valid UTF8 pem key")
-                    .into_bytes();
-
-                fs::write(dir.join(USER_AUTH_PUBLIC), public.as_slice())
-                    .expect("unable to write user auth public key");
-
-                tracing::info!("Done generating JWT keys.");
-
-                KeySet { public, private }
+                KeySet::generate(&dir)
             }
             #[cfg(not(feature = "generate-security"))]
             _ => {
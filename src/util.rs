@@ -19,3 +19,15 @@ pub fn find_first_subpath<P: AsRef<Path>, F: Fn(&Path) -> bool>(
         .map(|(b, a)| a.join(b))
         .find(|it: &PathBuf| search(&it))
 }
+
+/// Escapes `text` so it's matched literally when used in a regular expression.
+pub fn escape_regex(text: impl AsRef<str>) -> String {
+    let mut result = String::with_capacity(text.as_ref().len());
+    for c in text.as_ref().chars() {
+        if "\\.+*?()|[]{}^$#&-~".contains(c) {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}